@@ -0,0 +1,136 @@
+//!Scores ciphertexts by how unlikely their repeated blocks would be if they
+//!weren't ECB, so a large file of them can be ranked.
+
+use std::collections::HashMap;
+
+///How strongly a ciphertext looks like ECB
+#[derive(Debug)]
+pub struct Score {
+    ///Index of the ciphertext this is for
+    pub index: usize,
+    ///Number of pairs of equal blocks
+    pub repeats: usize,
+    ///Natural log of the chance of at least that many pairs in random data
+    pub ln_p_value: f64,
+    ///Byte offsets of blocks which are equal to each other, one group per
+    ///distinct block
+    pub repeated: Vec<Vec<usize>>,
+}
+
+///Natural log of the chance of a Poisson variable with mean `mean` being at
+///least `count`. This is done in logs, since for real block sizes the chance
+///is far too small for an f64.
+fn ln_poisson_tail(mean: f64, count: usize) -> f64 {
+    if count == 0 {
+        return 0.0;
+    }
+    let ln_term = |i: usize| {
+        -mean + i as f64 * mean.ln()
+            - (1..i + 1).map(|n| (n as f64).ln()).sum::<f64>()
+    };
+    if count as f64 <= mean {
+        //the chance isn't small, and summing upwards would overflow
+        let below = (0..count).map(|i| ln_term(i).exp()).sum::<f64>();
+        return (1.0 - below).max(0.0).ln();
+    }
+    //P(X >= k) = e^-mean * mean^k / k! * sum over j of mean^j k! / (k + j)!
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    let mut j = 1;
+    while term > sum * 1e-17 {
+        term *= mean / (count + j) as f64;
+        sum += term;
+        j += 1;
+    }
+    ln_term(count) + sum.ln()
+}
+
+///Scores `ciphertext` by its repeated `block_size` blocks. Random blocks
+///collide in pairs about as a Poisson variable whose mean is the number of
+///pairs over 2^(8 * block_size).
+pub fn score(index: usize, ciphertext: &[u8], block_size: usize) -> Score {
+    let mut offsets: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    let mut nblocks = 0usize;
+    for (idx, block) in ciphertext.chunks(block_size).enumerate() {
+        if block.len() == block_size {
+            offsets.entry(block).or_default().push(idx * block_size);
+            nblocks += 1;
+        }
+    }
+    let mut repeated = offsets.into_values()
+                              .filter(|offsets| offsets.len() > 1)
+                              .collect::<Vec<_>>();
+    repeated.sort();
+    let repeats = repeated.iter()
+                          .map(|offsets| offsets.len() * (offsets.len() - 1) / 2)
+                          .sum();
+    //2^-(8 * block_size) underflows past 127 byte blocks, so go through logs
+    let pairs = (nblocks * nblocks.saturating_sub(1) / 2) as f64;
+    let mean = (pairs.ln() - 8.0 * block_size as f64 * 2f64.ln()).exp();
+    Score {
+        index,
+        repeats,
+        ln_p_value: ln_poisson_tail(mean, repeats),
+        repeated,
+    }
+}
+
+///Scores every one of `ciphertexts`, most likely ECB first
+pub fn rank(ciphertexts: &[Vec<u8>], block_size: usize) -> Vec<Score> {
+    let mut scores = ciphertexts.iter().enumerate()
+                                .map(|(idx, c)| score(idx, c, block_size))
+                                .collect::<Vec<_>>();
+    scores.sort_by(|a, b| {
+        a.ln_p_value.partial_cmp(&b.ln_p_value).unwrap()
+         .then(a.index.cmp(&b.index))
+    });
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn ln_poisson_tail() {
+        assert_eq!(super::ln_poisson_tail(0.5, 0), 0.0);
+        //P(X >= 1) = 1 - e^-mean
+        let expected = (1.0 - (-0.5f64).exp()).ln();
+        assert!((super::ln_poisson_tail(0.5, 1) - expected).abs() < 1e-12);
+        //P(X >= 2) = 1 - e^-mean (1 + mean)
+        let expected = (1.0 - (-3.0f64).exp() * 4.0).ln();
+        assert!((super::ln_poisson_tail(3.0, 2) - expected).abs() < 1e-12);
+        //tiny means don't underflow
+        let ln_p = super::ln_poisson_tail(1e-30, 3);
+        assert!((ln_p - (1e-90f64.ln() - 6f64.ln())).abs() < 1e-9);
+        //nor do huge ones overflow
+        assert!(super::ln_poisson_tail(1e6, 10).abs() < 1e-12);
+    }
+
+    #[test]
+    fn score() {
+        let mut ciphertext = (0..80u8).collect::<Vec<u8>>();
+        ciphertext[48..64].copy_from_slice(&(0..16u8).collect::<Vec<u8>>());
+        ciphertext[64..80].copy_from_slice(&(0..16u8).collect::<Vec<u8>>());
+        let score = super::score(3, &ciphertext, 16);
+        assert_eq!(score.index, 3);
+        assert_eq!(score.repeats, 3);
+        assert_eq!(score.repeated, vec![vec![0, 48, 64]]);
+        assert!(score.ln_p_value < -250.0);
+
+        let score = super::score(0, &(0..80u8).collect::<Vec<u8>>(), 16);
+        assert_eq!(score.repeats, 0);
+        assert_eq!(score.ln_p_value, 0.0);
+        assert!(score.repeated.is_empty());
+    }
+
+    #[test]
+    fn rank() {
+        let distinct = (0..64u8).collect::<Vec<u8>>();
+        let mut once = distinct.clone();
+        once[16..32].copy_from_slice(&distinct[..16]);
+        let mut twice = once.clone();
+        twice[48..64].copy_from_slice(&distinct[..16]);
+        let ranked = super::rank(&[distinct, twice, once], 16);
+        let order = ranked.iter().map(|s| s.index).collect::<Vec<_>>();
+        assert_eq!(order, vec![1, 2, 0]);
+    }
+}
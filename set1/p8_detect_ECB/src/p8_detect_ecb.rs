@@ -1,39 +1,13 @@
-#![feature(rustc_private)]
-#![feature(core)]
-#![feature(io)]
-#![feature(std_misc)]
+extern crate rustc_serialize as serialize;
 
-extern crate serialize;
+mod detect;
 
-fn detect_ecb(ciphertext: &[u8], blocksize: u16) -> bool {
-    use std::collections::HashMap;
-    use std::collections::hash_map::Entry::{Occupied, Vacant};
-    use std::num::Int;
-    use std::slice::SliceExt;
+///Chance of random data repeating blocks at least as much, below which a
+///ciphertext is taken to be ECB
+const SIGNIFICANCE: f64 = 1e-6;
 
-    //get blocks
-    let blocks = ciphertext.chunks(blocksize as usize);
-    let nblocks = blocks.len();
-    
-    //get number of repetitions
-    let mut repetitions = HashMap::new();
-    for block in blocks {
-        match repetitions.entry(block) {
-            Vacant(entry)   => {entry.insert(0);},
-            Occupied(mut entry) => *entry.get_mut() += 1,
-        }
-    }
-    let mut nrepetitions = 0;
-    for value in repetitions.values(){
-        nrepetitions += *value;
-    }
-    
-    //calculate probablility
-    let expected = 
-        (nblocks.pow(2) as f32)/(2.pow(blocksize as u32) as f32);
-    
-    nrepetitions as f32 > expected
-}
+///Number of candidates listed when no count is given
+const DEFAULT_TOP: usize = 10;
 
 fn read_hex_lines(filename: &str) -> Result<Vec<Vec<u8>>,String> {
     use std::io::prelude::*;
@@ -41,24 +15,22 @@ fn read_hex_lines(filename: &str) -> Result<Vec<Vec<u8>>,String> {
     use std::fs::File;
     use serialize::hex::FromHex;
 
-    let mut buffer;
-    match File::open(filename) {
-        Ok(file) => buffer=BufReader::new(file),
+    let buffer = match File::open(filename) {
+        Ok(file) => BufReader::new(file),
         Err(e)   => {
             return Err(format!("Couldn't open {}: {}", filename, e));
         }
-    }
+    };
     
     let hex_lines = buffer.lines();
     let mut lines = Vec::new();
     for line_res in hex_lines {
-        let line;
-        match line_res {
-            Ok(s)  => line = s,
+        let line = match line_res {
+            Ok(s)  => s,
             Err(e) => {
                 return Err(format!("Couldn't read {}: {}", filename, e));
             }
-        }
+        };
         match line[..].from_hex() {
             Ok(v) => lines.push(v),
             Err(e) => {
@@ -70,27 +42,42 @@ fn read_hex_lines(filename: &str) -> Result<Vec<Vec<u8>>,String> {
 }
 
 fn main() {
-    use std::iter::IteratorExt;
-    use serialize::hex::ToHex;
-
     let args : Vec<String> = std::env::args().collect();
-    if args.len()!=2 {
-        println!("Invalid number of args!");
+    if args.len() < 2 || args.len() > 3 {
+        println!("Usage: {} <file> [number of candidates]", args[0]);
         return;
     }
     let filename = &args[1][..];
-    let lines;
-    match read_hex_lines(filename) {
-        Ok(ls)  => lines = ls,
+    let top = match args.get(2) {
+        Some(n) => match n.parse() {
+            Ok(n)  => n,
+            Err(e) => {
+                println!("Invalid number of candidates {}: {}", n, e);
+                return;
+            }
+        },
+        None => DEFAULT_TOP,
+    };
+    let lines = match read_hex_lines(filename) {
+        Ok(ls)  => ls,
         Err(e) => {
             println!("Error: {}", e);
             return;
         }
-    }
+    };
 
-    for (line_num, line) in lines.into_iter().enumerate(){
-        if detect_ecb(&line[..], 16){
-            println!("{}: {}", line_num, line.to_hex());
+    for score in detect::rank(&lines[..], 16).iter().take(top) {
+        //p-values underflow, so give them as powers of 10
+        println!("line {}: {} repeated pairs, p = 10^{:.1}{}",
+                 score.index + 1, score.repeats,
+                 score.ln_p_value / 10f64.ln(),
+                 if score.ln_p_value < SIGNIFICANCE.ln() {
+                     " (ECB)"
+                 } else {
+                     ""
+                 });
+        for offsets in score.repeated.iter() {
+            println!("    equal blocks at {:?}", offsets);
         }
     }
 }
@@ -0,0 +1,127 @@
+#![allow(dead_code)]
+extern crate crypto;
+extern crate rand;
+
+mod aes;
+mod image;
+mod leakage;
+use image::Image;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Ecb,
+    Cbc,
+}
+
+fn random_block() -> [u8; 16] {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut block = [0u8; 16];
+    for el in block.iter_mut() {
+        *el = rng.gen::<u8>();
+    }
+    block
+}
+
+//encrypts `pixels` in `mode` under `key`, keeping the length the same
+fn encrypt_pixels(pixels: &[u8], mode: Mode, key: &[u8], iv: &[u8])
+        -> Vec<u8> {
+    let mut encrypted = match mode {
+        Mode::Ecb => aes::aes_ecb_encrypt(pixels, key),
+        Mode::Cbc => aes::aes_cbc_encrypt(pixels, key, iv),
+    };
+    //the padding has nowhere to go in the image, so it's dropped
+    encrypted.truncate(pixels.len());
+    encrypted
+}
+
+fn read_image(filename: &str) -> Result<Image, String> {
+    use std::io::prelude::*;
+    use std::fs::File;
+    let mut f = match File::open(filename) {
+        Ok(file) => file,
+        Err(e)   => return Err(format!("Couldn't open {}: {}", filename, e)),
+    };
+    let mut contents = Vec::new();
+    if let Err(e) = f.read_to_end(&mut contents) {
+        return Err(format!("Couldn't read {}: {}", filename, e));
+    }
+    Image::parse(&contents)
+        .map_err(|e| format!("Couldn't parse {}: {}", filename, e))
+}
+
+fn write_image(filename: &str, image: &Image) -> Result<(), String> {
+    use std::io::prelude::*;
+    use std::fs::File;
+    let mut f = match File::create(filename) {
+        Ok(file) => file,
+        Err(e)   => return Err(format!("Couldn't create {}: {}", filename, e)),
+    };
+    f.write_all(&image.to_bytes())
+     .map_err(|e| format!("Couldn't write {}: {}", filename, e))
+}
+
+fn main() {
+    let args : Vec<String> = std::env::args().collect();
+    let mode = match args.get(3).map(|s| &s[..]) {
+        _ if args.len() < 3 || args.len() > 4 => None,
+        None | Some("ecb") => Some(Mode::Ecb),
+        Some("cbc") => Some(Mode::Cbc),
+        Some(_) => None,
+    };
+    let mode = match mode {
+        Some(mode) => mode,
+        None => {
+            println!("Usage: {} <input.ppm|bmp> <output> [ecb|cbc]", args[0]);
+            return;
+        }
+    };
+    let mut image = match read_image(&args[1]) {
+        Ok(image) => image,
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    };
+    let key = random_block();
+    let encrypted = encrypt_pixels(&image.pixels, mode, &key, &random_block());
+    println!("{:?} {}x{}, {:?}", image.format, image.width, image.height,
+             mode);
+    println!("Repeated blocks: {:.1}% of plaintext, {:.1}% of ciphertext",
+             100.0 * leakage::repeated_fraction(&image.pixels, 16),
+             100.0 * leakage::repeated_fraction(&encrypted, 16));
+    image.pixels = encrypted;
+    if let Err(e) = write_image(&args[2], &image) {
+        println!("Error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Image;
+    use leakage::repeated_fraction;
+    use super::{encrypt_pixels, Mode};
+
+    #[test]
+    fn ecb_leaks() {
+        //64x64, in four flat quadrants
+        let mut data = b"P6 64 64 255\n".to_vec();
+        for y in 0..64 {
+            for x in 0..64 {
+                let quadrant = (y / 32 * 2 + x / 32) as u8;
+                data.extend_from_slice(&[quadrant * 60, 255 - quadrant, 0]);
+            }
+        }
+        let image = Image::parse(&data).unwrap();
+        let key = b"yellow submarine";
+        let iv = [0; 16];
+        let ecb = encrypt_pixels(&image.pixels, Mode::Ecb, key, &iv);
+        let cbc = encrypt_pixels(&image.pixels, Mode::Cbc, key, &iv);
+        assert_eq!(ecb.len(), image.pixels.len());
+        assert_eq!(cbc.len(), image.pixels.len());
+        assert_eq!(repeated_fraction(&ecb, 16),
+                   repeated_fraction(&image.pixels, 16));
+        assert!(repeated_fraction(&ecb, 16) > 0.9);
+        assert_eq!(repeated_fraction(&cbc, 16), 0.0);
+    }
+}
@@ -0,0 +1,234 @@
+//!Uncompressed PPM and BMP images, split into the pixel data and the bytes
+//!around it, so the pixels can be changed while the file stays viewable.
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    ///Binary (P6) PPM
+    Ppm,
+    ///BMP with uncompressed pixels
+    Bmp,
+}
+
+#[derive(Debug)]
+pub struct Image {
+    pub format: Format,
+    pub width: usize,
+    pub height: usize,
+    ///Everything before the pixel data
+    pub header: Vec<u8>,
+    ///Pixel data, including any row padding
+    pub pixels: Vec<u8>,
+    ///Everything after the pixel data
+    pub trailer: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ImageError {
+    ///The file is neither a PPM nor a BMP
+    UnknownFormat,
+    ///The header is malformed, for the given reason
+    BadHeader(&'static str),
+    ///The pixel data is compressed
+    Compressed,
+    ///The file ends before the pixel data does
+    Truncated,
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ImageError::UnknownFormat =>
+                write!(formatter, "Not a binary PPM or a BMP"),
+            ImageError::BadHeader(reason) =>
+                write!(formatter, "Bad header: {}", reason),
+            ImageError::Compressed =>
+                write!(formatter, "Compressed images aren't supported"),
+            ImageError::Truncated =>
+                write!(formatter, "File ends inside the pixel data"),
+        }
+    }
+}
+
+impl Error for ImageError {
+    fn description(&self) -> &str {
+        match *self {
+            ImageError::UnknownFormat => "Not a binary PPM or a BMP",
+            ImageError::BadHeader(_) => "Bad header",
+            ImageError::Compressed => "Compressed images aren't supported",
+            ImageError::Truncated => "File ends inside the pixel data",
+        }
+    }
+}
+
+impl Image {
+    pub fn parse(data: &[u8]) -> Result<Image, ImageError> {
+        if data.starts_with(b"P6") {
+            parse_ppm(data)
+        } else if data.starts_with(b"BM") {
+            parse_bmp(data)
+        } else {
+            Err(ImageError::UnknownFormat)
+        }
+    }
+
+    ///Gives the whole file back
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.clone();
+        bytes.extend_from_slice(&self.pixels);
+        bytes.extend_from_slice(&self.trailer);
+        bytes
+    }
+
+    fn split(format: Format, width: usize, height: usize, data: &[u8],
+             start: usize, len: usize) -> Result<Image, ImageError> {
+        if start > data.len() || data.len() - start < len {
+            return Err(ImageError::Truncated);
+        }
+        Ok(Image {
+            format,
+            width,
+            height,
+            header: data[..start].to_vec(),
+            pixels: data[start..start + len].to_vec(),
+            trailer: data[start + len..].to_vec(),
+        })
+    }
+}
+
+//reads the decimal number after any whitespace and comments at `*pos`
+fn ppm_number(data: &[u8], pos: &mut usize) -> Result<usize, ImageError> {
+    loop {
+        match data.get(*pos) {
+            Some(byte) if byte.is_ascii_whitespace() => *pos += 1,
+            Some(&b'#') => {
+                while data.get(*pos).is_some_and(|&byte| byte != b'\n') {
+                    *pos += 1;
+                }
+            }
+            Some(_) => break,
+            None => return Err(ImageError::Truncated),
+        }
+    }
+    let start = *pos;
+    let mut number = 0usize;
+    while let Some(&byte) = data.get(*pos) {
+        if !byte.is_ascii_digit() {
+            break;
+        }
+        number = number.checked_mul(10)
+                       .and_then(|n| n.checked_add((byte - b'0') as usize))
+                       .ok_or(ImageError::BadHeader("number too large"))?;
+        *pos += 1;
+    }
+    if *pos == start {
+        return Err(ImageError::BadHeader("expected a number"));
+    }
+    Ok(number)
+}
+
+fn parse_ppm(data: &[u8]) -> Result<Image, ImageError> {
+    let mut pos = 2;
+    let width = ppm_number(data, &mut pos)?;
+    let height = ppm_number(data, &mut pos)?;
+    let max = ppm_number(data, &mut pos)?;
+    if max == 0 || max > 65535 {
+        return Err(ImageError::BadHeader("maximum value out of range"));
+    }
+    //a single whitespace byte separates the header from the pixels
+    match data.get(pos) {
+        Some(byte) if byte.is_ascii_whitespace() => pos += 1,
+        Some(_) => return Err(ImageError::BadHeader("expected whitespace")),
+        None => return Err(ImageError::Truncated),
+    }
+    let sample_size = if max < 256 { 1 } else { 2 };
+    let len = width.checked_mul(height)
+                   .and_then(|n| n.checked_mul(3 * sample_size))
+                   .ok_or(ImageError::BadHeader("image too large"))?;
+    Image::split(Format::Ppm, width, height, data, pos, len)
+}
+
+fn le_u32(data: &[u8], at: usize) -> Result<u32, ImageError> {
+    match data.get(at..at + 4) {
+        Some(bytes) => Ok(bytes.iter().rev()
+                               .fold(0, |acc, &byte| acc << 8 | byte as u32)),
+        None => Err(ImageError::Truncated),
+    }
+}
+
+fn parse_bmp(data: &[u8]) -> Result<Image, ImageError> {
+    //BI_RGB, BI_BITFIELDS and BI_ALPHABITFIELDS all leave pixels as they are
+    const UNCOMPRESSED: [u32; 3] = [0, 3, 6];
+    let start = le_u32(data, 10)? as usize;
+    if le_u32(data, 14)? < 40 {
+        return Err(ImageError::BadHeader("unsupported info header"));
+    }
+    let width = le_u32(data, 18)? as i32;
+    //a negative height means the rows go top down
+    let height = (le_u32(data, 22)? as i32).unsigned_abs() as usize;
+    let bits_per_pixel = le_u32(data, 28)? as u16 as usize;
+    if width <= 0 || bits_per_pixel == 0 {
+        return Err(ImageError::BadHeader("empty pixels"));
+    }
+    if !UNCOMPRESSED.contains(&le_u32(data, 30)?) {
+        return Err(ImageError::Compressed);
+    }
+    let width = width as usize;
+    //rows are padded to a multiple of four bytes
+    let row_size = (bits_per_pixel * width).div_ceil(32) * 4;
+    let len = row_size.checked_mul(height)
+                      .ok_or(ImageError::BadHeader("image too large"))?;
+    Image::split(Format::Bmp, width, height, data, start, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Format, Image, ImageError};
+
+    #[test]
+    fn ppm() {
+        let mut data = b"P6\n# a comment\n2 1\n255\n".to_vec();
+        data.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let image = Image::parse(&data).unwrap();
+        assert_eq!(image.format, Format::Ppm);
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.header, b"P6\n# a comment\n2 1\n255\n".to_vec());
+        assert_eq!(image.pixels, vec![1, 2, 3, 4, 5, 6]);
+        assert!(image.trailer.is_empty());
+        assert_eq!(image.to_bytes(), data);
+
+        data.pop();
+        assert_eq!(Image::parse(&data).err(), Some(ImageError::Truncated));
+    }
+
+    #[test]
+    fn bmp() {
+        //3x2, 24 bits per pixel, so each row is 9 bytes padded to 12
+        let mut data = b"BM".to_vec();
+        let header = [0u32, 0, 54, 40, 3, (-2i32) as u32, 1 | 24 << 16, 0];
+        for field in header.iter() {
+            data.extend((0..4).map(|n| (field >> (8 * n)) as u8));
+        }
+        data.extend(vec![0; 54 - data.len()]);
+        data.extend((0..24).collect::<Vec<u8>>());
+        data.push(0xff);
+        let image = Image::parse(&data).unwrap();
+        assert_eq!(image.format, Format::Bmp);
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.header.len(), 54);
+        assert_eq!(image.pixels, (0..24).collect::<Vec<u8>>());
+        assert_eq!(image.trailer, vec![0xff]);
+        assert_eq!(image.to_bytes(), data);
+
+        data[30] = 1;
+        assert_eq!(Image::parse(&data).err(), Some(ImageError::Compressed));
+    }
+
+    #[test]
+    fn unknown() {
+        assert_eq!(Image::parse(b"GIF89a").err(),
+                   Some(ImageError::UnknownFormat));
+    }
+}
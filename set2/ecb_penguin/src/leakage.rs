@@ -0,0 +1,36 @@
+use std::collections::HashSet;
+
+///Gives the fraction of the whole `block_size` blocks of `data` which repeat
+///an earlier block. Random-looking data gives about 0, while ECB leaves as
+///much structure as the plaintext had.
+pub fn repeated_fraction(data: &[u8], block_size: usize) -> f64 {
+    let mut seen = HashSet::new();
+    let mut blocks = 0;
+    let mut repeats = 0;
+    for block in data.chunks(block_size).filter(|b| b.len() == block_size) {
+        blocks += 1;
+        if !seen.insert(block) {
+            repeats += 1;
+        }
+    }
+    if blocks == 0 {
+        0.0
+    } else {
+        repeats as f64 / blocks as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::repeated_fraction;
+
+    #[test]
+    fn fraction() {
+        assert_eq!(repeated_fraction(&[], 16), 0.0);
+        assert_eq!(repeated_fraction(&[7; 63], 16), 2.0 / 3.0);
+        assert_eq!(repeated_fraction(&(0..64).collect::<Vec<u8>>(), 16), 0.0);
+        let mut data = (0..64).collect::<Vec<u8>>();
+        data[48..].copy_from_slice(&(0..16).collect::<Vec<u8>>());
+        assert_eq!(repeated_fraction(&data, 16), 0.25);
+    }
+}
@@ -5,7 +5,7 @@ pub fn pkcs_pad(message: &[u8], blocksize: u8) -> Vec<u8> {
     let padsize = blocksize-(message.len()%(blocksize as usize)) as u8;
     let mut result = Vec::with_capacity(message.len() + padsize as usize);
     let mut pad = vec![padsize; padsize as usize];
-    result.push_all(message);
+    result.extend_from_slice(message);
     result.append(& mut pad);
     result
 }
@@ -30,7 +30,7 @@ pub fn aes_ecb_encrypt(ciphertext: &[u8], key: &[u8]) -> Result<Vec<u8>, String>
                 InvalidPadding => return Err(str::to_string("Invalid Padding")),
             },
         }
-        final_result.push_all(
+        final_result.extend_from_slice(
             write_buffer.take_read_buffer().take_remaining());
         match result {
             BufferResult::BufferUnderflow => break,
@@ -60,7 +60,7 @@ pub fn aes_ecb_decrypt(ciphertext: &[u8], key: &[u8]) -> Result<Vec<u8>, String>
                 InvalidPadding => return Err(str::to_string("Invalid Padding")),
             },
         }
-        final_result.push_all(
+        final_result.extend_from_slice(
             write_buffer.take_read_buffer().take_remaining());
         match result {
             BufferResult::BufferUnderflow => break,
@@ -92,7 +92,7 @@ pub fn aes_cbc_encrypt(ciphertext: &[u8], key: &[u8], iv: &[u8])
                 InvalidPadding => return Err(str::to_string("Invalid Padding")),
             },
         }
-        final_result.push_all(
+        final_result.extend_from_slice(
             write_buffer.take_read_buffer().take_remaining());
         match result {
             BufferResult::BufferUnderflow => break,
@@ -124,7 +124,7 @@ pub fn aes_cbc_decrypt(ciphertext: &[u8], key: &[u8], iv: &[u8])
                 InvalidPadding => return Err(str::to_string("Invalid Padding")),
             },
         }
-        final_result.push_all(
+        final_result.extend_from_slice(
             write_buffer.take_read_buffer().take_remaining());
         match result {
             BufferResult::BufferUnderflow => break,
@@ -0,0 +1,552 @@
+//!Works out how something was encrypted: the mode, the block size, and how
+//!the IV or nonce changes from one message to the next. This works either from
+//!ciphertexts alone, or from an oracle which encrypts chosen input.
+
+///Something which encrypts chosen input, maybe with other data around it
+pub trait EncryptionOracle {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8>;
+}
+
+impl<F: Fn(&[u8]) -> Vec<u8>> EncryptionOracle for F {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Ecb,
+    Cbc,
+    ///CTR mode, as shown by keystream reused a whole number of blocks along
+    Ctr,
+    ///A stream cipher, or CTR mode with nothing to show its block structure
+    Stream,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IvBehavior {
+    ///The mode has no IV or nonce
+    NotUsed,
+    ///The same every message
+    Fixed,
+    ///Fresh and unpredictable every message
+    Random,
+    ///One more than last message's, counting big-endian
+    Counter,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fingerprint {
+    pub mode: Mode,
+    ///`None` if nothing shows a block structure
+    pub block_size: Option<usize>,
+    pub iv: IvBehavior,
+    ///Rough probability that all of the above is right, from 0 to 1
+    pub confidence: f64,
+}
+
+///Largest block size looked for
+const MAX_BLOCK_SIZE: usize = 64;
+
+///Chosen input used to find keystream reuse, in bytes
+const STREAM_PROBE_LEN: usize = 4 * MAX_BLOCK_SIZE;
+
+///Confidence when two answers can't be told apart, as for a stream cipher and
+///CTR mode with a fixed or random nonce
+const COIN_TOSS: f64 = 0.5;
+
+///Confidence in a random CBC IV from an oracle. A counter can hide from the
+///check if the oracle puts a whole block or more before the input.
+const UNSEEN_COUNTER: f64 = 0.9;
+
+///Chance of two random `block_size` blocks being the same
+fn collision_chance(block_size: usize) -> f64 {
+    0.5f64.powi(8 * block_size as i32)
+}
+
+///Number of pairs of equal blocks within each of `ciphertexts`
+fn repeated_blocks(ciphertexts: &[&[u8]], block_size: usize) -> usize {
+    ciphertexts.iter().map(|ciphertext| {
+        //equal blocks end up next to each other, and a run of n gives
+        //n(n-1)/2 pairs
+        let mut blocks = ciphertext.chunks(block_size).collect::<Vec<_>>();
+        blocks.sort();
+        blocks.chunk_by(|a, b| a == b)
+              .map(|run| run.len() * (run.len() - 1) / 2).sum::<usize>()
+    }).sum()
+}
+
+///Gives the first shift, up to `max_shift`, at which `second` matches `first`
+///shifted along in at least `needed` places
+fn matching_shift(first: &[u8], second: &[u8], max_shift: usize,
+                  needed: usize) -> Option<usize> {
+    (1..max_shift + 1).find(|&shift| {
+        first.iter().skip(shift).zip(second.iter())
+             .filter(|&(a, b)| a == b).count() >= needed
+    })
+}
+
+///Fingerprints an oracle which encrypts chosen input under the same key each
+///time, putting the same data before and after it
+pub fn classify_oracle<O: EncryptionOracle + ?Sized>(oracle: &O)
+        -> Fingerprint {
+    //padded block modes grow a whole block at a time, and others byte by byte
+    let base_len = oracle.encrypt(&[]).len();
+    for input_len in 1..MAX_BLOCK_SIZE + 1 {
+        let len = oracle.encrypt(&vec![0; input_len]).len();
+        if len == base_len + input_len && input_len == 1 {
+            return classify_stream_oracle(oracle);
+        }
+        if len > base_len {
+            return classify_block_oracle(oracle, len - base_len);
+        }
+    }
+    //no sign of padding or of a stream
+    Fingerprint {
+        mode: Mode::Stream,
+        block_size: None,
+        iv: IvBehavior::Fixed,
+        confidence: 0.0,
+    }
+}
+
+fn classify_block_oracle<O: EncryptionOracle + ?Sized>(oracle: &O,
+                                                       block_size: usize)
+        -> Fingerprint {
+    //three blocks of the same input always give two whole ones
+    let ciphertext = oracle.encrypt(&vec![0; 3 * block_size]);
+    let blocks = ciphertext.len() / block_size;
+    let false_alarm = (blocks * blocks) as f64 * collision_chance(block_size);
+    if repeated_blocks(&[&ciphertext], block_size) > 0 {
+        return Fingerprint {
+            mode: Mode::Ecb,
+            block_size: Some(block_size),
+            iv: IvBehavior::NotUsed,
+            confidence: 1.0 - false_alarm,
+        };
+    }
+    let cbc = |iv, confidence| Fingerprint {
+        mode: Mode::Cbc,
+        block_size: Some(block_size),
+        iv,
+        confidence,
+    };
+    let input = vec![0; 2 * block_size];
+    if oracle.encrypt(&input) == oracle.encrypt(&input) {
+        return cbc(IvBehavior::Fixed, 1.0 - false_alarm);
+    }
+    //An IV which counts up by one only flips its last bit when it's even.
+    //Whichever input byte ends the first block, flipping it back in one of
+    //two consecutive messages gives the same first block.
+    for idx in 0..block_size {
+        let mut flipped = input.clone();
+        flipped[idx] ^= 1;
+        let first_blocks = [&input, &flipped, &input].iter().map(|input| {
+            let mut ciphertext = oracle.encrypt(input);
+            ciphertext.truncate(block_size);
+            ciphertext
+        }).collect::<Vec<_>>();
+        if first_blocks[0] == first_blocks[1]
+                || first_blocks[1] == first_blocks[2] {
+            return cbc(IvBehavior::Counter, 1.0 - false_alarm);
+        }
+    }
+    cbc(IvBehavior::Random, UNSEEN_COUNTER)
+}
+
+fn classify_stream_oracle<O: EncryptionOracle + ?Sized>(oracle: &O)
+        -> Fingerprint {
+    //the same input twice gives keystream xor the same plaintext twice, so
+    //reused keystream shows up as equal bytes
+    let input = vec![0; STREAM_PROBE_LEN];
+    let first = oracle.encrypt(&input);
+    let second = oracle.encrypt(&input);
+    if first == second {
+        return Fingerprint {
+            mode: Mode::Stream,
+            block_size: None,
+            iv: IvBehavior::Fixed,
+            confidence: COIN_TOSS,
+        };
+    }
+    //a CTR counter which carries on from the last message's IV gives the same
+    //keystream again, a block along
+    match matching_shift(&first, &second, MAX_BLOCK_SIZE,
+                         STREAM_PROBE_LEN / 2) {
+        Some(block_size) => Fingerprint {
+            mode: Mode::Ctr,
+            block_size: Some(block_size),
+            iv: IvBehavior::Counter,
+            confidence: 1.0,
+        },
+        None => Fingerprint {
+            mode: Mode::Stream,
+            block_size: None,
+            iv: IvBehavior::Random,
+            confidence: COIN_TOSS,
+        },
+    }
+}
+
+///Fraction of the differing bytes of `first` xor `second`, shifted along by
+///`shift`, with the top bit set. Text xor text has it clear, but random bytes
+///half the time. Equal bytes say nothing, since block modes give those for
+///equal plaintext too.
+fn high_bit_fraction(first: &[u8], second: &[u8], shift: usize)
+        -> Option<f64> {
+    let pairs = first.iter().skip(shift).zip(second.iter())
+                     .map(|(a, b)| a ^ b).filter(|&byte| byte != 0)
+                     .collect::<Vec<u8>>();
+    if pairs.len() < 16 {
+        return None;
+    }
+    let high = pairs.iter().filter(|&&byte| byte & 0x80 != 0).count();
+    Some(high as f64 / pairs.len() as f64)
+}
+
+///Fingerprints `ciphertexts` alone, each encrypted under the same key. Finding
+///reused keystream assumes the plaintexts are mostly ASCII.
+pub fn classify_ciphertexts(ciphertexts: &[&[u8]]) -> Fingerprint {
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+    //reuse is unmistakable, whatever the lengths look like
+    if let Some(fingerprint) = keystream_reuse(ciphertexts) {
+        return fingerprint;
+    }
+    let common = ciphertexts.iter().fold(0, |acc, c| gcd(acc, c.len()));
+    let block_size = [16, 8].iter().cloned().find(|&size| {
+        common != 0 && common % size == 0
+    });
+    match block_size {
+        Some(block_size) => classify_block_ciphertexts(ciphertexts, block_size),
+        None => Fingerprint {
+            mode: Mode::Stream,
+            block_size: None,
+            iv: IvBehavior::Random,
+            confidence: COIN_TOSS,
+        },
+    }
+}
+
+fn classify_block_ciphertexts(ciphertexts: &[&[u8]], block_size: usize)
+        -> Fingerprint {
+    //lengths which are all a multiple of the block size by chance
+    let coincidence = (1.0 / block_size as f64).powi(ciphertexts.len() as i32);
+    if repeated_blocks(ciphertexts, block_size) > 0 {
+        return Fingerprint {
+            mode: Mode::Ecb,
+            block_size: Some(block_size),
+            iv: IvBehavior::NotUsed,
+            confidence: 1.0 - coincidence,
+        };
+    }
+    //no repeats could also be ECB of plaintext without any
+    let total_blocks = ciphertexts.iter().map(|c| c.len() / block_size)
+                                  .sum::<usize>();
+    let confidence = (1.0 - coincidence)
+                     * (1.0 - 0.5 / (1.0 + total_blocks as f64 / 16.0));
+    //an empty ciphertext has no first block to compare
+    let mut first_blocks = ciphertexts.iter()
+                                      .filter(|c| c.len() >= block_size)
+                                      .map(|c| &c[..block_size])
+                                      .collect::<Vec<_>>();
+    first_blocks.sort();
+    let shared_start = first_blocks.windows(2).any(|pair| pair[0] == pair[1]);
+    if shared_start {
+        //ECB gives the same first block for the same start too, and with no
+        //repeats to go on the two can't be told apart
+        return Fingerprint {
+            mode: Mode::Cbc,
+            block_size: Some(block_size),
+            iv: IvBehavior::Fixed,
+            confidence: COIN_TOSS * (1.0 - coincidence),
+        };
+    }
+    Fingerprint {
+        mode: Mode::Cbc,
+        block_size: Some(block_size),
+        //a counting IV looks just like a random one from here
+        iv: IvBehavior::Random,
+        confidence,
+    }
+}
+
+///Looks for reused keystream between any two of `ciphertexts`
+fn keystream_reuse(ciphertexts: &[&[u8]]) -> Option<Fingerprint> {
+    for (idx, first) in ciphertexts.iter().enumerate() {
+        for second in ciphertexts[idx + 1..].iter() {
+            for shift in 0..MAX_BLOCK_SIZE + 1 {
+                let reused = [(first, second), (second, first)].iter()
+                    .filter_map(|&(a, b)| high_bit_fraction(a, b, shift))
+                    .any(|fraction| fraction < 0.1);
+                if !reused {
+                    continue;
+                }
+                return Some(if shift == 0 {
+                    Fingerprint {
+                        mode: Mode::Stream,
+                        block_size: None,
+                        iv: IvBehavior::Fixed,
+                        confidence: COIN_TOSS,
+                    }
+                } else {
+                    Fingerprint {
+                        mode: Mode::Ctr,
+                        block_size: Some(shift),
+                        iv: IvBehavior::Counter,
+                        confidence: 1.0,
+                    }
+                });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use rand;
+    use rand::Rng;
+    use ciphers;
+    use super::{Fingerprint, IvBehavior, Mode};
+
+    //How a trial oracle encrypts. `Ctr` and `Stream` are given the IV
+    //behaviour, and `Cbc` too.
+    #[derive(Clone, Copy, Debug)]
+    enum Cipher {
+        Ecb,
+        Cbc(IvBehavior),
+        Ctr(IvBehavior),
+        Stream(IvBehavior),
+    }
+
+    fn random_bytes<R: Rng>(rng: &mut R, count: usize) -> Vec<u8> {
+        (0..count).map(|_| rng.gen::<u8>()).collect()
+    }
+
+    fn increment(iv: &mut [u8]) {
+        for byte in iv.iter_mut().rev() {
+            *byte = byte.wrapping_add(1);
+            if *byte != 0 {
+                break;
+            }
+        }
+    }
+
+    fn xorshift_keystream(seed: &[u8], len: usize) -> Vec<u8> {
+        let mut state = seed.iter().fold(0x9e3779b97f4a7c15u64, |state, &b| {
+            (state ^ b as u64).wrapping_mul(0x100000001b3)
+        }) | 1;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 32) as u8
+        }).collect()
+    }
+
+    fn ctr_keystream(key: &[u8], iv: &[u8], len: usize) -> Vec<u8> {
+        let mut counter = iv.to_vec();
+        let mut keystream = Vec::new();
+        while keystream.len() < len {
+            let block = ciphers::aes_ecb_encrypt(&counter, key).unwrap();
+            keystream.extend_from_slice(&block[..16]);
+            increment(&mut counter);
+        }
+        keystream.truncate(len);
+        keystream
+    }
+
+    //A p11-style oracle, with a random key, and 5 to 10 random bytes either
+    //side of the input
+    struct TrialOracle {
+        cipher: Cipher,
+        key: Vec<u8>,
+        before: Vec<u8>,
+        after: Vec<u8>,
+        iv: ::std::cell::RefCell<Vec<u8>>,
+    }
+
+    impl TrialOracle {
+        fn new<R: Rng>(rng: &mut R, cipher: Cipher) -> TrialOracle {
+            let before_len = rng.gen_range(5, 11);
+            let after_len = rng.gen_range(5, 11);
+            TrialOracle {
+                cipher,
+                key: random_bytes(rng, 16),
+                before: random_bytes(rng, before_len),
+                after: random_bytes(rng, after_len),
+                iv: ::std::cell::RefCell::new(random_bytes(rng, 16)),
+            }
+        }
+
+        //gives the IV for the next message
+        fn next_iv(&self, behavior: IvBehavior) -> Vec<u8> {
+            let mut iv = self.iv.borrow_mut();
+            match behavior {
+                IvBehavior::Random => {
+                    *iv = random_bytes(&mut rand::thread_rng(), 16);
+                }
+                IvBehavior::Counter => increment(&mut iv),
+                _ => {}
+            }
+            iv.clone()
+        }
+
+        fn encrypt_text(&self, text: &[u8]) -> Vec<u8> {
+            let xor = |keystream: Vec<u8>| {
+                text.iter().zip(keystream).map(|(t, k)| t ^ k).collect()
+            };
+            match self.cipher {
+                Cipher::Ecb => ciphers::aes_ecb_encrypt(text, &self.key)
+                                   .unwrap(),
+                Cipher::Cbc(behavior) => {
+                    let iv = self.next_iv(behavior);
+                    ciphers::aes_cbc_encrypt(text, &self.key, &iv).unwrap()
+                }
+                Cipher::Ctr(behavior) => {
+                    let iv = self.next_iv(behavior);
+                    xor(ctr_keystream(&self.key, &iv, text.len()))
+                }
+                Cipher::Stream(behavior) => {
+                    let mut seed = self.key.clone();
+                    seed.extend(self.next_iv(behavior));
+                    xor(xorshift_keystream(&seed, text.len()))
+                }
+            }
+        }
+
+        fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+            let mut text = self.before.clone();
+            text.extend_from_slice(input);
+            text.extend_from_slice(&self.after);
+            self.encrypt_text(&text)
+        }
+
+        //what the classifier can hope to see
+        fn expected(&self) -> (Mode, Option<usize>, IvBehavior) {
+            match self.cipher {
+                Cipher::Ecb => (Mode::Ecb, Some(16), IvBehavior::NotUsed),
+                Cipher::Cbc(behavior) => (Mode::Cbc, Some(16), behavior),
+                Cipher::Ctr(IvBehavior::Counter) =>
+                    (Mode::Ctr, Some(16), IvBehavior::Counter),
+                Cipher::Ctr(behavior) | Cipher::Stream(behavior) =>
+                    (Mode::Stream, None, behavior),
+            }
+        }
+    }
+
+    fn random_cipher<R: Rng>(rng: &mut R) -> Cipher {
+        let behaviors = [IvBehavior::Fixed, IvBehavior::Random,
+                         IvBehavior::Counter];
+        match rng.gen_range(0, 4) {
+            0 => Cipher::Ecb,
+            1 => Cipher::Cbc(behaviors[rng.gen_range(0, 3)]),
+            2 => Cipher::Ctr(behaviors[rng.gen_range(0, 3)]),
+            _ => Cipher::Stream(behaviors[rng.gen_range(0, 2)]),
+        }
+    }
+
+    fn matches(fingerprint: &Fingerprint,
+               expected: (Mode, Option<usize>, IvBehavior)) -> bool {
+        (fingerprint.mode, fingerprint.block_size, fingerprint.iv) == expected
+    }
+
+    #[test]
+    fn oracle_accuracy() {
+        let mut rng = rand::thread_rng();
+        let trials = 2000;
+        let mut correct = 0;
+        for _ in 0..trials {
+            let cipher = random_cipher(&mut rng);
+            let oracle = TrialOracle::new(&mut rng, cipher);
+            let fingerprint = super::classify_oracle(&|input: &[u8]| {
+                oracle.encrypt(input)
+            });
+            if matches(&fingerprint, oracle.expected()) {
+                correct += 1;
+            } else {
+                println!("{:?}: got {:?}", oracle.cipher, fingerprint);
+            }
+        }
+        let accuracy = correct as f64 / trials as f64;
+        println!("Oracle accuracy: {}/{} ({:.2}%)", correct, trials,
+                 100.0 * accuracy);
+        assert!(accuracy > 0.99);
+    }
+
+    //a few lines of `lines` from `start` as fixed-width records, followed by
+    //a note of any length
+    fn records<R: Rng>(rng: &mut R, lines: &[&str], start: usize) -> Vec<u8> {
+        let count = rng.gen_range(4, 16);
+        let mut text = Vec::new();
+        for line in lines[start..start + count].iter() {
+            let line = &line.as_bytes()[..line.len().min(64)];
+            text.extend_from_slice(line);
+            text.extend(vec![b' '; 64 - line.len()]);
+        }
+        let note_len = rng.gen_range(0, 64);
+        text.extend(b"Note: ".iter().cycle().take(note_len));
+        text
+    }
+
+    #[test]
+    fn ciphertext_accuracy() {
+        let mut rng = rand::thread_rng();
+        let text = ::load_txt("../../text_samples/les_miserable.txt").unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        let trials = 2000;
+        let mut correct = 0;
+        for _ in 0..trials {
+            let mut cipher = random_cipher(&mut rng);
+            //passively, a counting CBC IV looks just like a random one
+            if let Cipher::Cbc(IvBehavior::Counter) = cipher {
+                cipher = Cipher::Cbc(IvBehavior::Random);
+            }
+            let oracle = TrialOracle::new(&mut rng, cipher);
+            //every message starts with the same record, like a header
+            let start = rng.gen_range(0, lines.len() - 16);
+            let ciphertexts = (0..4).map(|_| {
+                oracle.encrypt_text(&records(&mut rng, &lines, start))
+            }).collect::<Vec<_>>();
+            let ciphertexts = ciphertexts.iter().map(|c| &c[..])
+                                         .collect::<Vec<_>>();
+            let fingerprint = super::classify_ciphertexts(&ciphertexts);
+            if matches(&fingerprint, oracle.expected()) {
+                correct += 1;
+            } else {
+                println!("{:?}: got {:?}", oracle.cipher, fingerprint);
+            }
+        }
+        let accuracy = correct as f64 / trials as f64;
+        println!("Ciphertext accuracy: {}/{} ({:.2}%)", correct, trials,
+                 100.0 * accuracy);
+        assert!(accuracy > 0.95);
+    }
+
+    #[test]
+    fn short_ciphertexts() {
+        let key = [0x5a; 16];
+        let ciphertext = ciphers::aes_cbc_encrypt(&[0; 40], &key, &[1; 16])
+                             .unwrap();
+        let fingerprint = super::classify_ciphertexts(&[b"", &ciphertext]);
+        assert_eq!(fingerprint.mode, Mode::Cbc);
+        assert_eq!(fingerprint.iv, IvBehavior::Random);
+    }
+
+    #[test]
+    fn shared_start() {
+        //a fixed IV CBC and ECB both give this, so neither is likely
+        let key = [0x5a; 16];
+        let messages = [&b"A 16 byte header, then"[..],
+                        b"A 16 byte header and so"];
+        let ciphertexts = messages.iter().map(|message| {
+            ciphers::aes_cbc_encrypt(message, &key, &[1; 16]).unwrap()
+        }).collect::<Vec<_>>();
+        let fingerprint = super::classify_ciphertexts(
+            &[&ciphertexts[0], &ciphertexts[1]]);
+        assert_eq!(fingerprint.iv, IvBehavior::Fixed);
+        assert!(fingerprint.confidence <= super::COIN_TOSS);
+    }
+}
@@ -1,35 +1,7 @@
-#![feature(collections)]
-#![feature(std_misc)]
-#![feature(core)]
-
 extern crate rand;
 extern crate crypto;
 mod ciphers;
-
-fn detect_ecb(ciphertext: &[u8], blocksize: u16) -> bool {
-    use std::collections::HashMap;
-    use std::collections::hash_map::Entry::{Occupied, Vacant};
-    use std::num::Int;
-
-    //get blocks
-    let blocks = ciphertext.chunks(blocksize as usize);
-    let nblocks = blocks.len();
-
-    //get number of repetitions
-    let mut repetitions = HashMap::new();
-    for block in blocks {
-        match repetitions.entry(block) {
-            Vacant(entry)   => {entry.insert(0);},
-            Occupied(mut entry) => *entry.get_mut() += 1,
-        }
-    }
-    let mut nrepetitions = 0;
-    for value in repetitions.values(){
-        nrepetitions += *value;
-    }
-
-    nrepetitions > 1
-}
+mod classify;
 
 #[derive(PartialEq)]
 enum Algorithm {
@@ -46,13 +18,17 @@ impl std::fmt::Display for Algorithm {
     }
 }
 
-fn encryption_oracle(ciphertext: &[u8]) -> Algorithm {
-    match detect_ecb(ciphertext, 16) {
-        true  => Algorithm::ECB,
-        false => Algorithm::CBC,
+fn to_algorithm(fingerprint: classify::Fingerprint) -> Algorithm {
+    match fingerprint.mode {
+        classify::Mode::Ecb => Algorithm::ECB,
+        _ => Algorithm::CBC,
     }
 }
 
+fn encryption_oracle(ciphertext: &[u8]) -> Algorithm {
+    to_algorithm(classify::classify_ciphertexts(&[ciphertext]))
+}
+
 fn generate_bytes<R: rand::Rng>(rng: &mut R, count: usize) -> Vec<u8> {
     let mut result = Vec::with_capacity(count);
     for _ in 0..count {
@@ -68,29 +44,38 @@ fn choose_cipher<R: rand::Rng>(rng: &mut R) -> Algorithm {
     }
 }
 
-fn create_cipher<R: rand::Rng>(rng: &mut R, plaintext: &[u8])
-        -> (Vec<u8>, Algorithm) {
-    use rand::sample;
-    let before_size = sample(rng, 5..10, 1)[0];
-    let before = &generate_bytes(rng, before_size)[..];
-    let after_size = sample(rng, 5..10, 1)[0];
-    let after = &generate_bytes(rng, after_size)[..];
+type Oracle = Box<dyn Fn(&[u8]) -> Vec<u8>>;
+
+///Makes an oracle which encrypts its input under a random key and cipher, with
+///5 to 10 random bytes either side. CBC gets a fresh IV every message.
+fn create_oracle<R: rand::Rng>(rng: &mut R)
+        -> (Oracle, Algorithm) {
+    let before_size = rng.gen_range(5, 11);
+    let before = generate_bytes(rng, before_size);
+    let after_size = rng.gen_range(5, 11);
+    let after = generate_bytes(rng, after_size);
     let algorithm = choose_cipher(rng);
-    let key = &generate_bytes(rng, 16)[..];
-    let mut input = Vec::new();
-    input.push_all(&before[..]);
-    input.push_all(plaintext);
-    input.push_all(&after[..]);
-    let ciphertext = match algorithm {
-        Algorithm::ECB => {
-            ciphers::aes_ecb_encrypt(&input[..], &key[..]).unwrap()
-        },
-        Algorithm::CBC => {
-            let iv = &generate_bytes(rng, 16)[..];
-            ciphers::aes_cbc_encrypt(&input[..], key, iv).unwrap()
-        },
+    let key = generate_bytes(rng, 16);
+    let cbc = algorithm == Algorithm::CBC;
+    let oracle = move |plaintext: &[u8]| {
+        let mut input = Vec::new();
+        input.extend_from_slice(&before);
+        input.extend_from_slice(plaintext);
+        input.extend_from_slice(&after);
+        if cbc {
+            let iv = generate_bytes(&mut rand::thread_rng(), 16);
+            ciphers::aes_cbc_encrypt(&input, &key, &iv).unwrap()
+        } else {
+            ciphers::aes_ecb_encrypt(&input, &key).unwrap()
+        }
     };
-    (ciphertext, algorithm)
+    (Box::new(oracle), algorithm)
+}
+
+fn create_cipher<R: rand::Rng>(rng: &mut R, plaintext: &[u8])
+        -> (Vec<u8>, Algorithm) {
+    let (oracle, algorithm) = create_oracle(rng);
+    (oracle(plaintext), algorithm)
 }
 
 fn load_txt(filename: &str) -> Result<String, String> {
@@ -129,4 +114,22 @@ fn main() {
         }
     }
     println!("Final score: {}/{}", score, iterations);
+
+    //with chosen input, the plaintext doesn't need any repeats
+    let mut score = 0;
+    for i in 0..iterations {
+        let (oracle, answer) = create_oracle(&mut rng);
+        let fingerprint = classify::classify_oracle(&oracle);
+        let guessed = to_algorithm(fingerprint);
+        print!("{}: Oracle guessed {} (IV {:?}), Answer was {}. ",
+               i, guessed, fingerprint.iv, answer);
+        if guessed==answer {
+            println!("Correct!");
+            score += 1;
+        }
+        else {
+            println!("Incorrect =(");
+        }
+    }
+    println!("Final oracle score: {}/{}", score, iterations);
 }
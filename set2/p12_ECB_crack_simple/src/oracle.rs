@@ -1,4 +1,5 @@
 use aes;
+use ecb_attack::EcbOracle;
 
 use rand;
 
@@ -29,6 +30,12 @@ impl AesEcbOracle {
     }
 }
 
+impl EcbOracle for AesEcbOracle {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        AesEcbOracle::encrypt(self, input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::AesEcbOracle;
@@ -0,0 +1,259 @@
+//!Builds ECB ciphertexts for a chosen plaintext out of blocks cut from an
+//!oracle which encrypts a known template with attacker-controlled fields.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+///Something which fills in a template's fields and encrypts the result
+pub trait FieldOracle {
+    ///Gives the encryption of the template with `fields` filled in, in order
+    fn encrypt(&self, fields: &[Vec<u8>]) -> Vec<u8>;
+}
+
+impl<F: Fn(&[Vec<u8>]) -> Vec<u8>> FieldOracle for F {
+    fn encrypt(&self, fields: &[Vec<u8>]) -> Vec<u8> {
+        self(fields)
+    }
+}
+
+///Fixed text with a field between each pair of literals, so
+///`["email=", "&uid=10&role=user"]` is `email=<field>&uid=10&role=user`
+pub struct Template {
+    literals: Vec<Vec<u8>>,
+    forbidden: Vec<u8>,
+}
+
+impl Template {
+    ///Panics if there are no literals
+    pub fn new(literals: &[&[u8]]) -> Template {
+        assert!(!literals.is_empty(), "A template needs at least one literal");
+        Template {
+            literals: literals.iter().map(|literal| literal.to_vec()).collect(),
+            forbidden: Vec::new(),
+        }
+    }
+
+    ///Marks `bytes` as ones which don't make it through a field, for instance
+    ///because the oracle strips or escapes them
+    pub fn forbid(mut self, bytes: &[u8]) -> Template {
+        self.forbidden.extend_from_slice(bytes);
+        self
+    }
+
+    fn fields(&self) -> usize {
+        self.literals.len() - 1
+    }
+
+    fn allowed(&self, bytes: &[u8]) -> bool {
+        bytes.iter().all(|byte| !self.forbidden.contains(byte))
+    }
+
+    ///Gives a byte which can go in any field, to push things into alignment
+    fn filler(&self) -> Option<u8> {
+        (0..256).map(|n| (b'A' as usize + n) as u8)
+                .find(|byte| !self.forbidden.contains(byte))
+    }
+
+    ///Tries to match `block` against the template, starting `offset` bytes
+    ///into the literal or field at `item`. Even items are literals and odd
+    ///ones fields. Fields met along the way get the bytes they have to hold.
+    fn fit(&self, block: &[u8], item: usize, offset: usize,
+           fields: &mut [Vec<u8>]) -> bool {
+        if block.is_empty() {
+            return true;
+        }
+        if item % 2 == 1 {
+            let field = item / 2;
+            for take in (0..block.len() + 1).rev() {
+                if self.allowed(&block[..take])
+                        && self.fit(&block[take..], item + 1, 0, fields) {
+                    fields[field].extend_from_slice(&block[..take]);
+                    return true;
+                }
+            }
+            return false;
+        }
+        let literal = &self.literals[item / 2][offset..];
+        let common = literal.len().min(block.len());
+        if block[..common] != literal[..common] {
+            return false;
+        }
+        let rest = &block[common..];
+        if item / 2 < self.fields() {
+            self.fit(rest, item + 1, 0, fields)
+        } else {
+            //after the last literal comes the PKCS7 padding
+            rest.iter().all(|&byte| byte as usize == rest.len())
+        }
+    }
+
+    ///Gives field values which put `block` at an aligned block of the
+    ///plaintext, and that block's index
+    fn place(&self, block: &[u8]) -> Option<(Vec<Vec<u8>>, usize)> {
+        let block_size = block.len();
+        let filler = self.filler()?;
+        //bytes before the start of literal `idx`, with empty fields
+        let mut before = 0;
+        for (idx, literal) in self.literals.iter().enumerate() {
+            //starting in the literal, the field just before it lines it up
+            for offset in 0..literal.len() {
+                let start = before + offset;
+                let align = (block_size - start % block_size) % block_size;
+                if align != 0 && idx == 0 {
+                    continue;
+                }
+                let mut fields = vec![Vec::new(); self.fields()];
+                if self.fit(block, 2 * idx, offset, &mut fields) {
+                    if align != 0 {
+                        fields[idx - 1] = vec![filler; align];
+                    }
+                    return Some((fields, (start + align) / block_size));
+                }
+            }
+            before += literal.len();
+            //starting in the field after it, filler at its front lines it up
+            if idx < self.fields() {
+                let align = (block_size - before % block_size) % block_size;
+                let mut fields = vec![Vec::new(); self.fields()];
+                if self.fit(block, 2 * idx + 1, 0, &mut fields) {
+                    let mut field = vec![filler; align];
+                    field.extend_from_slice(&fields[idx]);
+                    fields[idx] = field;
+                    return Some((fields, (before + align) / block_size));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum CutError {
+    ///No placement of the template gives target block `n`
+    Unreachable(usize),
+    ///The oracle's ciphertext was too short to hold target block `n`
+    ShortCiphertext(usize),
+}
+
+impl fmt::Display for CutError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CutError::Unreachable(n) =>
+                write!(formatter, "The template can't give block {}", n),
+            CutError::ShortCiphertext(n) =>
+                write!(formatter, "The oracle's ciphertext lacks block {}", n),
+        }
+    }
+}
+
+impl Error for CutError {
+    fn description(&self) -> &str {
+        match *self {
+            CutError::Unreachable(_) => "The template can't give a block",
+            CutError::ShortCiphertext(_) => "The oracle's ciphertext was short",
+        }
+    }
+}
+
+///Gives a ciphertext which decrypts to `target` under the key `oracle` uses,
+///made from whole blocks of its ciphertexts. Every `block_size` block of the
+///PKCS7-padded target has to be somewhere in some filling-in of `template`.
+pub fn cut_and_paste<O: FieldOracle + ?Sized>(oracle: &O, template: &Template,
+                                              target: &[u8], block_size: usize)
+        -> Result<Vec<u8>, CutError> {
+    let pad = block_size - target.len() % block_size;
+    let mut padded = target.to_vec();
+    padded.extend(vec![pad as u8; pad]);
+    //the same block always encrypts the same way, so only ask once
+    let mut found: HashMap<&[u8], Vec<u8>> = HashMap::new();
+    let mut ciphertext = Vec::with_capacity(padded.len());
+    for (n, block) in padded.chunks(block_size).enumerate() {
+        if !found.contains_key(block) {
+            let (fields, idx) = template.place(block)
+                                        .ok_or(CutError::Unreachable(n))?;
+            let encrypted = oracle.encrypt(&fields);
+            match encrypted.chunks(block_size).nth(idx) {
+                Some(encrypted) if encrypted.len() == block_size => {
+                    found.insert(block, encrypted.to_vec());
+                }
+                _ => return Err(CutError::ShortCiphertext(n)),
+            }
+        }
+        ciphertext.extend_from_slice(&found[block]);
+    }
+    Ok(ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use aes;
+    use super::{Template, CutError, FieldOracle};
+
+    const KEY: &[u8] = b"yellow submarine";
+
+    //fills in `literals`, dropping forbidden bytes from the fields
+    struct Oracle<'a> {
+        literals: &'a [&'a [u8]],
+        forbidden: &'a [u8],
+    }
+
+    impl<'a> FieldOracle for Oracle<'a> {
+        fn encrypt(&self, fields: &[Vec<u8>]) -> Vec<u8> {
+            let mut plaintext = self.literals[0].to_vec();
+            for (field, literal) in fields.iter()
+                                          .zip(self.literals[1..].iter()) {
+                plaintext.extend(field.iter()
+                                      .filter(|b| !self.forbidden.contains(b)));
+                plaintext.extend_from_slice(literal);
+            }
+            aes::aes_ecb_encrypt(&plaintext, KEY)
+        }
+    }
+
+    fn check(literals: &[&[u8]], forbidden: &[u8], target: &[u8]) {
+        let template = Template::new(literals).forbid(forbidden);
+        let ciphertext = super::cut_and_paste(&Oracle { literals, forbidden },
+                                              &template, target, 16).unwrap();
+        assert_eq!(aes::aes_ecb_decrypt(&ciphertext, KEY).unwrap(),
+                   target.to_vec());
+    }
+
+    #[test]
+    fn key_value() {
+        let literals: &[&[u8]] = &[b"user=", b"&comment=", b"&role=user"];
+        check(literals, b"&=", b"user=eve&comment=123456789&role=admin");
+        //a whole block of padding at the end
+        check(literals, b"&=", b"user=eve&comment=hello&role=user");
+    }
+
+    #[test]
+    fn json() {
+        let literals: &[&[u8]] = &[b"{\"name\":\"", b"\",\"admin\":false}"];
+        check(literals, b"\"\\",
+              b"{\"name\":\"abcdefghijklm\",\"admin\":true}");
+    }
+
+    #[test]
+    fn padded_literal() {
+        //with the padding byte forbidden, the last block has to be the end of
+        //the literal followed by real padding
+        let literals: &[&[u8]] = &[b"id=", b";comment=like a pound of bacon"];
+        check(literals, b";=\x0f",
+              b"id=0123456789abcdef;comment=like a pound of bacon");
+    }
+
+    #[test]
+    fn unreachable() {
+        let literals: &[&[u8]] = &[b"email=", b"&uid=10&role=user"];
+        let template = Template::new(literals).forbid(b"&=");
+        //"role=" doesn't end on a block boundary, so "admin" can't follow it
+        match super::cut_and_paste(&Oracle { literals, forbidden: b"&=" },
+                                   &template,
+                                   b"email=foo@bar.com&uid=10&role=admin", 16) {
+            Err(CutError::Unreachable(1)) => {}
+            other => panic!("Expected block 1 to be unreachable, got {:?}",
+                            other),
+        }
+    }
+}
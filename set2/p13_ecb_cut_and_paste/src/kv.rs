@@ -0,0 +1,219 @@
+//!URL-encoded `k=v&k=v` strings. Keys and values are percent-escaped, so
+//!anything encoded decodes back to exactly the same pairs.
+
+use std::error::Error;
+use std::fmt;
+
+///How forgiving decoding is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    ///Only accepts what `encode` could have produced
+    Strict,
+    ///Accepts anything. Empty pairs are skipped, a pair without `=` has an
+    ///empty value, and bytes which should have been escaped are taken as is.
+    Lenient,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KvError {
+    ///Pair `n` has no `=`
+    MissingEquals(usize),
+    ///Pair `n` has more than one `=`
+    ExtraEquals(usize),
+    ///The `%` at byte `n` isn't followed by two hex digits
+    BadEscape(usize),
+    ///Byte `n` should have been escaped
+    Unescaped(usize),
+    ///Pair `n` doesn't decode to UTF-8
+    BadUtf8(usize),
+}
+
+impl fmt::Display for KvError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KvError::MissingEquals(n) =>
+                write!(formatter, "Pair {} has no '='", n),
+            KvError::ExtraEquals(n) =>
+                write!(formatter, "Pair {} has more than one '='", n),
+            KvError::BadEscape(n) =>
+                write!(formatter, "Bad escape at byte {}", n),
+            KvError::Unescaped(n) =>
+                write!(formatter, "Unescaped byte at {}", n),
+            KvError::BadUtf8(n) =>
+                write!(formatter, "Pair {} isn't valid UTF-8", n),
+        }
+    }
+}
+
+impl Error for KvError {
+    fn description(&self) -> &str {
+        match *self {
+            KvError::MissingEquals(_) => "Pair has no '='",
+            KvError::ExtraEquals(_) => "Pair has more than one '='",
+            KvError::BadEscape(_) => "Bad escape",
+            KvError::Unescaped(_) => "Unescaped byte",
+            KvError::BadUtf8(_) => "Pair isn't valid UTF-8",
+        }
+    }
+}
+
+///True for bytes which are written as themselves
+fn is_plain(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~@".contains(&byte)
+}
+
+fn escape(text: &str, out: &mut String) {
+    for &byte in text.as_bytes() {
+        if is_plain(byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+///Undoes `escape` on `text`, which starts at byte `start` of the whole input
+fn unescape(text: &[u8], start: usize, mode: Mode)
+        -> Result<Vec<u8>, KvError> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut idx = 0;
+    while idx < text.len() {
+        let byte = text[idx];
+        if byte == b'%' {
+            let digits = (text.get(idx + 1).and_then(|&b| hex_value(b)),
+                          text.get(idx + 2).and_then(|&b| hex_value(b)));
+            if let (Some(high), Some(low)) = digits {
+                out.push(high << 4 | low);
+                idx += 3;
+                continue;
+            }
+            if mode == Mode::Strict {
+                return Err(KvError::BadEscape(start + idx));
+            }
+        } else if mode == Mode::Strict && !is_plain(byte) {
+            return Err(KvError::Unescaped(start + idx));
+        }
+        out.push(byte);
+        idx += 1;
+    }
+    Ok(out)
+}
+
+///Gives `pairs` as a `k=v&k=v` string
+pub fn encode<K: AsRef<str>, V: AsRef<str>>(pairs: &[(K, V)]) -> String {
+    let mut out = String::new();
+    for (idx, (key, value)) in pairs.iter().enumerate() {
+        if idx != 0 {
+            out.push('&');
+        }
+        escape(key.as_ref(), &mut out);
+        out.push('=');
+        escape(value.as_ref(), &mut out);
+    }
+    out
+}
+
+///Gives the pairs in `text`, in order. Repeated keys are kept.
+pub fn decode(text: &str, mode: Mode)
+        -> Result<Vec<(String, String)>, KvError> {
+    let mut pairs = Vec::new();
+    if text.is_empty() {
+        return Ok(pairs);
+    }
+    let mut start = 0;
+    for (n, pair) in text.as_bytes().split(|&b| b == b'&').enumerate() {
+        let pair_start = start;
+        start += pair.len() + 1;
+        if pair.is_empty() && mode == Mode::Lenient {
+            continue;
+        }
+        let split = pair.iter().position(|&b| b == b'=');
+        let (key, value) = match split {
+            Some(at) => (&pair[..at], &pair[at + 1..]),
+            None if mode == Mode::Strict =>
+                return Err(KvError::MissingEquals(n)),
+            None => (pair, &[][..]),
+        };
+        if mode == Mode::Strict && value.contains(&b'=') {
+            return Err(KvError::ExtraEquals(n));
+        }
+        let value_start = pair_start + key.len() + 1;
+        let key = unescape(key, pair_start, mode)?;
+        let value = unescape(value, value_start, mode)?;
+        let (key, value) = match mode {
+            Mode::Strict => match (String::from_utf8(key),
+                                   String::from_utf8(value)) {
+                (Ok(key), Ok(value)) => (key, value),
+                _ => return Err(KvError::BadUtf8(n)),
+            },
+            Mode::Lenient => (String::from_utf8_lossy(&key).into_owned(),
+                              String::from_utf8_lossy(&value).into_owned()),
+        };
+        pairs.push((key, value));
+    }
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KvError, Mode};
+
+    fn pairs(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(super::encode(&[("email", "foo@bar.com"), ("uid", "10")]),
+                   "email=foo@bar.com&uid=10");
+        assert_eq!(super::encode(&[("email", "a&role=admin")]),
+                   "email=a%26role%3Dadmin");
+        assert_eq!(super::encode(&[("k", "50% off\x0b")]), "k=50%25%20off%0B");
+        assert_eq!(super::encode::<&str, &str>(&[]), "");
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = pairs(&[("email", "a&role=admin"), ("", "%41"),
+                               ("naïve", "x=y&z"), ("uid", "")]);
+        let encoded = super::encode(&original);
+        for &mode in [Mode::Strict, Mode::Lenient].iter() {
+            assert_eq!(super::decode(&encoded, mode), Ok(original.clone()));
+        }
+    }
+
+    #[test]
+    fn strict() {
+        assert_eq!(super::decode("a=1&b=%3d", Mode::Strict),
+                   Ok(pairs(&[("a", "1"), ("b", "=")])));
+        assert_eq!(super::decode("a=1&b", Mode::Strict),
+                   Err(KvError::MissingEquals(1)));
+        assert_eq!(super::decode("a=1=2", Mode::Strict),
+                   Err(KvError::ExtraEquals(0)));
+        assert_eq!(super::decode("a=1&b=%4", Mode::Strict),
+                   Err(KvError::BadEscape(6)));
+        assert_eq!(super::decode("a=1&b=x y", Mode::Strict),
+                   Err(KvError::Unescaped(7)));
+        assert_eq!(super::decode("a=%ff", Mode::Strict),
+                   Err(KvError::BadUtf8(0)));
+        assert_eq!(super::decode("a=1&", Mode::Strict),
+                   Err(KvError::MissingEquals(1)));
+    }
+
+    #[test]
+    fn lenient() {
+        assert_eq!(super::decode("a=1&&b&c=x=y&d=50%&e=x y", Mode::Lenient),
+                   Ok(pairs(&[("a", "1"), ("b", ""), ("c", "x=y"),
+                              ("d", "50%"), ("e", "x y")])));
+        assert_eq!(super::decode("", Mode::Lenient), Ok(Vec::new()));
+    }
+}
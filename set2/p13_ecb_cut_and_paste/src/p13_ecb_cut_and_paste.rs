@@ -1,15 +1,19 @@
 #![allow(dead_code)]
 extern crate crypto;
 extern crate rand;
-extern crate regex;
 
 mod aes;
+mod cut_and_paste;
+mod kv;
 mod profile;
 
-use profile::{Profile, ProfileError};
+use cut_and_paste::{FieldOracle, Template};
+use kv::Mode;
+use profile::{Encoding, Profile, ProfileError};
 
 struct ProfileOracle {
     key: [u8; 16],
+    encoding: Encoding,
 }
 
 impl ProfileOracle {
@@ -20,55 +24,59 @@ impl ProfileOracle {
         for el in key.iter_mut() {
             *el = rng.gen::<u8>();
         }
-        ProfileOracle { key: key }
+        ProfileOracle { key: key, encoding: Encoding::Strip }
     }
 
-    fn with_key(key: &[u8; 16]) -> ProfileOracle {
-        ProfileOracle { key: key.clone() }
+    fn with_key(key: &[u8; 16], encoding: Encoding) -> ProfileOracle {
+        ProfileOracle { key: key.clone(), encoding }
+    }
+
+    fn mode(&self) -> Mode {
+        match self.encoding {
+            Encoding::Strip => Mode::Lenient,
+            Encoding::Escape => Mode::Strict,
+        }
     }
 
     fn is_admin(&self, ciphertext: &[u8]) -> bool {
-        match Profile::decrypt(ciphertext, &self.key) {
+        match self.get_profile(ciphertext) {
             Ok(p) => p.is_admin(),
             Err(_) => false,
         }
     }
 
     fn profile_for(&self, email: &str) -> Vec<u8> {
-        Profile::profile_for(email).encrypt(&self.key)
+        let profile = match self.encoding {
+            Encoding::Strip => Profile::profile_for(email),
+            Encoding::Escape => Profile::escaped_profile_for(email),
+        };
+        profile.encrypt(&self.key, self.encoding)
     }
 
     fn get_profile(&self, ciphertext: &[u8]) -> Result<Profile, ProfileError> {
-        Profile::decrypt(ciphertext, &self.key)
+        Profile::decrypt(ciphertext, &self.key, self.mode())
     }
 }
 
-fn gain_admin(oracle: &ProfileOracle) -> Profile {
-    use std::iter::repeat;
-    // step one: get a ciphertext block corresponding to "admin[pkcs7_pad...]"
-
-    // this is the required email length to align the block
-    let admin_block_email_len = 16-"email=".len();
-    let admin_block_pad_len = 16 - "admin".len();
-    let admin_block_email = repeat('A').take(admin_block_email_len).chain(
-        "admin".chars()
-    ).chain(
-        repeat(admin_block_pad_len as u8 as char).take(admin_block_pad_len)
-    ).collect::<String>();
-    let admin_block_profile = oracle.profile_for(&admin_block_email[..]);
-    let admin_block = admin_block_profile.chunks(16).nth(1).unwrap();
-
-    // step two: get a ciphertext block corresponding to an email of proper
-    // length to align the above admin block
-    let email_len = 16 - ("email=&uid=10&role=".len() % 16);
-    let email = repeat('A').take(email_len).collect::<String>();
-    let email_block_profile = oracle.profile_for(&email[..]);
-    let email_block = email_block_profile.iter().take(32);
+impl FieldOracle for ProfileOracle {
+    fn encrypt(&self, fields: &[Vec<u8>]) -> Vec<u8> {
+        self.profile_for(&String::from_utf8_lossy(&fields[0]))
+    }
+}
 
-    let ciphertext = email_block.into_iter().chain(admin_block.into_iter())
-                                .map(|c| *c).collect::<Vec<u8>>();
+fn gain_admin(oracle: &ProfileOracle) -> Result<Profile, String> {
+    let template = Template::new(&[b"email=", b"&uid=10&role=user"])
+                       .forbid(b"&=");
+    // the email's length puts "role=" at the end of a block, so that "admin"
+    // can be cut from the start of one
+    let email_len = 16 - ("email=&uid=10&role=".len() % 16);
+    let email = "A".repeat(email_len);
+    let target = format!("email={}&uid=10&role=admin", email);
+    let ciphertext = cut_and_paste::cut_and_paste(oracle, &template,
+                                                  target.as_bytes(), 16)
+                                    .map_err(|e| e.to_string())?;
     println!("Submitting ciphertext: {}", format_hex(&ciphertext[..]));
-    oracle.get_profile(&ciphertext[..]).unwrap()
+    oracle.get_profile(&ciphertext[..]).map_err(|e| e.to_string())
 }
 
 fn format_hex(hex: &[u8]) -> String {
@@ -82,9 +90,39 @@ fn format_hex(hex: &[u8]) -> String {
 
 fn main() {
     let oracle = ProfileOracle::new();
-    let profile = gain_admin(&oracle);
+    let profile = match gain_admin(&oracle) {
+        Ok(profile) => profile,
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    };
     println!("Profile:");
     println!("\temail: {}", profile.email);
     println!("\tuid: {}", profile.uid);
     println!("\trole: {}", profile.role);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ProfileOracle;
+    use profile::Encoding;
+
+    #[test]
+    fn gain_admin() {
+        let oracle = ProfileOracle::with_key(b"yellow submarine",
+                                             Encoding::Strip);
+        let profile = super::gain_admin(&oracle).unwrap();
+        assert!(profile.is_admin());
+        assert_eq!(profile.uid, 10);
+    }
+
+    #[test]
+    fn escaping_stops_gain_admin() {
+        //the padding bytes after "admin" get escaped, so that block is
+        //never made
+        let oracle = ProfileOracle::with_key(b"yellow submarine",
+                                             Encoding::Escape);
+        assert!(super::gain_admin(&oracle).is_err());
+    }
+}
@@ -2,8 +2,9 @@ use std::fmt::{Display, Formatter};
 use std::fmt;
 use std::str::FromStr;
 use std::error::Error;
-use regex::Regex;
 use aes;
+use kv;
+use kv::{KvError, Mode};
 
 pub enum Role {
     User,
@@ -19,6 +20,17 @@ impl Display for Role {
     }
 }
 
+///How a profile is written out before encryption
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    ///`&` and `=` are stripped from the email, and everything else written as
+    ///is. This loses data, and leaves the rest of the email free for
+    ///cut-and-paste attacks.
+    Strip,
+    ///Percent-escaped, which round-trips
+    Escape,
+}
+
 pub struct Profile {
     pub email: String,
     pub uid: u64,
@@ -31,6 +43,11 @@ impl Profile {
         Profile { email: cleaned_email, uid: 10, role: Role::User }
     }
 
+    ///Like `profile_for`, but keeps the email exactly as given
+    pub fn escaped_profile_for(email: &str) -> Profile {
+        Profile { email: email.to_string(), uid: 10, role: Role::User }
+    }
+
     pub fn is_admin(&self) -> bool {
         match self.role {
             Role::Admin => true,
@@ -38,16 +55,58 @@ impl Profile {
         }
     }
 
-    pub fn encrypt(&self, key: &[u8]) -> Vec<u8> {
-        aes::aes_ecb_encrypt(format!("{}", &self).as_bytes(), key)
+    pub fn encode(&self, encoding: Encoding) -> String {
+        match encoding {
+            Encoding::Strip => format!("{}", self),
+            Encoding::Escape => kv::encode(&[("email", self.email.clone()),
+                                             ("uid", self.uid.to_string()),
+                                             ("role", self.role.to_string())]),
+        }
     }
 
-    pub fn decrypt(ciphertext: &[u8], key: &[u8]) -> Result<Profile, ProfileError> {
+    ///Strict decoding wants exactly the keys `email`, `uid` and `role`, once
+    ///each. Lenient decoding ignores other keys, and takes the last of any
+    ///repeats.
+    pub fn decode(s: &str, mode: Mode) -> Result<Profile, ProfileError> {
+        let (mut email, mut uid, mut role) = (None, None, None);
+        for (key, value) in kv::decode(s, mode)? {
+            let slot = match &key[..] {
+                "email" => &mut email,
+                "uid" => &mut uid,
+                "role" => &mut role,
+                _ if mode == Mode::Lenient => continue,
+                _ => return Err(ProfileError::UnknownKey(key)),
+            };
+            if slot.is_some() && mode == Mode::Strict {
+                return Err(ProfileError::RepeatedKey(key));
+            }
+            *slot = Some(value);
+        }
+        let email = email.ok_or(ProfileError::MissingKey("email"))?;
+        let uid = uid.ok_or(ProfileError::MissingKey("uid"))?;
+        let uid = match uid.parse::<u64>() {
+            Ok(uid) => uid,
+            Err(_) => return Err(ProfileError::BadUid(uid)),
+        };
+        let role = match &role.ok_or(ProfileError::MissingKey("role"))?[..] {
+            "user" => Role::User,
+            "admin" => Role::Admin,
+            other => return Err(ProfileError::BadRole(other.to_string())),
+        };
+        Ok(Profile { email, uid, role })
+    }
+
+    pub fn encrypt(&self, key: &[u8], encoding: Encoding) -> Vec<u8> {
+        aes::aes_ecb_encrypt(self.encode(encoding).as_bytes(), key)
+    }
+
+    pub fn decrypt(ciphertext: &[u8], key: &[u8], mode: Mode)
+            -> Result<Profile, ProfileError> {
         use crypto::symmetriccipher::SymmetricCipherError;
         match aes::aes_ecb_decrypt(ciphertext, key) {
             Ok(v) =>  match String::from_utf8(v) {
-                Ok(s) => Profile::from_str(&s[..]),
-                Err(e) => Err(ProfileError::BadParse(format!("{}", e))),
+                Ok(s) => Profile::decode(&s[..], mode),
+                Err(_) => Err(ProfileError::BadUtf8),
             },
             Err(e) => match e {
                 SymmetricCipherError::InvalidLength =>
@@ -60,44 +119,59 @@ impl Profile {
 }
 
 impl Display for Profile {
+    ///Writes the profile as `Encoding::Strip` does
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let email = self.email.chars().filter(|&c| c != '&' && c != '=')
+                              .collect::<String>();
         let repr = format!("email={}&uid={}&role={}",
-                           self.email, self.uid, self.role);
+                           email, self.uid, self.role);
         formatter.write_str(&repr[..])
     }
 }
 
 impl FromStr for Profile {
     type Err = ProfileError;
+    ///Decodes leniently, so anything written by `Encoding::Strip` parses
     fn from_str(s: &str) -> Result<Self, ProfileError> {
-        let re = Regex::new(r"email=([^&=]*)&uid=(\d+)&role=(user|admin)").unwrap();
-        if let Some(caps) = re.captures(s) {
-            let email = caps.at(1).unwrap();
-            let uid = caps.at(2).unwrap().parse::<u64>().unwrap();
-            let role = match caps.at(3).unwrap() {
-                "user" => Role::User,
-                "admin" => Role::Admin,
-                _ => panic!("This regex (Profile::from_str()) doesn't work at all!"),
-            };
-            Ok(Profile {email: email.to_string(), uid: uid, role: role})
-        }
-        else {
-            Err(ProfileError::BadParse(format!("Could not parse {}", s)))
-        }
+        Profile::decode(s, Mode::Lenient)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ProfileError {
-    BadParse(String),
+    Kv(KvError),
+    MissingKey(&'static str),
+    UnknownKey(String),
+    RepeatedKey(String),
+    BadUid(String),
+    BadRole(String),
+    BadUtf8,
     BadDecrypt(String),
 }
 
+impl From<KvError> for ProfileError {
+    fn from(e: KvError) -> ProfileError {
+        ProfileError::Kv(e)
+    }
+}
+
 impl Display for ProfileError {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
-        let err = match self {
-            &ProfileError::BadParse(ref s) => format!("Bad Profile Parse: {}", s),
-            &ProfileError::BadDecrypt(ref s) =>
+        let err = match *self {
+            ProfileError::Kv(ref e) => format!("Bad Profile Parse: {}", e),
+            ProfileError::MissingKey(key) =>
+                format!("Bad Profile Parse: no {}", key),
+            ProfileError::UnknownKey(ref key) =>
+                format!("Bad Profile Parse: unknown key {:?}", key),
+            ProfileError::RepeatedKey(ref key) =>
+                format!("Bad Profile Parse: repeated key {:?}", key),
+            ProfileError::BadUid(ref uid) =>
+                format!("Bad Profile Parse: bad uid {:?}", uid),
+            ProfileError::BadRole(ref role) =>
+                format!("Bad Profile Parse: bad role {:?}", role),
+            ProfileError::BadUtf8 =>
+                "Bad Profile Parse: invalid UTF-8".to_string(),
+            ProfileError::BadDecrypt(ref s) =>
                 format!("Bad Profile Decryption: {}", s),
         };
         formatter.write_str(&err[..])
@@ -106,10 +180,67 @@ impl Display for ProfileError {
 
 impl Error for ProfileError {
     fn description(&self) -> &str {
-        match self {
-            &ProfileError::BadParse(_) => "Bad Profile Parse",
-            &ProfileError::BadDecrypt(_) => "Bad Profile Decrpytion",
+        match *self {
+            ProfileError::Kv(_) => "Bad Profile Parse",
+            ProfileError::MissingKey(_) => "Profile missing a key",
+            ProfileError::UnknownKey(_) => "Profile has an unknown key",
+            ProfileError::RepeatedKey(_) => "Profile has a repeated key",
+            ProfileError::BadUid(_) => "Bad Profile uid",
+            ProfileError::BadRole(_) => "Bad Profile role",
+            ProfileError::BadUtf8 => "Profile isn't valid UTF-8",
+            ProfileError::BadDecrypt(_) => "Bad Profile Decrpytion",
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use kv::{KvError, Mode};
+    use super::{Encoding, Profile, ProfileError};
+
+    #[test]
+    fn round_trip() {
+        let profile = Profile::escaped_profile_for("eve@x.com&role=admin");
+        let encoded = profile.encode(Encoding::Escape);
+        assert_eq!(encoded, "email=eve@x.com%26role%3Dadmin&uid=10&role=user");
+        let decoded = Profile::decode(&encoded, Mode::Strict).unwrap();
+        assert_eq!(decoded.email, "eve@x.com&role=admin");
+        assert_eq!(decoded.uid, 10);
+        assert!(!decoded.is_admin());
+    }
+
+    #[test]
+    fn strip() {
+        let profile = Profile::profile_for("eve@x.com&role=admin");
+        assert_eq!(profile.encode(Encoding::Strip),
+                   "email=eve@x.comroleadmin&uid=10&role=user");
+        let decoded = profile.encode(Encoding::Strip).parse::<Profile>()
+                             .unwrap();
+        assert_eq!(decoded.email, "eve@x.comroleadmin");
+    }
+
+    #[test]
+    fn decode_errors() {
+        let decode = |s: &str| Profile::decode(s, Mode::Strict).err();
+        assert_eq!(decode("email=a&uid=10"),
+                   Some(ProfileError::MissingKey("role")));
+        assert_eq!(decode("email=a&uid=10&role=user&x=1"),
+                   Some(ProfileError::UnknownKey("x".to_string())));
+        assert_eq!(decode("email=a&uid=10&role=user&role=admin"),
+                   Some(ProfileError::RepeatedKey("role".to_string())));
+        assert_eq!(decode("email=a&uid=ten&role=user"),
+                   Some(ProfileError::BadUid("ten".to_string())));
+        assert_eq!(decode("email=a&uid=10&role=root"),
+                   Some(ProfileError::BadRole("root".to_string())));
+        assert_eq!(decode("email=a b&uid=10&role=user"),
+                   Some(ProfileError::Kv(KvError::Unescaped(7))));
+    }
+
+    #[test]
+    fn lenient() {
+        let profile = Profile::decode("email=a b&uid=10&role=user&x=1&\
+                                       role=admin", Mode::Lenient).unwrap();
+        assert_eq!(profile.email, "a b");
+        assert!(profile.is_admin());
+    }
+}
@@ -0,0 +1,386 @@
+//!Byte-at-a-time decryption of a secret which an ECB oracle appends to
+//!attacker-controlled input, with or without a prefix before it. The prefix
+//!may be fixed, or drawn afresh for every call.
+
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use ecb;
+
+pub trait EcbOracle {
+    ///Encrypts `input`, along with whatever the oracle puts around it
+    fn encrypt(&self, input: &[u8]) -> Vec<u8>;
+}
+
+impl<F: Fn(&[u8]) -> Vec<u8>> EcbOracle for F {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+///What the attack found out
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    pub block_size: usize,
+    ///Length of whatever the oracle puts before the input, or `None` if it
+    ///changes on every call
+    pub prefix_len: Option<usize>,
+    ///Whatever the oracle puts after the input
+    pub secret: Vec<u8>,
+    ///Number of calls made to the oracle
+    pub queries: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AttackError {
+    NoBlockSize,
+    NotEcb,
+    ///Nothing matched secret byte `n`
+    NoMatch(usize),
+    ///The marker blocks never lined up with a changing prefix
+    LostSync,
+}
+
+impl Display for AttackError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            AttackError::NoBlockSize =>
+                formatter.write_str("Couldn't find the block size"),
+            AttackError::NotEcb => formatter.write_str("Oracle isn't ECB"),
+            AttackError::NoMatch(n) =>
+                write!(formatter, "Nothing matched secret byte {}", n),
+            AttackError::LostSync =>
+                formatter.write_str("Couldn't line up the marker blocks"),
+        }
+    }
+}
+
+impl Error for AttackError {
+    fn description(&self) -> &str {
+        match *self {
+            AttackError::NoBlockSize => "Couldn't find the block size",
+            AttackError::NotEcb => "Oracle isn't ECB",
+            AttackError::NoMatch(_) => "Nothing matched a secret byte",
+            AttackError::LostSync => "Couldn't line up the marker blocks",
+        }
+    }
+}
+
+///Largest block size looked for
+const MAX_BLOCK_SIZE: usize = 256;
+
+///Calls made with a changing prefix to find the block size
+const LENGTH_SAMPLES: usize = 32;
+
+///Calls made with a changing prefix per block of possible alignments, before
+///giving up on lining up the markers
+const SYNC_TRIES: usize = 64;
+
+///Bytes which fill the two marker blocks
+const MARKERS: [u8; 2] = [0xaa, 0x55];
+
+///Something the attack can ask to encrypt its input, which might fail
+trait Query {
+    fn query(&self, input: &[u8]) -> Result<Vec<u8>, AttackError>;
+}
+
+///Wraps an oracle, counting calls to it
+struct Counted<'a, O: 'a + ?Sized> {
+    oracle: &'a O,
+    queries: Cell<usize>,
+}
+
+impl<'a, O: EcbOracle + ?Sized> Query for Counted<'a, O> {
+    fn query(&self, input: &[u8]) -> Result<Vec<u8>, AttackError> {
+        self.queries.set(self.queries.get() + 1);
+        Ok(self.oracle.encrypt(input))
+    }
+}
+
+///Hides a prefix which changes on every call. The input goes after two marker
+///blocks, and calls are repeated until the prefix happens to end on a block
+///boundary, which the encrypted markers show. Only what comes after them is
+///given back, so it looks like there's no prefix at all.
+struct Resynced<'a, Q: 'a + ?Sized> {
+    oracle: &'a Q,
+    block_size: usize,
+    ///The markers, encrypted
+    encrypted_markers: Vec<Vec<u8>>,
+}
+
+impl<'a, Q: Query + ?Sized> Resynced<'a, Q> {
+    fn new(oracle: &'a Q, block_size: usize)
+            -> Result<Resynced<'a, Q>, AttackError> {
+        //three blocks of marker always give two whole blocks, wherever the
+        //prefix ends
+        let mut encrypted_markers = Vec::new();
+        for &marker in MARKERS.iter() {
+            let ciphertext = oracle.query(&vec![marker; 3 * block_size])?;
+            let blocks = ciphertext.chunks(block_size).collect::<Vec<_>>();
+            match blocks.windows(2).find(|pair| pair[0] == pair[1]) {
+                Some(pair) => encrypted_markers.push(pair[0].to_vec()),
+                None => return Err(AttackError::NotEcb),
+            }
+        }
+        Ok(Resynced { oracle, block_size, encrypted_markers })
+    }
+}
+
+impl<'a, Q: Query + ?Sized> Query for Resynced<'a, Q> {
+    fn query(&self, input: &[u8]) -> Result<Vec<u8>, AttackError> {
+        let block_size = self.block_size;
+        let mut marked = Vec::with_capacity(2 * block_size + input.len());
+        for &marker in MARKERS.iter() {
+            marked.extend(vec![marker; block_size]);
+        }
+        marked.extend_from_slice(input);
+        for _ in 0..SYNC_TRIES * block_size {
+            let ciphertext = self.oracle.query(&marked)?;
+            //the prefix can't end with both markers, since the second block
+            //would then have some of the first marker in it
+            let found = ciphertext.chunks(block_size).collect::<Vec<_>>()
+                                  .windows(2)
+                                  .position(|pair| {
+                                      pair[0] == &self.encrypted_markers[0][..]
+                                      && pair[1] == &self.encrypted_markers[1][..]
+                                  });
+            if let Some(idx) = found {
+                return Ok(ciphertext[(idx + 2) * block_size..].to_vec());
+            }
+        }
+        Err(AttackError::LostSync)
+    }
+}
+
+///Recovers everything `oracle` puts after the input. What it puts before the
+///input may be fixed, or change on every call.
+pub fn attack<O: EcbOracle + ?Sized>(oracle: &O) -> Result<Report, AttackError> {
+    let oracle = Counted { oracle, queries: Cell::new(0) };
+    //ECB with a fixed prefix gives the same ciphertext every time
+    let report = if oracle.query(&[])? == oracle.query(&[])? {
+        attack_fixed(&oracle)?
+    } else {
+        attack_changing(&oracle)?
+    };
+    Ok(Report { queries: oracle.queries.get(), ..report })
+}
+
+fn attack_fixed<Q: Query + ?Sized>(oracle: &Q) -> Result<Report, AttackError> {
+    let (block_size, surrounding_len) = find_block_size(oracle)?;
+    if !is_ecb(oracle, block_size)? {
+        return Err(AttackError::NotEcb);
+    }
+    let prefix_len = find_prefix_len(oracle, block_size)?;
+    let secret = decrypt(oracle, block_size, prefix_len,
+                         surrounding_len - prefix_len)?;
+    Ok(Report {
+        block_size,
+        prefix_len: Some(prefix_len),
+        secret,
+        queries: 0,
+    })
+}
+
+fn attack_changing<Q: Query + ?Sized>(oracle: &Q)
+        -> Result<Report, AttackError> {
+    let block_size = find_changing_block_size(oracle)?;
+    if !is_ecb(oracle, block_size)? {
+        return Err(AttackError::NotEcb);
+    }
+    let resynced = Resynced::new(oracle, block_size)?;
+    let (_, secret_len) = find_block_size(&resynced)?;
+    let secret = decrypt(&resynced, block_size, 0, secret_len)?;
+    Ok(Report {
+        block_size,
+        prefix_len: None,
+        secret,
+        queries: 0,
+    })
+}
+
+///Gives the block size, and the combined length of the prefix and secret. The
+///ciphertext grows by a whole block as soon as the input fills out the padding.
+fn find_block_size<Q: Query + ?Sized>(oracle: &Q)
+        -> Result<(usize, usize), AttackError> {
+    let base_len = oracle.query(&[])?.len();
+    for input_len in 1..MAX_BLOCK_SIZE + 1 {
+        let len = oracle.query(&vec![0; input_len])?.len();
+        if len > base_len {
+            //without padding, there's no telling the surrounding length
+            return base_len.checked_sub(input_len)
+                           .map(|surrounding_len| (len - base_len,
+                                                   surrounding_len))
+                           .ok_or(AttackError::NoBlockSize);
+        }
+    }
+    Err(AttackError::NoBlockSize)
+}
+
+///Gives the block size when the prefix changes length on every call, as the
+///greatest common divisor of a sample of ciphertext lengths
+fn find_changing_block_size<Q: Query + ?Sized>(oracle: &Q)
+        -> Result<usize, AttackError> {
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+    let mut block_size = 0;
+    for _ in 0..LENGTH_SAMPLES {
+        block_size = gcd(block_size, oracle.query(&[])?.len());
+    }
+    if block_size == 0 || block_size > MAX_BLOCK_SIZE {
+        return Err(AttackError::NoBlockSize);
+    }
+    Ok(block_size)
+}
+
+///True if three blocks of the same input give two matching ciphertext blocks
+fn is_ecb<Q: Query + ?Sized>(oracle: &Q, block_size: usize)
+        -> Result<bool, AttackError> {
+    let ciphertext = oracle.query(&vec![0; 3 * block_size])?;
+    Ok(ecb::detect_ecb(&ciphertext, block_size))
+}
+
+///Finds the prefix length by watching where the input's last byte lands. The
+///first block which changes with that byte moves on by one just as the input
+///fills out the prefix's last block.
+fn find_prefix_len<Q: Query + ?Sized>(oracle: &Q, block_size: usize)
+        -> Result<usize, AttackError> {
+    let changed_block = |fill_len: usize| -> Result<usize, AttackError> {
+        let mut first = vec![0; fill_len + 1];
+        let mut second = first.clone();
+        first[fill_len] = 0;
+        second[fill_len] = 1;
+        Ok(first_difference(oracle.query(&first)?.chunks(block_size),
+                            oracle.query(&second)?.chunks(block_size)))
+    };
+    let start = changed_block(0)?;
+    for fill_len in 1..block_size {
+        if changed_block(fill_len)? != start {
+            return Ok(start * block_size + block_size - fill_len);
+        }
+    }
+    Ok(start * block_size)
+}
+
+///Gives the index of the first place `a` and `b` differ
+fn first_difference<T, I, J>(a: I, b: J) -> usize
+        where T: PartialEq, I: Iterator<Item=T>, J: Iterator<Item=T> {
+    a.zip(b).take_while(|(x, y)| x == y).count()
+}
+
+///Recovers `secret_len` bytes of secret, one at a time. Each byte is shifted to
+///the end of a block whose other bytes are known, and that block is looked up
+///in a dictionary of all 256 possibilities, built with a single query.
+fn decrypt<Q: Query + ?Sized>(oracle: &Q, block_size: usize,
+                              prefix_len: usize, secret_len: usize)
+        -> Result<Vec<u8>, AttackError> {
+    //fill out the prefix's last block, so the input starts on a block
+    let align = (block_size - prefix_len % block_size) % block_size;
+    let start_block = (prefix_len + align) / block_size;
+    //the target ciphertexts only depend on how far the secret is shifted
+    let shifted = (0..block_size).map(|shift| {
+        oracle.query(&vec![0; align + shift])
+    }).collect::<Result<Vec<_>, _>>()?;
+    //the known bytes before each secret byte, starting with the shift's zeros
+    let mut known = vec![0u8; block_size - 1];
+    for idx in 0..secret_len {
+        let ciphertext = &shifted[block_size - 1 - idx % block_size];
+        let target_block = start_block + idx / block_size;
+        let target = &ciphertext[target_block * block_size..
+                                 (target_block + 1) * block_size];
+        let window = known[idx..].to_vec();
+        let mut dictionary = vec![0; align];
+        for guess in 0..256usize {
+            dictionary.extend_from_slice(&window);
+            dictionary.push(guess as u8);
+        }
+        let encrypted = oracle.query(&dictionary)?;
+        let found = encrypted[start_block * block_size..]
+                        .chunks(block_size)
+                        .take(256)
+                        .position(|block| block == target);
+        match found {
+            Some(guess) => known.push(guess as u8),
+            None => return Err(AttackError::NoMatch(idx)),
+        }
+    }
+    Ok(known.split_off(block_size - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use aes;
+    use super::AttackError;
+
+    const KEY: &[u8] = b"yellow submarine";
+
+    fn oracle<'a>(prefix: &'a [u8], secret: &'a [u8])
+            -> impl Fn(&[u8]) -> Vec<u8> + 'a {
+        move |input: &[u8]| {
+            let plaintext = prefix.iter().chain(input.iter())
+                                  .chain(secret.iter()).cloned()
+                                  .collect::<Vec<u8>>();
+            aes::aes_ecb_encrypt(&plaintext, KEY)
+        }
+    }
+
+    #[test]
+    fn attack() {
+        let secret = b"Rollin' in my 5.0\nWith my rag-top down so my hair can blow";
+        for prefix_len in [0, 1, 15, 16, 17, 255].iter() {
+            let prefix = vec![0; *prefix_len];
+            let report = super::attack(&oracle(&prefix, secret)).unwrap();
+            assert_eq!(report.block_size, 16);
+            assert_eq!(report.prefix_len, Some(*prefix_len));
+            assert_eq!(report.secret, secret.to_vec());
+            //one query per byte, plus a few to get started
+            assert!(report.queries < secret.len() + 80);
+        }
+    }
+
+    #[test]
+    fn attack_changing_prefix() {
+        use rand::Rng;
+        let secret = b"Rollin' in my 5.0\nWith my rag-top down so my hair can blow";
+        let oracle = |input: &[u8]| {
+            let mut rng = ::rand::thread_rng();
+            let prefix_len = rng.gen_range(0, 64);
+            let plaintext = rng.gen_iter::<u8>().take(prefix_len)
+                               .chain(input.iter().cloned())
+                               .chain(secret.iter().cloned())
+                               .collect::<Vec<u8>>();
+            aes::aes_ecb_encrypt(&plaintext, KEY)
+        };
+        let report = super::attack(&oracle).unwrap();
+        assert_eq!(report.block_size, 16);
+        assert_eq!(report.prefix_len, None);
+        assert_eq!(report.secret, secret.to_vec());
+    }
+
+    #[test]
+    fn attack_empty_secret() {
+        let report = super::attack(&oracle(b"prefix", b"")).unwrap();
+        assert_eq!(report.prefix_len, Some(6));
+        assert!(report.secret.is_empty());
+    }
+
+    #[test]
+    fn not_ecb() {
+        //mixing each block's index in first means equal blocks never match
+        let oracle = |input: &[u8]| {
+            let mixed = input.iter().chain(b"secret".iter()).enumerate()
+                             .map(|(idx, &byte)| byte ^ (idx / 16) as u8)
+                             .collect::<Vec<u8>>();
+            aes::aes_ecb_encrypt(&mixed, KEY)
+        };
+        assert_eq!(super::attack(&oracle), Err(AttackError::NotEcb));
+    }
+
+    #[test]
+    fn no_block_size() {
+        let oracle = |_: &[u8]| vec![0; 16];
+        assert_eq!(super::attack(&oracle), Err(AttackError::NoBlockSize));
+    }
+}
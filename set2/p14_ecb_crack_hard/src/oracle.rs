@@ -1,4 +1,5 @@
 use aes;
+use ecb_attack::EcbOracle;
 
 use rand;
 
@@ -6,6 +7,8 @@ pub struct AesEcbOracle {
     key: [u8; 16],
     base_str: Vec<u8>,
     prefix: Vec<u8>,
+    ///If set, a new prefix is drawn for every call instead
+    fresh_prefix: bool,
 }
 
 impl AesEcbOracle {
@@ -20,6 +23,14 @@ impl AesEcbOracle {
             key: key,
             base_str: Vec::from(base_str),
             prefix: AesEcbOracle::generate_prefix(),
+            fresh_prefix: false,
+        }
+    }
+    ///Like `new`, but with a new random prefix for every call to `encrypt`
+    pub fn with_fresh_prefix(base_str: &[u8]) -> AesEcbOracle {
+        AesEcbOracle {
+            fresh_prefix: true,
+            ..AesEcbOracle::new(base_str)
         }
     }
     #[cfg(test)]
@@ -29,11 +40,19 @@ impl AesEcbOracle {
             key: key.clone(),
             base_str: Vec::from(base_str),
             prefix: prefix.to_vec(),
+            fresh_prefix: false,
         }
     }
     pub fn encrypt(&self, user_controlled: &[u8]) -> Vec<u8> {
+        let fresh;
+        let prefix = if self.fresh_prefix {
+            fresh = AesEcbOracle::generate_prefix();
+            &fresh
+        } else {
+            &self.prefix
+        };
         let text: Vec<u8> =
-            self.prefix.iter().cloned().chain(
+            prefix.iter().cloned().chain(
                 user_controlled.iter().cloned()
             ).chain(
                 self.base_str.iter().cloned()
@@ -52,6 +71,12 @@ impl AesEcbOracle {
     }
 }
 
+impl EcbOracle for AesEcbOracle {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        AesEcbOracle::encrypt(self, input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::AesEcbOracle;
@@ -77,4 +102,24 @@ mod tests {
                         0xdb, 0xf4, 0xcc, 0xf6, 0xc1, 0xf5, 0xeb];
         assert_eq!(&output[..], expected);
     }
+
+    #[test]
+    fn fresh_prefix() {
+        let fixed = AesEcbOracle::new(b"world");
+        assert_eq!(fixed.encrypt(b"hello "), fixed.encrypt(b"hello "));
+        //two prefixes of the same length and bytes are vanishingly unlikely
+        let fresh = AesEcbOracle::with_fresh_prefix(b"world");
+        assert!(fresh.encrypt(b"hello ") != fresh.encrypt(b"hello "));
+    }
+
+    #[test]
+    fn attack_fresh_prefix() {
+        use ecb_attack;
+        let secret = b"Rollin' in my 5.0\nWith my rag-top down so my hair can blow";
+        let oracle = AesEcbOracle::with_fresh_prefix(secret);
+        let report = ecb_attack::attack(&oracle).unwrap();
+        assert_eq!(report.block_size, 16);
+        assert_eq!(report.prefix_len, None);
+        assert_eq!(report.secret, secret.to_vec());
+    }
 }
@@ -1,12 +1,32 @@
+///Gives 0xff if `x` is 0, otherwise 0x00
+fn zero_mask(x: u8) -> u8 {
+    //the top bit of (x - 1) & !x is set only when x is 0
+    ((((x as u16).wrapping_sub(1) & !(x as u16)) >> 8) as u8 & 1)
+        .wrapping_neg()
+}
+
+///Gives 0xff if `a` is less than `b`, otherwise 0x00
+fn lt_mask(a: u8, b: u8) -> u8 {
+    (((a as u16).wrapping_sub(b as u16) >> 8) as u8 & 1).wrapping_neg()
+}
+
+///True if `bytes` ends in valid PKCS7 padding, otherwise false. The last 255
+///bytes are all looked at whatever the padding byte, so the time taken doesn't
+///say where the padding went wrong.
 fn valid_pkcs7_padding(bytes: &[u8]) -> bool {
-    if let Some(last_byte) = bytes.last() {
-        *last_byte != 0 &&
-            bytes.len() >= *last_byte as usize &&
-            bytes[(bytes.len() - *last_byte as usize)..].iter().all(|b| b==last_byte)
-    }
-    else {
-        false
+    let pad = match bytes.last() {
+        Some(&last_byte) => last_byte,
+        None => return false,
+    };
+    let window = &bytes[bytes.len().saturating_sub(255)..];
+    //pad must be 1..=window.len()
+    let mut good = !zero_mask(pad) & !lt_mask(window.len() as u8, pad);
+    for (idx, &byte) in window.iter().rev().enumerate() {
+        //the last `pad` bytes must all equal `pad`
+        let in_pad = lt_mask(idx as u8, pad);
+        good &= !in_pad | zero_mask(byte ^ pad);
     }
+    good == 0xff
 }
 
 #[test]
@@ -33,6 +53,12 @@ fn correct_pad() {
     assert_eq!(valid_pkcs7_padding(test), true);
 }
 
+#[test]
+fn long_pad() {
+    assert!(valid_pkcs7_padding(&[0xff; 255]));
+    assert!(!valid_pkcs7_padding(&[0xff; 254]));
+}
+
 fn check(s: &str, expected: bool) {
     print!("Checking {:?}: ", s);
     match valid_pkcs7_padding(s.as_bytes()) == expected {
@@ -2,24 +2,23 @@ extern crate crypto;
 extern crate rand;
 
 mod aes;
+mod inject;
 mod oracle;
 use oracle::Oracle;
 
 fn gain_admin(oracle: &Oracle) -> Vec<u8>{
-    use std::iter::repeat;
-    // this attack isn't super general, but it will work here because the target
-    // area is not spread out over multiple blocks
     let blocksize = 16;
     let injection = "user=admin";
-    let aaa = repeat('A').take(injection.len()).collect::<String>();
-    let canvas = oracle.encrypt(&aaa[..]);
     let prefix = "comment1=cooking%20MCs;userdata="; //length = 32
-    let target_idx = prefix.len() - blocksize;
-    let mut paint = canvas.clone();
-    for idx in 0..injection.len() {
-        paint[target_idx + idx] ^= injection.as_bytes()[idx] ^ ('A' as u8);
-    }
-    paint
+    // enough As for a whole block to scramble, followed by the injection
+    let aaa = "A".repeat(3 * blocksize);
+    let canvas = oracle.encrypt(&aaa[..]);
+    let offset = inject::harmless_offset(prefix.len(), prefix.len() + aaa.len(),
+                                         injection.len(), blocksize).unwrap();
+    let known = &aaa.as_bytes()[..injection.len()];
+    inject::inject(&canvas, known, injection.as_bytes(), offset,
+                   inject::Mode::Cbc { block_size: blocksize })
+        .unwrap().ciphertext
 }
 
 fn format_hex(hex: &[u8]) -> String {
@@ -37,3 +36,14 @@ fn main() {
     println!("Submitting ciphertext: {}", format_hex(&admin_ciphertext[..]));
     println!("Admin: {}", oracle.is_admin(&admin_ciphertext[..]));
 }
+
+#[cfg(test)]
+mod tests {
+    use oracle::Oracle;
+
+    #[test]
+    fn gain_admin() {
+        let oracle = Oracle::new();
+        assert!(oracle.is_admin(&super::gain_admin(&oracle)));
+    }
+}
@@ -63,6 +63,34 @@ pub fn aes_cbc_decrypt(ciphertext: &[u8], key: &[u8], iv: &[u8]) ->
     Ok(final_result)
 }
 
+/// This function decrypts `ciphertext` using CBC mode AES, using `key`, and
+/// leaves any padding in place. `ciphertext`'s length must be a multiple of
+/// 128 bits.
+pub fn aes_cbc_decrypt_unpadded(ciphertext: &[u8], key: &[u8], iv: &[u8]) ->
+        Result<Vec<u8>, SymmetricCipherError> {
+    let mut decryptor = aes::cbc_decryptor(
+        aes::KeySize::KeySize128,
+        key,
+        iv,
+        blockmodes::NoPadding);
+    let mut final_result = Vec::<u8>::new();
+    let mut read_buffer = buffer::RefReadBuffer::new(ciphertext);
+    let mut buffer = [0; 4096];
+    let mut write_buffer = buffer::RefWriteBuffer::new(&mut buffer);
+    loop {
+        let result = try!(
+            decryptor.decrypt(&mut read_buffer, &mut write_buffer, true)
+        );
+        final_result.extend(write_buffer.take_read_buffer().take_remaining()
+                            .iter().cloned());
+        match result {
+            BufferResult::BufferUnderflow => break,
+            BufferResult::BufferOverflow => { }
+        }
+    }
+    Ok(final_result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::aes_cbc_encrypt;
@@ -91,6 +119,18 @@ mod tests {
         assert_eq!(&output[..], expected);
     }
 
+    #[test]
+    fn decrypt_unpadded() {
+        let ciphertext = [224, 191, 66, 17, 60, 77, 69, 210, 210, 160, 37, 129,
+                          90, 239, 119, 37];
+        let key = "yellow submarine".as_bytes();
+        let iv = "the 1st 16 bytes".as_bytes();
+        let output = super::aes_cbc_decrypt_unpadded(&ciphertext, key, iv)
+                         .unwrap();
+        let expected = "hello world\x05\x05\x05\x05\x05".as_bytes();
+        assert_eq!(&output[..], expected);
+    }
+
     #[test]
     fn decrypt_invalid_length() {
         //ciphertext is only 15 bytes
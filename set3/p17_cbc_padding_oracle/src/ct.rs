@@ -0,0 +1,213 @@
+//!Constant-time helpers. Nothing here branches or indexes on secret data, so
+//!the time taken depends only on the lengths of the inputs.
+
+///Gives 0xff if `x` is 0, otherwise 0x00
+fn zero_mask(x: u8) -> u8 {
+    //the top bit of (x - 1) & !x is set only when x is 0
+    ((((x as u16).wrapping_sub(1) & !(x as u16)) >> 8) as u8 & 1)
+        .wrapping_neg()
+}
+
+///Gives 0xff if `a` is less than `b`, otherwise 0x00
+#[allow(dead_code)]
+fn lt_mask(a: u8, b: u8) -> u8 {
+    (((a as u16).wrapping_sub(b as u16) >> 8) as u8 & 1).wrapping_neg()
+}
+
+///True if `first` and `second` are equal, otherwise false. The time taken
+///depends on their lengths, but not on where they differ.
+pub fn eq(first: &[u8], second: &[u8]) -> bool {
+    if first.len() != second.len() {
+        return false;
+    }
+    let diff = first.iter().zip(second.iter())
+                    .fold(0u8, |diff, (x, y)| diff | (x ^ y));
+    zero_mask(diff) == 0xff
+}
+
+///Gives `a` if `choice` is true, otherwise `b`, without branching on `choice`
+#[allow(dead_code)]
+pub fn select(choice: bool, a: u8, b: u8) -> u8 {
+    let mask = (choice as u8).wrapping_neg();
+    (a & mask) | (b & !mask)
+}
+
+///True if `message` ends in valid PKCS7 padding for `block_size`, otherwise
+///false. Every byte of the last block is looked at, whatever the padding.
+#[allow(dead_code)]
+pub fn pkcs7_padding_valid(message: &[u8], block_size: usize) -> bool {
+    if block_size == 0 || block_size > 255 || message.is_empty()
+            || !message.len().is_multiple_of(block_size) {
+        return false;
+    }
+    let last_block = &message[message.len() - block_size..];
+    let pad = last_block[block_size - 1];
+    //pad must be 1..=block_size
+    let mut good = !zero_mask(pad) & !lt_mask(block_size as u8, pad);
+    for (idx, &byte) in last_block.iter().rev().enumerate() {
+        //the last `pad` bytes must all equal `pad`
+        let in_pad = lt_mask(idx as u8, pad);
+        good &= !in_pad | zero_mask(byte ^ pad);
+    }
+    good == 0xff
+}
+
+///Checks and strips PKCS7 padding from `message` without branching on its
+///contents. Gives whether the padding was valid, and the length of the
+///unpadded message, which is 0 if it wasn't.
+#[allow(dead_code)]
+pub fn pkcs7_unpad(message: &[u8], block_size: usize) -> (bool, usize) {
+    let valid = pkcs7_padding_valid(message, block_size);
+    if message.is_empty() {
+        return (false, 0);
+    }
+    let pad = message[message.len() - 1] as usize;
+    //wrapping, since pad may be bogus and is masked out below anyway
+    let len = message.len().wrapping_sub(pad);
+    let mask = (valid as usize).wrapping_neg();
+    (valid, len & mask)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn eq() {
+        assert!(super::eq(b"yellow submarine", b"yellow submarine"));
+        assert!(!super::eq(b"yellow submarine", b"yellow_submarine"));
+        assert!(!super::eq(b"yellow submarine", b"Yellow submarine"));
+        assert!(!super::eq(b"yellow submarine", b"yellow submarin"));
+        assert!(super::eq(b"", b""));
+    }
+
+    #[test]
+    fn select() {
+        assert_eq!(super::select(true, 0x12, 0x34), 0x12);
+        assert_eq!(super::select(false, 0x12, 0x34), 0x34);
+    }
+
+    #[test]
+    fn masks() {
+        for x in 0..256 {
+            let expected = if x == 0 { 0xff } else { 0 };
+            assert_eq!(super::zero_mask(x as u8), expected);
+            for y in 0..256 {
+                let expected = if x < y { 0xff } else { 0 };
+                assert_eq!(super::lt_mask(x as u8, y as u8), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn pkcs7_padding_valid() {
+        assert!(super::pkcs7_padding_valid(b"ICE ICE BABY\x04\x04\x04\x04", 16));
+        assert!(super::pkcs7_padding_valid(&[16u8; 16], 16));
+        assert!(super::pkcs7_padding_valid(b"ICE ICE BABY ICE\x01", 17));
+        assert!(!super::pkcs7_padding_valid(b"ICE ICE BABY\x05\x05\x05\x05",
+                                            16));
+        assert!(!super::pkcs7_padding_valid(b"ICE ICE BABY\x01\x02\x03\x04",
+                                            16));
+        assert!(!super::pkcs7_padding_valid(b"ICE ICE BABY ICE", 16));
+        assert!(!super::pkcs7_padding_valid(&[17u8; 16], 16));
+        assert!(!super::pkcs7_padding_valid(b"ICE ICE BABY\x04\x04\x04", 16));
+        assert!(!super::pkcs7_padding_valid(b"", 16));
+    }
+
+    #[test]
+    fn pkcs7_unpad() {
+        let message = b"ICE ICE BABY\x04\x04\x04\x04";
+        assert_eq!(super::pkcs7_unpad(message, 16), (true, 12));
+        assert_eq!(super::pkcs7_unpad(&[16u8; 32], 16), (true, 16));
+        assert_eq!(super::pkcs7_unpad(b"ICE ICE BABY\x05\x05\x05\x05", 16),
+                   (false, 0));
+        assert_eq!(super::pkcs7_unpad(&[0xffu8; 16], 16), (false, 0));
+        assert_eq!(super::pkcs7_unpad(b"", 16), (false, 0));
+    }
+
+    //Welch's t statistic for the difference in means of `a` and `b`
+    fn welch_t(a: &[f64], b: &[f64]) -> f64 {
+        let mean = |xs: &[f64]| xs.iter().sum::<f64>() / xs.len() as f64;
+        let var = |xs: &[f64], m: f64| {
+            xs.iter().map(|x| (x - m) * (x - m)).sum::<f64>()
+                / (xs.len() - 1) as f64
+        };
+        let (mean_a, mean_b) = (mean(a), mean(b));
+        (mean_a - mean_b)
+            / (var(a, mean_a) / a.len() as f64
+               + var(b, mean_b) / b.len() as f64).sqrt()
+    }
+
+    //Times `f` on `first` and `second`, giving the t statistic
+    //of the difference. Outliers past the 90th percentile are dropped, since
+    //they are mostly scheduling noise.
+    //Both inputs are copied into the same buffer before timing, since
+    //inputs at different addresses alone can differ by t = 100 or more.
+    fn timing_t<F>(f: F, first: &[u8], second: &[u8], samples: usize) -> f64
+            where F: Fn(&[u8]) -> bool {
+        use std::hint::black_box;
+        use std::time::Instant;
+        //calls per sample, so each one is well above the timer's resolution
+        const CALLS: usize = 1000;
+        let mut times = [Vec::new(), Vec::new()];
+        //the classes are picked at random, so drift in the machine's speed
+        //lands on both evenly
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut buffer = first.to_vec();
+        for _ in 0..CALLS {
+            black_box(f(black_box(&buffer[..])));
+        }
+        for _ in 0..2 * samples {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let (which, input) = if state & 1 == 0 { (0, first) }
+                                 else { (1, second) };
+            buffer.copy_from_slice(input);
+            let input = &buffer[..];
+            let start = Instant::now();
+            for _ in 0..CALLS {
+                black_box(f(black_box(input)));
+            }
+            times[which].push(start.elapsed().as_nanos() as f64);
+        }
+        for class in times.iter_mut() {
+            class.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let keep = class.len() * 9 / 10;
+            class.truncate(keep);
+        }
+        welch_t(&times[0], &times[1])
+    }
+
+    //The timing tests measure wall-clock time, so they only mean anything with
+    //optimizations on and an otherwise idle machine. Run them explicitly with
+    //`cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn eq_timing() {
+        //dudect's rule of thumb: |t| above 10 means a leak
+        let secret = [0x5au8; 64];
+        let mut late = secret;
+        late[63] ^= 1;
+        let mut early = secret;
+        early[0] ^= 1;
+        let t = timing_t(|input| super::eq(&secret, input), &early, &late,
+                         50000);
+        assert!(t.abs() < 10.0, "eq leaks timing: t = {}", t);
+    }
+
+    #[test]
+    #[ignore]
+    fn harness_catches_leaks() {
+        let secret = [0x5au8; 64];
+        let mut late = secret;
+        late[63] ^= 1;
+        let mut early = secret;
+        early[0] ^= 1;
+        let t = timing_t(|input| {
+            for (x, y) in secret.iter().zip(input.iter()) {
+                if ::std::hint::black_box(x != y) { return false; }
+            }
+            true
+        }, &early, &late, 50000);
+        assert!(t.abs() > 10.0, "harness missed a leak: t = {}", t);
+    }
+}
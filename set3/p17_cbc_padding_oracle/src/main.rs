@@ -3,83 +3,517 @@ extern crate rand;
 extern crate rustc_serialize;
 
 mod cbc;
+mod ct;
 mod oracle;
+mod padding_oracle;
+mod score;
 
-fn crack_block(oracle: &oracle::Oracle, block: &[u8], previous: &[u8]) -> Vec<u8> {
-    let mut decrypted = vec![0u8; block.len()]; //fill with ones for xoring
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
+
+use padding_oracle::PaddingOracle;
+
+#[derive(Debug)]
+enum AttackError {
+    InvalidLength,
+    NoSolution,
+    Oracle(io::Error),
+}
+
+impl PartialEq for AttackError {
+    ///Oracle errors are equal if they are of the same kind
+    fn eq(&self, other: &AttackError) -> bool {
+        match (self, other) {
+            (AttackError::InvalidLength, AttackError::InvalidLength) => true,
+            (AttackError::NoSolution, AttackError::NoSolution) => true,
+            (AttackError::Oracle(a), AttackError::Oracle(b)) =>
+                a.kind() == b.kind(),
+            _ => false,
+        }
+    }
+}
+
+impl From<io::Error> for AttackError {
+    fn from(e: io::Error) -> AttackError {
+        AttackError::Oracle(e)
+    }
+}
+
+impl Display for AttackError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            AttackError::InvalidLength => formatter.write_str(
+                "Ciphertext isn't a whole number of IV-sized blocks"),
+            AttackError::NoSolution =>
+                formatter.write_str("No guess gave valid padding"),
+            AttackError::Oracle(ref e) =>
+                write!(formatter, "Couldn't ask the oracle: {}", e),
+        }
+    }
+}
+
+impl Error for AttackError {
+    fn description(&self) -> &str {
+        match *self {
+            AttackError::InvalidLength =>
+                "Ciphertext isn't a whole number of IV-sized blocks",
+            AttackError::NoSolution => "No guess gave valid padding",
+            AttackError::Oracle(_) => "Couldn't ask the oracle",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            AttackError::Oracle(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+///Decrypts `block` using `oracle`, with `previous` as the block before it.
+///Guesses are tried in order of how likely they are: English text first, or
+///padding if `last` says this is the final block.
+fn crack_block<O>(oracle: &O, block: &[u8], previous: &[u8], last: bool)
+        -> Result<Vec<u8>, AttackError> where O: PaddingOracle + ?Sized {
+    //some oracles wrongly accept a padding byte of 0, which `confirm_last`
+    //can't catch. If the last byte leads nowhere, try again without it.
+    let mut rejected = Vec::new();
+    loop {
+        match crack_block_from(oracle, block, previous, last, &rejected)? {
+            Ok(decrypted) => return Ok(decrypted),
+            Err(Some(last_byte)) => rejected.push(last_byte),
+            Err(None) => return Err(AttackError::NoSolution),
+        }
+    }
+}
+
+///Does the work for `crack_block`, never guessing anything in `rejected` for
+///the last byte. On failure, gives the last byte it had guessed, if any. The
+///outer `Result` is for errors asking `oracle`.
+fn crack_block_from<O>(oracle: &O, block: &[u8], previous: &[u8], last: bool,
+                       rejected: &[u8])
+        -> io::Result<Result<Vec<u8>, Option<u8>>>
+        where O: PaddingOracle + ?Sized {
+    let english = score::by_frequency();
+    let mut decrypted = vec![0u8; block.len()]; //fill with zeros for xoring
     //crack block one byte at a time, from the end
-    let mut idx = 0; //index from end
-    let mut start = 0; //starting guess, gets changed on backtrack
-    while idx < block.len() {
+    for idx in 0..block.len() { //index from end
         let target_idx = block.len()-idx-1;
-        //create xor pad
-        let mut pad = vec![0u8; block.len()];
-        for pad_idx in target_idx..block.len() {
-            pad[pad_idx] = idx as u8 + 1;
+        //create submission basis, which is sent as the iv. It has
+        //padding of idx + 1 wherever the bytes are known.
+        let pad = idx as u8 + 1;
+        let mut submission: Vec<u8> =
+            previous.iter().zip(decrypted.iter()).enumerate()
+                    .map(|(fidx, (p, d))| if fidx > target_idx { p ^ d ^ pad }
+                                          else { *p })
+                    .collect();
+        submission[target_idx] ^= pad;
+        //put likely padding bytes first in the last block
+        let mut guesses = Vec::with_capacity(256 + block.len());
+        if last {
+            let known_pad = *decrypted.last().unwrap() as usize;
+            if idx == 0 {
+                guesses.extend((1..block.len() + 1).map(|x| x as u8));
+            } else if idx < known_pad {
+                guesses.push(known_pad as u8);
+            }
         }
-        let mut first_block = Vec::with_capacity(block.len());
-        for fidx in 0..block.len() { //fidx = first block index
-            first_block.push(previous[fidx] ^ pad[fidx] ^ decrypted[fidx]);
+        //the padding bytes are English too, so don't ask about them twice
+        let rest: Vec<u8> = english.iter().cloned()
+                                   .filter(|guess| !guesses.contains(guess))
+                                   .collect();
+        guesses.extend(rest);
+        if idx == 0 {
+            guesses.retain(|guess| !rejected.contains(guess));
         }
-        //create submission basis
-        let mut submission: Vec<u8> =
-            first_block.iter().cloned().chain(block.iter().cloned()).collect();
-        //loop over possible bytes
         let mut found = false;
-        for guess in (start as usize..256).map(|x| x as u8) {
+        for &guess in guesses.iter() {
             submission[target_idx] ^= guess; //xor in guessed byte
-            if oracle.valid_padding(&submission[..]) {
+            if oracle.check(&submission[..], block)?
+                    && (idx != 0 || confirm_last(oracle, &submission, block)?) {
                 decrypted[target_idx] = guess; //update decrypted
                 found = true;
                 break;
             }
             submission[target_idx] ^= guess; //clean up if not found
         }
-        if found { //if you found a good number continue
-            idx += 1;
-            start = 0;
-        }
-        else { //otherwise backtrack
-            if idx==0 {
-                panic!("No solution");
-            }
-            idx = 0; //go back to the start
-            start = decrypted.last().unwrap() + 1; //start where you left off
-            decrypted = vec![0; decrypted.len()]; //zero out decrypted
+        if !found {
+            return Ok(Err(if idx == 0 { None }
+                          else { Some(*decrypted.last().unwrap()) }));
         }
     }
-    decrypted
+    Ok(Ok(decrypted))
 }
 
-fn cbc_padding_attack(oracle: &oracle::Oracle) -> Vec<u8> {
-    //encoded using AES-128
-    let blocksize = 16;
-    //get ciphertext
-    let ciphertext = oracle.get();
-    //set up result vector
+///Checks that good padding from `submission` really ended in `\x01`, and not
+///something like `\x02\x02`, by changing the second to last byte and asking
+///again
+fn confirm_last<O>(oracle: &O, submission: &[u8], block: &[u8])
+        -> io::Result<bool> where O: PaddingOracle + ?Sized {
+    if submission.len() < 2 {
+        return Ok(true);
+    }
+    let mut changed = submission.to_vec();
+    changed[submission.len() - 2] ^= 1;
+    oracle.check(&changed[..], block)
+}
+
+///Decrypts `ciphertext`, which was encrypted in CBC mode under `iv`, using
+///`oracle`. The block size is taken to be the length of `iv`. The result still
+///has its padding.
+fn cbc_padding_attack<O>(oracle: &O, iv: &[u8], ciphertext: &[u8])
+        -> Result<Vec<u8>, AttackError>
+        where O: PaddingOracle + Sync + ?Sized {
+    let blocksize = iv.len();
+    if blocksize == 0 || ciphertext.is_empty()
+            || !ciphertext.len().is_multiple_of(blocksize) {
+        return Err(AttackError::InvalidLength);
+    }
+    //pair each block with the one before it, starting with the iv
+    let first = Some(iv).into_iter().chain(ciphertext.chunks(blocksize));
+    let second = ciphertext.chunks(blocksize);
+    let pairs: Vec<(&[u8], &[u8])> = first.zip(second).collect();
+    //crack blocks in parallel, each worker taking the next block left. The
+    //first error stops them all.
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let threads = MAX_THREADS.min(pairs.len());
+    let mut cracked = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| scope.spawn(|| {
+            let mut cracked = Vec::new();
+            loop {
+                if failed.load(Ordering::SeqCst) {
+                    return cracked;
+                }
+                let idx = next.fetch_add(1, Ordering::SeqCst);
+                if idx >= pairs.len() {
+                    return cracked;
+                }
+                let (previous, block) = pairs[idx];
+                let last = idx + 1 == pairs.len();
+                let result = crack_block(oracle, block, previous, last);
+                if result.is_err() {
+                    failed.store(true, Ordering::SeqCst);
+                }
+                cracked.push((idx, result));
+            }
+        })).collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap())
+               .collect::<Vec<_>>()
+    });
+    cracked.sort_by_key(|&(idx, _)| idx);
     let mut decrypted = Vec::new();
-    //iterate over pairs of blocks
-    let first = ciphertext.chunks(blocksize);
-    let second = ciphertext.chunks(blocksize).skip(1);
-    for (previous, block) in first.zip(second) {
-        let decrypted_block = crack_block(oracle, block, previous);
-        decrypted.extend(decrypted_block);
-    }
-    decrypted
+    for (_, block) in cracked {
+        decrypted.extend(block?);
+    }
+    Ok(decrypted)
+}
+
+///Makes a ciphertext which decrypts to `plaintext`, using `oracle` and its
+///cipher's `blocksize`. Gives the iv and the ciphertext. This works backwards
+///from a random last block: each block's decryption is cracked as if the
+///block before it were all zeros, and the block before it is then chosen to
+///turn that into the plaintext wanted.
+///
+///There's no ciphertext to take the block size from, and finding it through
+///`oracle` would cost up to 256 queries for every size ruled out, so it has
+///to be given.
+fn forge_ciphertext<O>(oracle: &O, blocksize: usize, plaintext: &[u8])
+        -> Result<(Vec<u8>, Vec<u8>), AttackError>
+        where O: PaddingOracle + ?Sized {
+    use rand::Rng;
+    //PKCS7 can only pad blocks of up to 255 bytes
+    if blocksize == 0 || blocksize > 255 {
+        return Err(AttackError::InvalidLength);
+    }
+    let pad = blocksize - plaintext.len() % blocksize;
+    let padded: Vec<u8> = plaintext.iter().cloned()
+                                   .chain(std::iter::repeat_n(pad as u8, pad))
+                                   .collect();
+    let zeros = vec![0u8; blocksize];
+    let mut blocks = vec![rand::thread_rng().gen_iter::<u8>().take(blocksize)
+                                            .collect::<Vec<u8>>()];
+    for plain_block in padded.chunks(blocksize).rev() {
+        let intermediate = crack_block(oracle, blocks.last().unwrap(), &zeros,
+                                       false)?;
+        let previous = intermediate.iter().zip(plain_block.iter())
+                                   .map(|(i, p)| i ^ p)
+                                   .collect();
+        blocks.push(previous);
+    }
+    blocks.reverse();
+    let iv = blocks.remove(0);
+    Ok((iv, blocks.concat()))
+}
+
+///Most blocks cracked at once. Queries are mostly spent waiting on the oracle,
+///so this can be more than the number of cores.
+const MAX_THREADS: usize = 16;
+
+///Block size forged for when none is given, as for AES
+const DEFAULT_BLOCK_SIZE: usize = 16;
+
+const USAGE: &str = "\
+Usage: p17_cbc_padding_oracle <file> [--encrypt-then-mac]
+       p17_cbc_padding_oracle <file> --forge <plaintext>
+       p17_cbc_padding_oracle --http <host:port> <path> <action>
+       p17_cbc_padding_oracle --process <command> <action>
+where <action> is <iv hex> <ct hex> to decrypt, or
+--forge <plaintext> [block size] to encrypt (the block size defaults to 16)";
+
+enum Action {
+    Decrypt(Vec<u8>, Vec<u8>),
+    Forge(Vec<u8>, usize),
+}
+
+fn parse_action(args: &[&str]) -> Option<Action> {
+    use rustc_serialize::hex::FromHex;
+    match *args {
+        ["--forge", plaintext] => Some(Action::Forge(plaintext.as_bytes()
+                                                              .to_vec(),
+                                                     DEFAULT_BLOCK_SIZE)),
+        ["--forge", plaintext, blocksize] => match blocksize.parse() {
+            Ok(blocksize) => Some(Action::Forge(plaintext.as_bytes().to_vec(),
+                                                blocksize)),
+            Err(_) => None,
+        },
+        [iv, ct] => match (iv.from_hex(), ct.from_hex()) {
+            (Ok(iv), Ok(ct)) => Some(Action::Decrypt(iv, ct)),
+            _ => None,
+        },
+        _ => None,
+    }
 }
 
 fn main() {
     let args : Vec<String> = std::env::args().collect();
-    if args.len()!=2 {
-        println!("Invalid number of args!");
-        return;
-    }
-    let filename = &args[1][..];
-    let oracle = oracle::Oracle::new(filename);
-    let decrypted = cbc_padding_attack(&oracle);
-    let output = match String::from_utf8(decrypted.clone()) {
-        Ok(result) => format!("{}", result),
-        Err(_) => format!("Error: couldn't convert {:?}", decrypted),
+    let args: Vec<&str> = args.iter().map(|arg| &arg[..]).collect();
+    let (oracle, action): (Box<dyn PaddingOracle + Sync>, Option<Action>) =
+            match &args[1..] {
+        ["--http", addr, path, rest @ ..] => {
+            (Box::new(padding_oracle::HttpOracle::new(addr, path)),
+             parse_action(rest))
+        },
+        ["--process", command, rest @ ..] => {
+            match padding_oracle::ProcessOracle::spawn("sh", &["-c", command]) {
+                Ok(process) => (Box::new(process), parse_action(rest)),
+                Err(e) => {
+                    println!("Couldn't start {:?}: {}", command, e);
+                    return;
+                },
+            }
+        },
+        [filename, "--forge", plaintext] => {
+            let oracle = oracle::Oracle::new(filename, oracle::Mode::Padding);
+            (Box::new(oracle),
+             Some(Action::Forge(plaintext.as_bytes().to_vec(),
+                                oracle::BLOCK_SIZE)))
+        },
+        [filename] | [filename, "--encrypt-then-mac"] => {
+            let mode = if args.len() == 3 { oracle::Mode::EncryptThenMac }
+                       else { oracle::Mode::Padding };
+            let oracle = oracle::Oracle::new(filename, mode);
+            let mut ciphertext = oracle.get();
+            let iv = ciphertext.drain(..oracle::BLOCK_SIZE).collect();
+            (Box::new(oracle), Some(Action::Decrypt(iv, ciphertext)))
+        },
+        _ => {
+            println!("{}", USAGE);
+            return;
+        },
     };
-    println!("{}", output);
+    let oracle = padding_oracle::CountingOracle::new(&*oracle);
+    let start = Instant::now();
+    let bytes = match action {
+        Some(Action::Decrypt(iv, ciphertext)) => {
+            let decrypted = match cbc_padding_attack(&oracle, &iv[..],
+                                                     &ciphertext[..]) {
+                Ok(decrypted) => decrypted,
+                Err(e) => {
+                    println!("Attack failed after {} queries: {}",
+                             oracle.queries(), e);
+                    return;
+                },
+            };
+            let output = match String::from_utf8(decrypted.clone()) {
+                Ok(result) => result,
+                Err(_) => format!("Error: couldn't convert {:?}", decrypted),
+            };
+            println!("{}", output);
+            decrypted.len()
+        },
+        Some(Action::Forge(plaintext, blocksize)) => {
+            use rustc_serialize::hex::ToHex;
+            match forge_ciphertext(&oracle, blocksize, &plaintext[..]) {
+                Ok((iv, ciphertext)) => {
+                    println!("{} {}", iv.to_hex(), ciphertext.to_hex());
+                    ciphertext.len()
+                },
+                Err(e) => {
+                    println!("Attack failed after {} queries: {}",
+                             oracle.queries(), e);
+                    return;
+                },
+            }
+        },
+        None => {
+            println!("{}", USAGE);
+            return;
+        },
+    };
+    println!("{} queries for {} bytes ({:.1} per byte) in {:.2?}",
+             oracle.queries(), bytes,
+             oracle.queries() as f64 / bytes.max(1) as f64, start.elapsed());
+}
+
+#[cfg(test)]
+mod tests {
+    use ct;
+    use oracle::{Oracle, Mode};
+    use super::AttackError;
+
+    #[test]
+    fn cbc_padding_attack() {
+        use rustc_serialize::base64::FromBase64;
+        use std::io::prelude::*;
+        use std::fs::File;
+        let mut contents = String::new();
+        File::open("input.txt").unwrap().read_to_string(&mut contents)
+            .unwrap();
+        let lines: Vec<Vec<u8>> = contents.lines()
+                                          .map(|line| line.from_base64()
+                                                          .unwrap())
+                                          .collect();
+        let oracle = Oracle::new("input.txt", Mode::Padding);
+        let ciphertext = oracle.get();
+        let decrypted = super::cbc_padding_attack(&oracle, &ciphertext[..16],
+                                                  &ciphertext[16..]).unwrap();
+        let (valid, len) = ct::pkcs7_unpad(&decrypted[..], 16);
+        assert!(valid);
+        assert!(lines.contains(&decrypted[..len].to_vec()));
+    }
+
+    #[test]
+    fn cbc_padding_attack_closure() {
+        use cbc;
+        let key = b"YELLOW SUBMARINE";
+        let iv = b"the 1st 16 bytes";
+        let ciphertext = cbc::aes_cbc_encrypt(b"attack at dawn", key, iv);
+        let oracle = |iv: &[u8], ct: &[u8]| {
+            cbc::aes_cbc_decrypt(ct, key, iv).is_ok()
+        };
+        assert_eq!(super::cbc_padding_attack(&oracle, iv, &ciphertext[..]),
+                   Ok(b"attack at dawn\x02\x02".to_vec()));
+        assert_eq!(super::cbc_padding_attack(&oracle, iv, &ciphertext[1..]),
+                   Err(AttackError::InvalidLength));
+    }
+
+    #[test]
+    fn cbc_padding_attack_queries() {
+        use cbc;
+        use padding_oracle::CountingOracle;
+        let key = b"YELLOW SUBMARINE";
+        let iv = b"the 1st 16 bytes";
+        let plaintext = b"Now that the party is jumping, with the bass kicked in";
+        let ciphertext = cbc::aes_cbc_encrypt(plaintext, key, iv);
+        let oracle = |iv: &[u8], ct: &[u8]| {
+            cbc::aes_cbc_decrypt(ct, key, iv).is_ok()
+        };
+        let counting = CountingOracle::new(&oracle);
+        let decrypted = super::cbc_padding_attack(&counting, iv,
+                                                  &ciphertext[..]).unwrap();
+        assert_eq!(&decrypted[..plaintext.len()], &plaintext[..]);
+        //trying guesses in order 0..256 takes around 128 per byte
+        assert!(counting.queries() < 30 * ciphertext.len(),
+                "{} queries", counting.queries());
+    }
+
+    #[test]
+    fn crack_block_no_repeats() {
+        use cbc;
+        use std::sync::Mutex;
+        let key = b"YELLOW SUBMARINE";
+        let iv = b"the 1st 16 bytes";
+        //a block not ending in padding makes every padding guess fail
+        let ciphertext = cbc::aes_cbc_encrypt(b"YELLOW SUBMARINE", key, iv);
+        let asked = Mutex::new(Vec::new());
+        let oracle = |iv: &[u8], ct: &[u8]| {
+            asked.lock().unwrap().push(iv.to_vec());
+            cbc::aes_cbc_decrypt(ct, key, iv).is_ok()
+        };
+        let decrypted = super::crack_block(&oracle, &ciphertext[..16], iv,
+                                           true).unwrap();
+        assert_eq!(decrypted, b"YELLOW SUBMARINE".to_vec());
+        let mut asked = asked.into_inner().unwrap();
+        let queries = asked.len();
+        asked.sort();
+        asked.dedup();
+        assert_eq!(asked.len(), queries);
+    }
+
+    #[test]
+    fn forge_ciphertext() {
+        use cbc;
+        let key = [0x42; 16];
+        let oracle = Oracle::controlled(&key, &[0; 16], "input.txt");
+        for plaintext in [&b""[..], b"attack at dawn",
+                          b";admin=true;comment=exactly 32 b"].iter() {
+            let (iv, ciphertext) =
+                super::forge_ciphertext(&oracle, 16, plaintext).unwrap();
+            assert_eq!(iv.len(), 16);
+            assert_eq!(cbc::aes_cbc_decrypt(&ciphertext, &key, &iv).unwrap(),
+                       plaintext.to_vec());
+        }
+        assert_eq!(super::forge_ciphertext(&oracle, 0, b"attack at dawn"),
+                   Err(AttackError::InvalidLength));
+        let oracle = Oracle::new("input.txt", Mode::EncryptThenMac);
+        assert!(super::forge_ciphertext(&oracle, 16, b"attack at dawn")
+                    .is_err());
+    }
+
+    #[test]
+    fn cbc_padding_attack_encrypt_then_mac() {
+        let oracle = Oracle::new("input.txt", Mode::EncryptThenMac);
+        let ciphertext = oracle.get();
+        assert_eq!(super::cbc_padding_attack(&oracle, &ciphertext[..16],
+                                             &ciphertext[16..]),
+                   Err(AttackError::NoSolution));
+    }
+
+    #[test]
+    fn oracle_errors() {
+        use std::io;
+        use std::net::TcpListener;
+        use padding_oracle::{CountingOracle, HttpOracle, PaddingOracle};
+        struct Broken;
+        impl PaddingOracle for Broken {
+            fn check(&self, _: &[u8], _: &[u8]) -> io::Result<bool> {
+                Err(io::Error::new(io::ErrorKind::ConnectionReset, "broken"))
+            }
+        }
+        assert_eq!(super::cbc_padding_attack(&Broken, &[0; 16], &[0; 32]),
+                   Err(AttackError::Oracle(
+                       io::Error::from(io::ErrorKind::ConnectionReset))));
+        //the first error stops the workers taking any more blocks
+        let counting = CountingOracle::new(&Broken);
+        assert!(super::cbc_padding_attack(&counting, &[0; 16], &[0; 1600])
+                    .is_err());
+        assert!(counting.queries() <= super::MAX_THREADS,
+                "{} queries", counting.queries());
+        assert_eq!(super::forge_ciphertext(&Broken, 16, b"attack at dawn"),
+                   Err(AttackError::Oracle(
+                       io::Error::from(io::ErrorKind::ConnectionReset))));
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr()
+                       .unwrap().to_string();
+        let oracle = HttpOracle::new(&addr, "/oracle");
+        match super::cbc_padding_attack(&oracle, &[0; 16], &[0; 16]) {
+            Err(AttackError::Oracle(_)) => {},
+            other => panic!("Expected an oracle error, got {:?}", other),
+        }
+    }
 }
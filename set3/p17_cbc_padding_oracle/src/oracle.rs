@@ -1,26 +1,46 @@
 use cbc;
+use ct;
+use padding_oracle::LocalOracle;
 use rand;
 
 use std::io;
 
+///Block size of the oracle's cipher, AES
+pub const BLOCK_SIZE: usize = 16;
+
+///How the oracle protects its ciphertexts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    ///Plain CBC, so bad padding can be told apart from good
+    Padding,
+    ///CBC, then an HMAC-SHA256 tag over the IV and ciphertext, which is checked
+    ///before anything is decrypted
+    EncryptThenMac,
+}
+
 pub struct Oracle {
     key: [u8; 16],
+    mac_key: [u8; 16],
     iv: [u8; 16],
     filename: String,
+    mode: Mode,
 }
 
 impl Oracle {
-    pub fn new(filename: &str) -> Oracle {
+    pub fn new(filename: &str, mode: Mode) -> Oracle {
         Oracle {
             key: random_block(),
+            mac_key: random_block(),
             iv: random_block(),
             filename: filename.to_string(),
+            mode,
         }
     }
 
     #[cfg(test)]
     pub fn controlled(key : &[u8; 16], iv: &[u8; 16], filename: &str) -> Oracle {
-        Oracle { key: key.clone(), iv: iv.clone(), filename: filename.to_string() }
+        Oracle { key: key.clone(), mac_key: key.clone(), iv: iv.clone(),
+                 filename: filename.to_string(), mode: Mode::Padding }
     }
 
     pub fn get(&self) -> Vec<u8> {
@@ -29,17 +49,60 @@ impl Oracle {
             Err(e) => panic!("Error reading {}: {}", self.filename, e),
         };
         let encrypted = cbc::aes_cbc_encrypt(&text[..], &self.key, &self.iv);
-        self.iv.iter().cloned().chain(encrypted.iter().cloned()).collect()
+        let mut result: Vec<u8> = self.iv.iter().cloned()
+                                      .chain(encrypted.iter().cloned())
+                                      .collect();
+        if self.mode == Mode::EncryptThenMac {
+            let tag = self.tag(&self.iv, &encrypted[..]);
+            result.extend(tag.iter().cloned());
+        }
+        result
+    }
+
+    ///Gives the HMAC-SHA256 tag of `iv` followed by `encrypted`
+    fn tag(&self, iv: &[u8], encrypted: &[u8]) -> [u8; TAG_LEN] {
+        use crypto::hmac::Hmac;
+        use crypto::mac::Mac;
+        use crypto::sha2::Sha256;
+        let mut hmac = Hmac::new(Sha256::new(), &self.mac_key);
+        hmac.input(iv);
+        hmac.input(encrypted);
+        let mut tag = [0u8; TAG_LEN];
+        hmac.raw_result(&mut tag);
+        tag
     }
+}
 
-    pub fn valid_padding(&self, ciphertext: &[u8]) -> bool {
-        match cbc::aes_cbc_decrypt(ciphertext, &self.key, &self.iv) {
-            Err(cbc::SymmetricCipherError::InvalidPadding) => false,
-            _ => true,
+impl LocalOracle for Oracle {
+    ///In `Mode::Padding`, true if `ct` decrypts to something with valid
+    ///padding. In `Mode::EncryptThenMac`, `ct` must also end in a good tag, and
+    ///anything else is turned down before it is decrypted.
+    fn check(&self, iv: &[u8], ct: &[u8]) -> bool {
+        match self.mode {
+            Mode::Padding => {
+                !matches!(cbc::aes_cbc_decrypt(ct, &self.key, iv),
+                          Err(cbc::SymmetricCipherError::InvalidPadding))
+            },
+            Mode::EncryptThenMac => {
+                if ct.len() < TAG_LEN {
+                    return false;
+                }
+                let (encrypted, tag) = ct.split_at(ct.len() - TAG_LEN);
+                if !ct::eq(&self.tag(iv, encrypted)[..], tag) {
+                    return false;
+                }
+                match cbc::aes_cbc_decrypt_unpadded(encrypted, &self.key, iv) {
+                    Ok(decrypted) =>
+                        ct::pkcs7_unpad(&decrypted[..], BLOCK_SIZE).0,
+                    Err(_) => false,
+                }
+            },
         }
     }
 }
 
+const TAG_LEN: usize = 32;
+
 fn random_block() -> [u8; 16] {
     use rand::Rng;
     let mut rng = rand::thread_rng();
@@ -76,3 +139,24 @@ fn random_base64_line(filename: &str) -> io::Result<Vec<u8>> {
                       ),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Oracle, Mode};
+    use padding_oracle::LocalOracle;
+
+    #[test]
+    fn encrypt_then_mac() {
+        let oracle = Oracle::new("input.txt", Mode::EncryptThenMac);
+        let ciphertext = oracle.get();
+        let iv = &ciphertext[..16];
+        assert!(oracle.check(iv, &ciphertext[16..]));
+        let mut tampered = ciphertext.clone();
+        let len = tampered.len();
+        tampered[len - 33] ^= 1;
+        assert!(!oracle.check(iv, &tampered[16..]));
+        assert!(!oracle.check(iv, &ciphertext[16..len - 1]));
+        assert!(!oracle.check(iv, &ciphertext[16..len - 32]));
+        assert!(!oracle.check(&[0; 16], &ciphertext[16..]));
+    }
+}
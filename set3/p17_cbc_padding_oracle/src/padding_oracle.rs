@@ -0,0 +1,240 @@
+//!Things which can say whether a CBC ciphertext decrypts to valid padding, and
+//!adapters for talking to ones outside this process. Those can fail, so every
+//!answer is an `io::Result`, but oracles inside this process only need to be
+//!a `LocalOracle`.
+
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::TcpStream;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rustc_serialize::hex::ToHex;
+
+pub trait PaddingOracle {
+    ///True if `ct`, decrypted in CBC mode under `iv`, has valid padding.
+    ///Errors if the oracle couldn't be asked.
+    fn check(&self, iv: &[u8], ct: &[u8]) -> io::Result<bool>;
+}
+
+///A padding oracle inside this process, which always answers
+pub trait LocalOracle {
+    ///True if `ct`, decrypted in CBC mode under `iv`, has valid padding
+    fn check(&self, iv: &[u8], ct: &[u8]) -> bool;
+}
+
+impl<F: Fn(&[u8], &[u8]) -> bool> LocalOracle for F {
+    fn check(&self, iv: &[u8], ct: &[u8]) -> bool {
+        self(iv, ct)
+    }
+}
+
+impl<O: LocalOracle + ?Sized> PaddingOracle for O {
+    fn check(&self, iv: &[u8], ct: &[u8]) -> io::Result<bool> {
+        Ok(LocalOracle::check(self, iv, ct))
+    }
+}
+
+///Passes queries on to another oracle, and counts them
+pub struct CountingOracle<'a, O: 'a + ?Sized> {
+    oracle: &'a O,
+    queries: AtomicUsize,
+}
+
+impl<'a, O: PaddingOracle + ?Sized> CountingOracle<'a, O> {
+    pub fn new(oracle: &'a O) -> CountingOracle<'a, O> {
+        CountingOracle { oracle, queries: AtomicUsize::new(0) }
+    }
+
+    ///Gives the number of queries so far
+    pub fn queries(&self) -> usize {
+        self.queries.load(Ordering::SeqCst)
+    }
+}
+
+impl<'a, O: PaddingOracle + ?Sized> PaddingOracle for CountingOracle<'a, O> {
+    fn check(&self, iv: &[u8], ct: &[u8]) -> io::Result<bool> {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        self.oracle.check(iv, ct)
+    }
+}
+
+///Asks an HTTP server, with `GET <path>?iv=<hex>&ct=<hex>`. A 200 response
+///means the padding was valid, and anything else means it wasn't.
+pub struct HttpOracle {
+    addr: String,
+    path: String,
+}
+
+impl HttpOracle {
+    ///`addr` is the server's `host:port`, and `path` the endpoint on it
+    pub fn new(addr: &str, path: &str) -> HttpOracle {
+        HttpOracle { addr: addr.to_string(), path: path.to_string() }
+    }
+}
+
+impl PaddingOracle for HttpOracle {
+    ///Errors if the server can't be reached or gives no status
+    fn check(&self, iv: &[u8], ct: &[u8]) -> io::Result<bool> {
+        let mut stream = TcpStream::connect(&self.addr[..])?;
+        let request = format!("GET {}?iv={}&ct={} HTTP/1.1\r\nHost: {}\r\n\
+                               Connection: close\r\n\r\n",
+                              self.path, iv.to_hex(), ct.to_hex(), self.addr);
+        stream.write_all(request.as_bytes())?;
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        match status_line.split_whitespace().nth(1) {
+            Some(status) => Ok(status == "200"),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                       format!("Bad status line: {:?}",
+                                               status_line))),
+        }
+    }
+}
+
+///Asks a child process, one query per line. Each query is `<iv hex> <ct hex>`
+///on the child's stdin, and it answers `1` on stdout if the padding was valid,
+///or `0` if it wasn't.
+pub struct ProcessOracle {
+    child: Child,
+    pipes: Mutex<(ChildStdin, BufReader<ChildStdout>)>,
+}
+
+impl ProcessOracle {
+    ///Starts `program` with `args`
+    pub fn spawn(program: &str, args: &[&str]) -> io::Result<ProcessOracle> {
+        let mut child = Command::new(program)
+                            .args(args)
+                            .stdin(Stdio::piped())
+                            .stdout(Stdio::piped())
+                            .spawn()?;
+        let pipes = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, BufReader::new(stdout)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe,
+                                          "Oracle process has no pipes"));
+            },
+        };
+        Ok(ProcessOracle { child, pipes: Mutex::new(pipes) })
+    }
+}
+
+impl PaddingOracle for ProcessOracle {
+    ///Errors if the process stops answering, or answers something other than
+    ///`0` or `1`
+    fn check(&self, iv: &[u8], ct: &[u8]) -> io::Result<bool> {
+        let mut pipes = self.pipes.lock().map_err(|_| {
+            io::Error::other("Oracle process lock poisoned")
+        })?;
+        let (ref mut stdin, ref mut stdout) = *pipes;
+        writeln!(stdin, "{} {}", iv.to_hex(), ct.to_hex())?;
+        stdin.flush()?;
+        let mut answer = String::new();
+        if stdout.read_line(&mut answer)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                      "Oracle process exited"));
+        }
+        match answer.trim() {
+            "1" => Ok(true),
+            "0" => Ok(false),
+            other => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                        format!("Bad answer: {:?}", other))),
+        }
+    }
+}
+
+impl Drop for ProcessOracle {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PaddingOracle, CountingOracle, HttpOracle, ProcessOracle};
+
+    #[test]
+    fn closure() {
+        let oracle = |iv: &[u8], ct: &[u8]| iv.len() == ct.len();
+        assert!(oracle.check(&[0; 16], &[1; 16]).unwrap());
+        assert!(!oracle.check(&[0; 16], &[1; 32]).unwrap());
+    }
+
+    #[test]
+    fn counting() {
+        let oracle = |iv: &[u8], _: &[u8]| iv[0] == 0;
+        let counting = CountingOracle::new(&oracle);
+        assert!(counting.check(&[0], &[]).unwrap());
+        assert!(!counting.check(&[1], &[]).unwrap());
+        assert_eq!(counting.queries(), 2);
+    }
+
+    #[test]
+    fn http() {
+        use std::io::prelude::*;
+        use std::io::BufReader;
+        use std::net::TcpListener;
+        use std::thread;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                //skip the headers
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let status = if request_line.starts_with(
+                        "GET /oracle?iv=0001&ct=ff HTTP/1.1") {
+                    "200 OK"
+                } else {
+                    "403 Forbidden"
+                };
+                write!(stream, "HTTP/1.1 {}\r\n\r\n", status).unwrap();
+            }
+        });
+        let oracle = HttpOracle::new(&addr, "/oracle");
+        assert!(oracle.check(&[0, 1], &[0xff]).unwrap());
+        assert!(!oracle.check(&[0, 2], &[0xff]).unwrap());
+    }
+
+    #[test]
+    fn http_unreachable() {
+        use std::net::TcpListener;
+        //bind then drop, so nothing is listening on the port
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr()
+                       .unwrap().to_string();
+        let oracle = HttpOracle::new(&addr, "/oracle");
+        assert!(oracle.check(&[0, 1], &[0xff]).is_err());
+    }
+
+    #[test]
+    fn process() {
+        //says yes to queries with an empty iv
+        let oracle = ProcessOracle::spawn("sh", &["-c", "while read iv ct; do \
+                                                   if [ -z \"$ct\" ]; \
+                                                   then echo 1; \
+                                                   else echo 0; fi; done"])
+                         .unwrap();
+        assert!(oracle.check(&[], &[0xab]).unwrap());
+        assert!(!oracle.check(&[0xcd], &[0xab]).unwrap());
+        assert!(oracle.check(&[], &[0x12, 0x34]).unwrap());
+    }
+
+    #[test]
+    fn process_bad_answers() {
+        let oracle = ProcessOracle::spawn("sh", &["-c", "read line; echo 2"])
+                         .unwrap();
+        assert!(oracle.check(&[], &[0xab]).is_err());
+        assert!(oracle.check(&[], &[0xab]).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+/// Gives every byte, most likely in English text first: lowercase letters and
+/// space by `english_frequency()`, then uppercase letters the same way, then
+/// other printable characters, then whitespace, then everything else.
+pub fn by_frequency() -> Vec<u8> {
+    let tier = |b: u8| match b {
+        b'a'..=b'z' | b' ' => 0,
+        b'A'..=b'Z' => 1,
+        0x21..=0x7e => 2,
+        b'\n' | b'\r' | b'\t' => 3,
+        _ => 4,
+    };
+    let mut bytes: Vec<u8> = (0..256).map(|b| b as u8).collect();
+    bytes.sort_by(|&a, &b| {
+        let freq = |c: u8| if tier(c) < 2 { english_frequency(c as char) }
+                           else { 0f32 };
+        tier(a).cmp(&tier(b))
+               .then(freq(b).partial_cmp(&freq(a)).unwrap())
+               .then(a.cmp(&b))
+    });
+    bytes
+}
+
+/// Gives the expected english frequency of a character.
+fn english_frequency(c: char) -> f32{
+    match c.to_lowercase().next() {
+        Some('a') => 0.0609,
+        Some('b') => 0.0105,
+        Some('c') => 0.0284,
+        Some('d') => 0.0292,
+        Some('e') => 0.1136,
+        Some('f') => 0.0179,
+        Some('g') => 0.0138,
+        Some('h') => 0.0341,
+        Some('i') => 0.0544,
+        Some('j') => 0.0024,
+        Some('k') => 0.0041,
+        Some('l') => 0.0292,
+        Some('m') => 0.0276,
+        Some('n') => 0.0544,
+        Some('o') => 0.0600,
+        Some('p') => 0.0195,
+        Some('q') => 0.0024,
+        Some('r') => 0.0495,
+        Some('s') => 0.0568,
+        Some('t') => 0.0803,
+        Some('u') => 0.0243,
+        Some('v') => 0.0097,
+        Some('w') => 0.0138,
+        Some('x') => 0.0024,
+        Some('y') => 0.0130,
+        Some('z') => 0.0003,
+        Some(' ') => 0.1217,
+        Some(_)   => 0.0657, //"other" case #1
+        None      => 0.0657, //"other" case #2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn by_frequency_test(){
+        let order = by_frequency();
+        assert_eq!(order.len(), 256);
+        assert_eq!(&order[..4], b" eta");
+        assert!(order.iter().position(|&b| b==b'E').unwrap() >
+                order.iter().position(|&b| b==b'z').unwrap());
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..256).map(|b| b as u8).collect::<Vec<u8>>());
+    }
+}
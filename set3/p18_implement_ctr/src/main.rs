@@ -3,6 +3,11 @@ extern crate rustc_serialize;
 
 mod ctr;
 mod aes;
+mod chacha20;
+mod keystream;
+mod salsa20;
+
+use keystream::Keystream;
 
 fn main() {
     use rustc_serialize::base64::FromBase64;
@@ -13,4 +18,26 @@ fn main() {
     let decrypted = ctr.decrypt(&ciphertext[..]).unwrap();
     let s = String::from_utf8(decrypted).unwrap();
     println!("{}", s);
+
+    //any block can be decrypted on its own, by seeking to it
+    ctr.seek(2);
+    let tail = ctr.decrypt(&ciphertext[32..]).unwrap();
+    println!("From block 2: {}", String::from_utf8(tail).unwrap());
+
+    //the stream ciphers work the same way, with 64-byte blocks
+    let long_key = [key, key].concat();
+    let mut salsa = salsa20::Salsa20::new(0, key);
+    let mut chacha = chacha20::ChaCha20::new(0, &long_key);
+    assert!(round_trip(&mut salsa, s.as_bytes()));
+    assert!(round_trip(&mut chacha, s.as_bytes()));
+    println!("Salsa20 and ChaCha20 round trips: ok");
+}
+
+//Encrypts `message` from the start of `cipher`'s keystream, then seeks back
+//and checks that it decrypts again
+fn round_trip(cipher: &mut dyn Keystream, message: &[u8]) -> bool {
+    cipher.seek(0);
+    let encrypted = cipher.encrypt(message).unwrap();
+    cipher.seek(0);
+    cipher.decrypt(&encrypted).unwrap() == message
 }
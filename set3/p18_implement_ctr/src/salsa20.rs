@@ -0,0 +1,201 @@
+//!Salsa20/20 as Bernstein specified it: a 16- or 32-byte key, a 64-bit nonce
+//!and a 64-bit block counter. Implements `Keystream`, like `AesCtr`.
+
+use keystream::{Keystream, KeystreamError};
+
+const SIGMA: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+const TAU: [u32; 4] = [0x61707865, 0x3120646e, 0x79622d36, 0x6b206574];
+
+#[derive(Debug)]
+pub enum Salsa20Error {
+    ExpiredNonce,
+}
+
+pub struct Salsa20 {
+    constants: [u32; 4],
+    key: [u32; 8],
+    nonce: u64,
+    counter: u64,
+    expired: bool,
+}
+
+impl Salsa20 {
+    ///Sets up Salsa20 with a 16- or 32-byte `key`. `nonce` is used in
+    ///little-endian order.
+    pub fn new(nonce: u64, key: &[u8]) -> Salsa20 {
+        assert!(key.len() == 16 || key.len() == 32,
+                "Salsa20 keys are 16 or 32 bytes long");
+        let mut key_words = [0u32; 8];
+        for (idx, word) in key_words.iter_mut().enumerate() {
+            *word = le_word(&key[(4 * idx) % key.len()..]);
+        }
+        let constants = if key.len() == 32 { SIGMA } else { TAU };
+        Salsa20 { constants, key: key_words, nonce, counter: 0,
+                  expired: false }
+    }
+
+    ///Makes the next call to `encrypt` or `decrypt` start at keystream block
+    ///`block`
+    pub fn seek(&mut self, block: u64) {
+        self.counter = block;
+        self.expired = false;
+    }
+
+    ///Encrypts `message`, starting from the next unused block of keystream.
+    ///Any keystream left over in the last block is thrown away.
+    pub fn encrypt(&mut self, message: &[u8])
+            -> Result<Vec<u8>, Salsa20Error> {
+        let mut encrypted = Vec::with_capacity(message.len());
+        for chunk in message.chunks(64) {
+            if self.expired {
+                return Err(Salsa20Error::ExpiredNonce);
+            }
+            let keystream = block(&self.constants, &self.key, self.nonce,
+                                  self.counter);
+            encrypted.extend(chunk.iter().zip(keystream.iter())
+                                  .map(|(m, k)| m ^ k));
+            match self.counter.checked_add(1) {
+                Some(counter) => self.counter = counter,
+                None => self.expired = true,
+            }
+        }
+        Ok(encrypted)
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &[u8])
+            -> Result<Vec<u8>, Salsa20Error> {
+        self.encrypt(ciphertext)
+    }
+}
+
+impl Keystream for Salsa20 {
+    fn seek(&mut self, block: u64) {
+        Salsa20::seek(self, block)
+    }
+
+    fn encrypt(&mut self, message: &[u8]) -> Result<Vec<u8>, KeystreamError> {
+        Salsa20::encrypt(self, message)
+            .map_err(|_| KeystreamError::ExpiredNonce)
+    }
+
+    fn decrypt(&mut self, ciphertext: &[u8])
+            -> Result<Vec<u8>, KeystreamError> {
+        Salsa20::decrypt(self, ciphertext)
+            .map_err(|_| KeystreamError::ExpiredNonce)
+    }
+}
+
+///The Salsa20 expansion function
+fn block(constants: &[u32; 4], key: &[u32; 8], nonce: u64, counter: u64)
+        -> [u8; 64] {
+    let initial = [constants[0], key[0], key[1], key[2], key[3], constants[1],
+                   nonce as u32, (nonce >> 32) as u32, counter as u32,
+                   (counter >> 32) as u32, constants[2], key[4], key[5],
+                   key[6], key[7], constants[3]];
+    let mut state = initial;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 5, 9, 13, 1);
+        quarter_round(&mut state, 10, 14, 2, 6);
+        quarter_round(&mut state, 15, 3, 7, 11);
+        quarter_round(&mut state, 0, 1, 2, 3);
+        quarter_round(&mut state, 5, 6, 7, 4);
+        quarter_round(&mut state, 10, 11, 8, 9);
+        quarter_round(&mut state, 15, 12, 13, 14);
+    }
+    let mut output = [0u8; 64];
+    for (idx, word) in state.iter().enumerate() {
+        let sum = word.wrapping_add(initial[idx]);
+        for byte in 0..4 {
+            output[4 * idx + byte] = (sum >> (8 * byte)) as u8;
+        }
+    }
+    output
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize,
+                 d: usize) {
+    state[b] ^= state[a].wrapping_add(state[d]).rotate_left(7);
+    state[c] ^= state[b].wrapping_add(state[a]).rotate_left(9);
+    state[d] ^= state[c].wrapping_add(state[b]).rotate_left(13);
+    state[a] ^= state[d].wrapping_add(state[c]).rotate_left(18);
+}
+
+//u32 from the first 4 bytes of `bytes`, in little-endian order
+fn le_word(bytes: &[u8]) -> u32 {
+    bytes[..4].iter().rev().fold(0, |word, &byte| (word << 8) | byte as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Salsa20;
+
+    #[test]
+    fn quarter_round() {
+        //from the Salsa20 specification
+        let mut state = [0u32; 16];
+        state[0] = 1;
+        super::quarter_round(&mut state, 0, 1, 2, 3);
+        assert_eq!(&state[..4], &[0x08008145, 0x00000080, 0x00010200,
+                                  0x20500000]);
+    }
+
+    #[test]
+    fn expansion() {
+        //from the Salsa20 specification: nonce 101..=108, block 109..=116
+        let key = (1..17).chain(201..217).collect::<Vec<u8>>();
+        let mut salsa = Salsa20::new(0x6c6b6a6968676665, &key);
+        salsa.seek(0x74737271706f6e6d);
+        let expected = [69, 37, 68, 39, 41, 15, 107, 193, 255, 139, 122, 6,
+                        170, 233, 217, 98, 89, 144, 182, 106, 21, 51, 200, 65,
+                        239, 49, 222, 34, 215, 114, 40, 126, 104, 197, 7, 225,
+                        197, 153, 31, 2, 102, 78, 76, 176, 84, 245, 246, 184,
+                        177, 160, 133, 130, 6, 72, 149, 119, 192, 195, 132,
+                        236, 234, 103, 246, 74];
+        assert_eq!(&salsa.encrypt(&[0u8; 64]).unwrap()[..], &expected[..]);
+    }
+
+    #[test]
+    fn expansion_short_key() {
+        let key = (1..17).collect::<Vec<u8>>();
+        let mut salsa = Salsa20::new(0x6c6b6a6968676665, &key);
+        salsa.seek(0x74737271706f6e6d);
+        let expected = [39, 173, 46, 248, 30, 200, 82, 17, 48, 67, 254, 239,
+                        37, 18, 13, 247, 241, 200, 61, 144, 10, 55, 50, 185, 6,
+                        47, 246, 253, 143, 86, 187, 225, 134, 85, 110, 246,
+                        161, 163, 43, 235, 231, 94, 171, 51, 145, 214, 112, 29,
+                        14, 232, 5, 16, 151, 140, 183, 141, 171, 9, 122, 181,
+                        104, 182, 177, 193];
+        assert_eq!(&salsa.encrypt(&[0u8; 64]).unwrap()[..], &expected[..]);
+    }
+
+    #[test]
+    fn encrypt() {
+        //eSTREAM, set 1, vector 0
+        let mut key = [0u8; 32];
+        key[0] = 0x80;
+        let mut salsa = Salsa20::new(0, &key);
+        let expected = [0xe3, 0xbe, 0x8f, 0xdd, 0x8b, 0xec, 0xa2, 0xe3, 0xea,
+                        0x8e, 0xf9, 0x47, 0x5b, 0x29, 0xa6, 0xe7, 0x00, 0x39,
+                        0x51, 0xe1, 0x09, 0x7a, 0x5c, 0x38, 0xd2, 0x3b, 0x7a,
+                        0x5f, 0xad, 0x9f, 0x68, 0x44, 0xb2, 0x2c, 0x97, 0x55,
+                        0x9e, 0x27, 0x23, 0xc7, 0xcb, 0xbd, 0x3f, 0xe4, 0xfc,
+                        0x8d, 0x9a, 0x07, 0x44, 0x65, 0x2a, 0x83, 0xe7, 0x2a,
+                        0x9c, 0x46, 0x18, 0x76, 0xaf, 0x4d, 0x7e, 0xf1, 0xa1,
+                        0x17];
+        assert_eq!(&salsa.encrypt(&[0u8; 64]).unwrap()[..], &expected[..]);
+    }
+
+    #[test]
+    fn expired_nonce() {
+        let mut salsa = Salsa20::new(0, b"YELLOW SUBMARINE");
+        salsa.seek(u64::MAX);
+        assert!(salsa.encrypt(&[0u8; 64]).is_ok());
+        assert!(salsa.encrypt(&[0u8; 1]).is_err());
+        salsa.seek(0);
+        let plaintext = b"It was love at first sight.";
+        let ciphertext = salsa.encrypt(plaintext).unwrap();
+        salsa.seek(0);
+        assert_eq!(&salsa.decrypt(&ciphertext).unwrap()[..], &plaintext[..]);
+    }
+}
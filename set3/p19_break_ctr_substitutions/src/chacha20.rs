@@ -0,0 +1,232 @@
+//!ChaCha20 as in RFC 8439: a 256-bit key, a 96-bit nonce and a 32-bit block
+//!counter. Implements `Keystream`, like `AesCtr`.
+
+use keystream::{Keystream, KeystreamError};
+
+const CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+#[derive(Debug)]
+pub enum ChaCha20Error {
+    ExpiredNonce,
+}
+
+pub struct ChaCha20 {
+    key: [u32; 8],
+    nonce: [u32; 3],
+    counter: u64,
+}
+
+impl ChaCha20 {
+    ///Sets up ChaCha20 with a 32-byte `key`. `nonce` fills the last 8 bytes of
+    ///the 12-byte nonce, in little-endian order; the first 4 bytes are 0.
+    pub fn new(nonce: u64, key: &[u8]) -> ChaCha20 {
+        let mut nonce_bytes = [0u8; 12];
+        for idx in 0..8 {
+            nonce_bytes[idx + 4] = (nonce >> (8 * idx)) as u8;
+        }
+        ChaCha20::with_nonce(&nonce_bytes, key)
+    }
+
+    ///Sets up ChaCha20 with a 32-byte `key` and a full 12-byte `nonce`
+    pub fn with_nonce(nonce: &[u8; 12], key: &[u8]) -> ChaCha20 {
+        assert_eq!(key.len(), 32, "ChaCha20 keys are 32 bytes long");
+        let mut key_words = [0u32; 8];
+        for (idx, word) in key_words.iter_mut().enumerate() {
+            *word = le_word(&key[4 * idx..]);
+        }
+        let mut nonce_words = [0u32; 3];
+        for (idx, word) in nonce_words.iter_mut().enumerate() {
+            *word = le_word(&nonce[4 * idx..]);
+        }
+        ChaCha20 { key: key_words, nonce: nonce_words, counter: 0 }
+    }
+
+    ///Makes the next call to `encrypt` or `decrypt` start at keystream block
+    ///`block`
+    pub fn seek(&mut self, block: u64) {
+        self.counter = block;
+    }
+
+    ///Encrypts `message`, starting from the next unused block of keystream.
+    ///Any keystream left over in the last block is thrown away.
+    pub fn encrypt(&mut self, message: &[u8])
+            -> Result<Vec<u8>, ChaCha20Error> {
+        let mut encrypted = Vec::with_capacity(message.len());
+        for chunk in message.chunks(64) {
+            if self.counter > u32::MAX as u64 {
+                return Err(ChaCha20Error::ExpiredNonce);
+            }
+            let keystream = block(&self.key, self.counter as u32, &self.nonce);
+            encrypted.extend(chunk.iter().zip(keystream.iter())
+                                  .map(|(m, k)| m ^ k));
+            self.counter += 1;
+        }
+        Ok(encrypted)
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &[u8])
+            -> Result<Vec<u8>, ChaCha20Error> {
+        self.encrypt(ciphertext)
+    }
+}
+
+impl Keystream for ChaCha20 {
+    fn seek(&mut self, block: u64) {
+        ChaCha20::seek(self, block)
+    }
+
+    fn encrypt(&mut self, message: &[u8]) -> Result<Vec<u8>, KeystreamError> {
+        ChaCha20::encrypt(self, message)
+            .map_err(|_| KeystreamError::ExpiredNonce)
+    }
+
+    fn decrypt(&mut self, ciphertext: &[u8])
+            -> Result<Vec<u8>, KeystreamError> {
+        ChaCha20::decrypt(self, ciphertext)
+            .map_err(|_| KeystreamError::ExpiredNonce)
+    }
+}
+
+///The ChaCha20 block function
+pub fn block(key: &[u32; 8], counter: u32, nonce: &[u32; 3]) -> [u8; 64] {
+    let mut initial = [0u32; 16];
+    initial[..4].copy_from_slice(&CONSTANTS);
+    initial[4..12].copy_from_slice(key);
+    initial[12] = counter;
+    initial[13..].copy_from_slice(nonce);
+    let mut state = initial;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+    let mut output = [0u8; 64];
+    for (idx, word) in state.iter().enumerate() {
+        let sum = word.wrapping_add(initial[idx]);
+        for byte in 0..4 {
+            output[4 * idx + byte] = (sum >> (8 * byte)) as u8;
+        }
+    }
+    output
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize,
+                 d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+//u32 from the first 4 bytes of `bytes`, in little-endian order
+fn le_word(bytes: &[u8]) -> u32 {
+    bytes[..4].iter().rev().fold(0, |word, &byte| (word << 8) | byte as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChaCha20;
+
+    fn rfc_key() -> Vec<u8> {
+        (0..32).collect()
+    }
+
+    #[test]
+    fn quarter_round() {
+        //RFC 8439, section 2.2.1
+        let mut state = [0u32; 16];
+        state[2] = 0x516461b1;
+        state[7] = 0x2a5f714c;
+        state[8] = 0x53372767;
+        state[13] = 0x3d631689;
+        super::quarter_round(&mut state, 2, 7, 8, 13);
+        assert_eq!([state[2], state[7], state[8], state[13]],
+                   [0xbdb886dc, 0xcfacafd2, 0xe46bea80, 0xccc07c79]);
+    }
+
+    #[test]
+    fn block() {
+        //RFC 8439, section 2.3.2
+        let nonce = [0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x4a, 0x00,
+                     0x00, 0x00, 0x00];
+        let mut chacha = ChaCha20::with_nonce(&nonce, &rfc_key());
+        chacha.seek(1);
+        let expected = [0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15, 0x50,
+                        0x0f, 0xdd, 0x1f, 0xa3, 0x20, 0x71, 0xc4, 0xc7, 0xd1,
+                        0xf4, 0xc7, 0x33, 0xc0, 0x68, 0x03, 0x04, 0x22, 0xaa,
+                        0x9a, 0xc3, 0xd4, 0x6c, 0x4e, 0xd2, 0x82, 0x64, 0x46,
+                        0x07, 0x9f, 0xaa, 0x09, 0x14, 0xc2, 0xd7, 0x05, 0xd9,
+                        0x8b, 0x02, 0xa2, 0xb5, 0x12, 0x9c, 0xd1, 0xde, 0x16,
+                        0x4e, 0xb9, 0xcb, 0xd0, 0x83, 0xe8, 0xa2, 0x50, 0x3c,
+                        0x4e];
+        assert_eq!(&chacha.encrypt(&[0u8; 64]).unwrap()[..], &expected[..]);
+    }
+
+    #[test]
+    fn encrypt() {
+        //RFC 8439, section 2.4.2
+        let mut chacha = ChaCha20::new(0x4a000000, &rfc_key());
+        chacha.seek(1);
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could \
+                          offer you only one tip for the future, sunscreen \
+                          would be it.";
+        let expected = [0x6e, 0x2e, 0x35, 0x9a, 0x25, 0x68, 0xf9, 0x80, 0x41,
+                        0xba, 0x07, 0x28, 0xdd, 0x0d, 0x69, 0x81, 0xe9, 0x7e,
+                        0x7a, 0xec, 0x1d, 0x43, 0x60, 0xc2, 0x0a, 0x27, 0xaf,
+                        0xcc, 0xfd, 0x9f, 0xae, 0x0b, 0xf9, 0x1b, 0x65, 0xc5,
+                        0x52, 0x47, 0x33, 0xab, 0x8f, 0x59, 0x3d, 0xab, 0xcd,
+                        0x62, 0xb3, 0x57, 0x16, 0x39, 0xd6, 0x24, 0xe6, 0x51,
+                        0x52, 0xab, 0x8f, 0x53, 0x0c, 0x35, 0x9f, 0x08, 0x61,
+                        0xd8, 0x07, 0xca, 0x0d, 0xbf, 0x50, 0x0d, 0x6a, 0x61,
+                        0x56, 0xa3, 0x8e, 0x08, 0x8a, 0x22, 0xb6, 0x5e, 0x52,
+                        0xbc, 0x51, 0x4d, 0x16, 0xcc, 0xf8, 0x06, 0x81, 0x8c,
+                        0xe9, 0x1a, 0xb7, 0x79, 0x37, 0x36, 0x5a, 0xf9, 0x0b,
+                        0xbf, 0x74, 0xa3, 0x5b, 0xe6, 0xb4, 0x0b, 0x8e, 0xed,
+                        0xf2, 0x78, 0x5e, 0x42, 0x87, 0x4d];
+        assert_eq!(&chacha.encrypt(&plaintext[..]).unwrap()[..],
+                   &expected[..]);
+    }
+
+    #[test]
+    fn decrypt_after_seek() {
+        let plaintext = (0..200).collect::<Vec<u8>>();
+        let ciphertext = ChaCha20::new(24601, &rfc_key()).encrypt(&plaintext)
+                                                         .unwrap();
+        let mut chacha = ChaCha20::new(24601, &rfc_key());
+        chacha.seek(2);
+        assert_eq!(chacha.decrypt(&ciphertext[128..]).unwrap(),
+                   &plaintext[128..]);
+    }
+
+    #[test]
+    fn expired_nonce() {
+        let mut chacha = ChaCha20::new(0, &rfc_key());
+        chacha.seek(u32::MAX as u64);
+        assert!(chacha.encrypt(&[0u8; 64]).is_ok());
+        assert!(chacha.encrypt(&[0u8; 1]).is_err());
+        chacha.seek(1 << 32);
+        assert!(chacha.encrypt(&[0u8; 1]).is_err());
+    }
+
+    #[test]
+    fn keystream() {
+        use keystream::Keystream;
+        let message = [0x42u8; 150];
+        let expected = ChaCha20::new(7, &rfc_key()).encrypt(&message).unwrap();
+        let mut chacha = ChaCha20::new(7, &rfc_key());
+        let cipher: &mut dyn Keystream = &mut chacha;
+        cipher.seek(2);
+        assert_eq!(cipher.decrypt(&expected[128..]).unwrap(), &message[128..]);
+        cipher.seek(1 << 32);
+        assert!(cipher.encrypt(&message).is_err());
+    }
+}
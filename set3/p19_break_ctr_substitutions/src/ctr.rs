@@ -1,4 +1,5 @@
 use aes;
+use keystream::{Keystream, KeystreamError};
 
 struct BlockStream {
     nonce: u64,
@@ -72,6 +73,12 @@ impl AesCtr {
         AesCtr{ key: key.to_vec(), blocks: BlockStream::new(nonce) }
     }
 
+    ///Makes the next call to `encrypt` or `decrypt` start at keystream block
+    ///`block`
+    pub fn seek(&mut self, block: u64) {
+        self.blocks.ctr = block;
+    }
+
     pub fn encrypt(&mut self, message: &[u8]) -> Result<Vec<u8>, AesCtrError> {
         let mut encrypted = Vec::new();
         let mut msg_itr = message.iter();
@@ -101,6 +108,23 @@ impl AesCtr {
     }
 }
 
+impl Keystream for AesCtr {
+    fn seek(&mut self, block: u64) {
+        AesCtr::seek(self, block)
+    }
+
+    fn encrypt(&mut self, message: &[u8]) -> Result<Vec<u8>, KeystreamError> {
+        AesCtr::encrypt(self, message)
+            .map_err(|_| KeystreamError::ExpiredNonce)
+    }
+
+    fn decrypt(&mut self, ciphertext: &[u8])
+            -> Result<Vec<u8>, KeystreamError> {
+        AesCtr::decrypt(self, ciphertext)
+            .map_err(|_| KeystreamError::ExpiredNonce)
+    }
+}
+
 #[cfg(test)]
 mod blockstream_tests{
     use super::BlockStream;
@@ -172,4 +196,16 @@ mod aesctr_tests{
         let expected = "It was love at first sight.".as_bytes();
         assert_eq!(&output[..], &expected[..])
     }
+
+    #[test]
+    fn seek() {
+        let key = "yellow submarine".as_bytes();
+        let message = "It was love at first sight.".as_bytes();
+        let ciphertext = AesCtr::new(0x1337cafec0ded00d, &key[..])
+                             .encrypt(&message[..]).unwrap();
+        let mut ctr = AesCtr::new(0x1337cafec0ded00d, &key[..]);
+        ctr.seek(1);
+        let output = ctr.decrypt(&ciphertext[16..]).unwrap();
+        assert_eq!(&output[..], &message[16..])
+    }
 }
@@ -0,0 +1,21 @@
+//!The interface shared by the seekable stream ciphers, `AesCtr`, `Salsa20` and
+//!`ChaCha20`, so attacks on one work on all of them.
+
+#[derive(Debug)]
+pub enum KeystreamError {
+    ExpiredNonce,
+}
+
+///A cipher which xors messages with a keystream that can be started at any
+///block
+pub trait Keystream {
+    ///Makes the next call to `encrypt` or `decrypt` start at keystream block
+    ///`block`
+    fn seek(&mut self, block: u64);
+
+    ///Encrypts `message`, starting from the next unused block of keystream
+    fn encrypt(&mut self, message: &[u8]) -> Result<Vec<u8>, KeystreamError>;
+
+    fn decrypt(&mut self, ciphertext: &[u8])
+            -> Result<Vec<u8>, KeystreamError>;
+}
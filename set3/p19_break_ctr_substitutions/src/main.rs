@@ -2,11 +2,22 @@ extern crate crypto;
 extern crate rustc_serialize;
 
 mod aes;
+#[cfg(test)]
+mod chacha20;
 mod ctr;
+mod keystream;
+#[cfg(test)]
+mod salsa20;
+mod ngram;
 mod score;
 
 use std::io;
 
+use keystream::Keystream;
+
+///Where the language model's training text is
+const TEXT_SAMPLES: &str = "../../text_samples";
+
 fn crack(ciphertexts: &Vec<Vec<u8>>) -> Vec<u8> {
     let max_len = ciphertexts.iter().map(|v| v.len()).max().unwrap();
     let mut keystream = Vec::new();
@@ -65,6 +76,16 @@ fn base64_lines(filename: &str) -> io::Result<Vec<Vec<u8>>> {
     Ok(lines)
 }
 
+//Encrypts each of `lines` from the start of `cipher`'s keystream, as if they
+//all used the same nonce
+fn encrypt_lines(cipher: &mut dyn Keystream, lines: &[Vec<u8>])
+        -> Vec<Vec<u8>> {
+    lines.iter().map(|line| {
+        cipher.seek(0);
+        cipher.encrypt(&line[..]).unwrap()
+    }).collect()
+}
+
 fn main() {
     let args : Vec<String> = std::env::args().collect();
     if args.len()!=2 {
@@ -80,18 +101,83 @@ fn main() {
             return;
         }
     }
-    let mut ciphertexts = Vec::new();
     let nonce = 0;
     let key = "YELLOW SUBMARINE".as_bytes();
-    for line in lines {
-        let mut encryptor = ctr::AesCtr::new(nonce, &key[..]);
-        ciphertexts.push(encryptor.encrypt(&line[..]).unwrap());
-    }
-    let keystream = crack(&ciphertexts);
+    let mut encryptor = ctr::AesCtr::new(nonce, &key[..]);
+    let ciphertexts = encrypt_lines(&mut encryptor, &lines);
+    let (keystream, confidence) = match ngram::Model::from_dir(TEXT_SAMPLES) {
+        Ok(model) => {
+            let solution = ngram::solve(&ciphertexts, &model);
+            (solution.keystream, Some(solution.confidence))
+        }
+        Err(e) => {
+            println!("Couldn't train on {}: {}", TEXT_SAMPLES, e);
+            println!("Falling back to unigram scores");
+            (crack(&ciphertexts), None)
+        }
+    };
     for ciphertext in ciphertexts {
         for idx in 0..ciphertext.len() {
             print!("{}", (ciphertext[idx] ^ keystream[idx]) as char);
         }
         println!("");
     }
+    //each keystream byte's confidence, from 0 for none to 9 for certain
+    if let Some(confidence) = confidence {
+        println!();
+        for c in confidence {
+            print!("{}", (c * 9.0).round() as u8);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chacha20::ChaCha20;
+    use keystream::Keystream;
+    use salsa20::Salsa20;
+
+    //Encrypts the input under `cipher` with a fixed nonce, cut to the length
+    //of the shortest line, then gives the fraction of keystream bytes which
+    //`crack` gets right
+    fn fraction_cracked<F>(cipher: &mut dyn Keystream, crack: F) -> f64
+            where F: Fn(&Vec<Vec<u8>>) -> Vec<u8> {
+        let lines = super::base64_lines("input.txt").unwrap();
+        let len = lines.iter().map(|l| l.len()).min().unwrap();
+        let mut ciphertexts = super::encrypt_lines(cipher, &lines);
+        for ciphertext in ciphertexts.iter_mut() {
+            ciphertext.truncate(len);
+        }
+        cipher.seek(0);
+        let keystream = cipher.encrypt(&vec![0u8; len]).unwrap();
+        let found = crack(&ciphertexts);
+        let right = (0..len).filter(|&i| found[i] == keystream[i]).count();
+        right as f64 / len as f64
+    }
+
+    #[test]
+    fn crack_stream_ciphers() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let long_key = "YELLOW SUBMARINEYELLOW SUBMARINE".as_bytes();
+        let mut chacha = ChaCha20::new(0, long_key);
+        assert!(fraction_cracked(&mut chacha, super::crack) > 0.9);
+        let mut salsa = Salsa20::new(0, key);
+        assert!(fraction_cracked(&mut salsa, super::crack) > 0.9);
+    }
+
+    #[test]
+    fn solve_stream_ciphers() {
+        use ngram::{Model, solve};
+        let model = Model::from_dir(super::TEXT_SAMPLES).unwrap();
+        let crack = |ciphertexts: &Vec<Vec<u8>>| {
+            solve(ciphertexts, &model).keystream
+        };
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let long_key = "YELLOW SUBMARINEYELLOW SUBMARINE".as_bytes();
+        let mut chacha = ChaCha20::new(0, long_key);
+        assert!(fraction_cracked(&mut chacha, crack) > 0.9);
+        let mut salsa = Salsa20::new(0, key);
+        assert!(fraction_cracked(&mut salsa, crack) > 0.9);
+    }
 }
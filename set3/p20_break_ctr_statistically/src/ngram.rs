@@ -0,0 +1,367 @@
+//!Breaks fixed-nonce CTR with a byte trigram model of English. A bigram
+//!Viterbi pass picks a keystream jointly across neighboring columns, then
+//!trigram passes refine it one byte at a time, which keeps working on the
+//!long tail where only one or two ciphertexts are left.
+
+use std::collections::HashMap;
+use std::io;
+
+///What comes before the first byte of each line
+const START: u8 = b'\n';
+
+///Weight of the trigram estimate over the bigram one, when it has data
+const TRIGRAM_WEIGHT: f64 = 0.7;
+
+///Weight of the bigram estimate over the unigram one, when it has data
+const BIGRAM_WEIGHT: f64 = 0.8;
+
+///Keystream bytes kept per column, by unigram score
+const CANDIDATES: usize = 32;
+
+///Columns with this many ciphertexts or fewer keep every keystream byte,
+///since unigram scores can't tell their candidates apart
+const SPARSE: usize = 4;
+
+///Most refinement passes made over the keystream
+const PASSES: usize = 10;
+
+///Byte trigram language model, interpolated down to bigrams and unigrams
+pub struct Model {
+    pair_counts: Vec<f64>,
+    triple_counts: HashMap<(u8, u8, u8), f64>,
+    //ln P(c), add-one smoothed
+    ln_unigram: Vec<f64>,
+    //ln P(c | b), indexed by b * 256 + c
+    ln_bigram: Vec<f64>,
+}
+
+impl Model {
+    pub fn train(text: &[u8]) -> Model {
+        let text = text.iter().cloned().filter(|&c| c != b'\r')
+                       .collect::<Vec<u8>>();
+        let mut counts = vec![0f64; 256];
+        let mut pair_counts = vec![0f64; 256 * 256];
+        let mut triple_counts = HashMap::new();
+        for (idx, &c) in text.iter().enumerate() {
+            counts[c as usize] += 1.0;
+            if idx >= 1 {
+                pair_counts[text[idx - 1] as usize * 256 + c as usize] += 1.0;
+            }
+            if idx >= 2 {
+                *triple_counts.entry((text[idx - 2], text[idx - 1], c))
+                              .or_insert(0.0) += 1.0;
+            }
+        }
+        let total = text.len() as f64;
+        let unigram = counts.iter().map(|&n| (n + 1.0) / (total + 256.0))
+                            .collect::<Vec<f64>>();
+        let mut ln_bigram = vec![0f64; 256 * 256];
+        for b in 0..256 {
+            for c in 0..256 {
+                let p = if counts[b] > 0.0 {
+                    BIGRAM_WEIGHT * pair_counts[b * 256 + c] / counts[b]
+                        + (1.0 - BIGRAM_WEIGHT) * unigram[c]
+                } else {
+                    unigram[c]
+                };
+                ln_bigram[b * 256 + c] = p.ln();
+            }
+        }
+        Model {
+            pair_counts,
+            triple_counts,
+            ln_unigram: unigram.iter().map(|p| p.ln()).collect(),
+            ln_bigram,
+        }
+    }
+
+    ///Trains on every `.txt` file in `dir`
+    pub fn from_dir(dir: &str) -> io::Result<Model> {
+        use std::io::prelude::*;
+        use std::fs::{self, File};
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "txt") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        let mut text = Vec::new();
+        for path in paths {
+            File::open(path)?.read_to_end(&mut text)?;
+            text.push(b'\n');
+        }
+        Ok(Model::train(&text))
+    }
+
+    pub fn ln_unigram(&self, c: u8) -> f64 {
+        self.ln_unigram[c as usize]
+    }
+
+    ///ln P(c | b)
+    pub fn ln_bigram(&self, b: u8, c: u8) -> f64 {
+        self.ln_bigram[b as usize * 256 + c as usize]
+    }
+
+    ///ln P(c | a b)
+    pub fn ln_trigram(&self, a: u8, b: u8, c: u8) -> f64 {
+        let context = self.pair_counts[a as usize * 256 + b as usize];
+        if context == 0.0 {
+            return self.ln_bigram(b, c);
+        }
+        let seen = self.triple_counts.get(&(a, b, c)).cloned().unwrap_or(0.0);
+        (TRIGRAM_WEIGHT * seen / context
+            + (1.0 - TRIGRAM_WEIGHT) * self.ln_bigram(b, c).exp()).ln()
+    }
+}
+
+///A recovered keystream, with the chance each byte of it is right
+#[derive(Debug)]
+pub struct Solution {
+    pub keystream: Vec<u8>,
+    pub confidence: Vec<f64>,
+}
+
+//trigram score of the bytes whose trigrams include column `idx`, with
+//`byte` as the keystream there
+fn window_score(model: &Model, ciphertexts: &[Vec<u8>], keystream: &[u8],
+                idx: usize, byte: u8) -> f64 {
+    let mut score = 0.0;
+    for ciphertext in ciphertexts.iter().filter(|c| c.len() > idx) {
+        let plain = |pos: usize| {
+            if pos == idx {
+                ciphertext[pos] ^ byte
+            } else {
+                ciphertext[pos] ^ keystream[pos]
+            }
+        };
+        for pos in idx..ciphertext.len().min(idx + 3) {
+            let a = if pos >= 2 { plain(pos - 2) } else { START };
+            let b = if pos >= 1 { plain(pos - 1) } else { START };
+            score += model.ln_trigram(a, b, plain(pos));
+        }
+    }
+    score
+}
+
+//ln of the sum of the exps of `values`
+fn ln_sum_exp(values: &[f64]) -> f64 {
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    max + values.iter().map(|v| (v - max).exp()).sum::<f64>().ln()
+}
+
+//keystream bytes worth trying in each column
+fn candidates(model: &Model, ciphertexts: &[Vec<u8>], len: usize)
+        -> Vec<Vec<u8>> {
+    (0..len).map(|idx| {
+        let column = ciphertexts.iter().filter_map(|c| c.get(idx).cloned())
+                                .collect::<Vec<u8>>();
+        let mut bytes = (0..256).map(|b| b as u8).collect::<Vec<u8>>();
+        if column.len() > SPARSE {
+            let score = |byte: u8| {
+                column.iter().map(|&c| model.ln_unigram(c ^ byte))
+                      .sum::<f64>()
+            };
+            let mut scored = bytes.iter().map(|&b| (score(b), b))
+                                  .collect::<Vec<_>>();
+            scored.sort_by(|x, y| y.0.partial_cmp(&x.0).unwrap());
+            bytes = scored.iter().take(CANDIDATES).map(|&(_, b)| b).collect();
+        }
+        bytes
+    }).collect()
+}
+
+//bigram scores between neighboring columns: steps[idx][n * m_len + m] is the
+//score of column idx with candidate n there and candidate m before it, where
+//column 0 has a single candidate before it, the start of the line
+fn steps(model: &Model, ciphertexts: &[Vec<u8>], candidates: &[Vec<u8>])
+        -> Vec<Vec<f64>> {
+    (0..candidates.len()).map(|idx| {
+        let rows = ciphertexts.iter().filter(|c| c.len() > idx)
+                              .collect::<Vec<_>>();
+        let column = &candidates[idx];
+        let prev = if idx == 0 { &[0][..] } else { &candidates[idx - 1][..] };
+        let mut scores = Vec::with_capacity(column.len() * prev.len());
+        for &k in column.iter() {
+            for &j in prev.iter() {
+                scores.push(rows.iter().map(|c| {
+                    //like the trigram passes, lines start after a blank line
+                    if idx == 0 {
+                        model.ln_trigram(START, START, c[0] ^ k)
+                    } else {
+                        model.ln_bigram(c[idx - 1] ^ j, c[idx] ^ k)
+                    }
+                }).sum::<f64>());
+            }
+        }
+        scores
+    }).collect()
+}
+
+//the most likely candidate in each column, by Viterbi
+fn viterbi(candidates: &[Vec<u8>], steps: &[Vec<f64>]) -> Vec<usize> {
+    let len = candidates.len();
+    //best[idx][n] is the best score of columns up to idx with candidate n
+    //there, and back[idx][n] the candidate before it
+    let mut best: Vec<Vec<f64>> = Vec::with_capacity(len);
+    let mut back: Vec<Vec<usize>> = Vec::with_capacity(len);
+    for idx in 0..len {
+        let prev_len = steps[idx].len() / candidates[idx].len();
+        let (scores, from) = (0..candidates[idx].len()).map(|n| {
+            (0..prev_len).map(|m| {
+                let before = if idx == 0 { 0.0 } else { best[idx - 1][m] };
+                (before + steps[idx][n * prev_len + m], m)
+            }).fold((f64::NEG_INFINITY, 0),
+                    |a, b| if b.0 > a.0 { b } else { a })
+        }).unzip();
+        best.push(scores);
+        back.push(from);
+    }
+    let mut chosen = vec![0; len];
+    if len > 0 {
+        let last = &best[len - 1];
+        let mut n = (0..last.len())
+                        .fold(0, |a, b| if last[b] > last[a] { b } else { a });
+        for idx in (0..len).rev() {
+            chosen[idx] = n;
+            n = back[idx][n];
+        }
+    }
+    chosen
+}
+
+//the chance of each candidate in each column, by forward-backward
+fn marginals(candidates: &[Vec<u8>], steps: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let len = candidates.len();
+    let mut forward: Vec<Vec<f64>> = Vec::with_capacity(len);
+    for idx in 0..len {
+        let prev_len = steps[idx].len() / candidates[idx].len();
+        forward.push((0..candidates[idx].len()).map(|n| {
+            ln_sum_exp(&(0..prev_len).map(|m| {
+                let before = if idx == 0 { 0.0 } else { forward[idx - 1][m] };
+                before + steps[idx][n * prev_len + m]
+            }).collect::<Vec<f64>>())
+        }).collect());
+    }
+    let mut backward: Vec<Vec<f64>> = vec![Vec::new(); len];
+    for idx in (0..len).rev() {
+        backward[idx] = (0..candidates[idx].len()).map(|m| {
+            if idx + 1 == len {
+                return 0.0;
+            }
+            let next_len = candidates[idx + 1].len();
+            let prev_len = candidates[idx].len();
+            ln_sum_exp(&(0..next_len).map(|n| {
+                backward[idx + 1][n] + steps[idx + 1][n * prev_len + m]
+            }).collect::<Vec<f64>>())
+        }).collect();
+    }
+    (0..len).map(|idx| {
+        let joint = forward[idx].iter().zip(backward[idx].iter())
+                                .map(|(f, b)| f + b).collect::<Vec<f64>>();
+        let total = ln_sum_exp(&joint);
+        joint.iter().map(|j| (j - total).exp()).collect()
+    }).collect()
+}
+
+///Recovers the keystream shared by `ciphertexts`
+pub fn solve(ciphertexts: &[Vec<u8>], model: &Model) -> Solution {
+    let len = ciphertexts.iter().map(|c| c.len()).max().unwrap_or(0);
+    let candidates = candidates(model, ciphertexts, len);
+    let steps = steps(model, ciphertexts, &candidates);
+    let mut keystream = viterbi(&candidates, &steps).iter().enumerate()
+                                .map(|(idx, &n)| candidates[idx][n])
+                                .collect::<Vec<u8>>();
+
+    //trigram refinement, one column at a time
+    for _ in 0..PASSES {
+        let mut changed = false;
+        for idx in 0..len {
+            let current = keystream[idx];
+            let mut best_byte = current;
+            let mut best_score = window_score(model, ciphertexts, &keystream,
+                                              idx, current);
+            for &byte in candidates[idx].iter() {
+                let score = window_score(model, ciphertexts, &keystream, idx,
+                                         byte);
+                if score > best_score {
+                    best_byte = byte;
+                    best_score = score;
+                }
+            }
+            if best_byte != current {
+                keystream[idx] = best_byte;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    //a byte is only as sure as the less sure of its bigram marginal, which
+    //takes in doubt about its neighbors, and its trigram chance given them
+    let marginals = marginals(&candidates, &steps);
+    let confidence = (0..len).map(|idx| {
+        let scores = candidates[idx].iter().map(|&byte| {
+            window_score(model, ciphertexts, &keystream, idx, byte)
+        }).collect::<Vec<f64>>();
+        let chosen = window_score(model, ciphertexts, &keystream, idx,
+                                  keystream[idx]);
+        let given_neighbors = (chosen - ln_sum_exp(&scores)).exp();
+        let n = candidates[idx].iter().position(|&b| b == keystream[idx])
+                               .unwrap();
+        given_neighbors.min(marginals[idx][n])
+    }).collect();
+    Solution { keystream, confidence }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Model;
+
+    fn model() -> Model {
+        Model::from_dir("../../text_samples").unwrap()
+    }
+
+    #[test]
+    fn model_prefers_english() {
+        let model = Model::train(b"the cat sat on the mat\nthe hat\n");
+        assert!(model.ln_trigram(b't', b'h', b'e')
+                > model.ln_trigram(b't', b'h', b'q'));
+        assert!(model.ln_bigram(b'a', b't') > model.ln_bigram(b'a', b'e'));
+        assert!(model.ln_unigram(b't') > model.ln_unigram(b'z'));
+        //unseen contexts fall back to shorter ones
+        assert_eq!(model.ln_trigram(b'z', b'h', b'e'),
+                   model.ln_bigram(b'h', b'e'));
+    }
+
+    #[test]
+    fn solve() {
+        let model = model();
+        let lines = ::base64_lines("input.txt").unwrap();
+        let keystream = (0..256).map(|n| (n * 167 + 13) as u8)
+                                .collect::<Vec<u8>>();
+        let ciphertexts = lines.iter().map(|line| {
+            line.iter().zip(keystream.iter()).map(|(p, k)| p ^ k).collect()
+        }).collect::<Vec<Vec<u8>>>();
+        let solution = super::solve(&ciphertexts, &model);
+        let len = solution.keystream.len();
+        let right = |found: &[u8]| {
+            (0..len).filter(|&i| found[i] == keystream[i]).count()
+        };
+        assert!(right(&solution.keystream) as f64 / len as f64 > 0.9,
+                "{} of {} keystream bytes right", right(&solution.keystream),
+                len);
+        assert!(right(&solution.keystream) > right(&::crack(&ciphertexts)));
+        //confident bytes are right
+        for ((found, actual), &confidence) in solution.keystream.iter()
+                                              .zip(keystream.iter())
+                                              .zip(solution.confidence.iter()) {
+            if confidence > 0.9 {
+                assert_eq!(found, actual);
+            }
+        }
+    }
+}
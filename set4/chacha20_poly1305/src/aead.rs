@@ -0,0 +1,161 @@
+//!The ChaCha20-Poly1305 AEAD construction from RFC 8439
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use chacha20::ChaCha20;
+use poly1305;
+
+#[derive(Debug, PartialEq)]
+pub enum AeadError {
+    BadTag,
+    ExpiredNonce,
+}
+
+impl Display for AeadError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str(match *self {
+            AeadError::BadTag => "Bad AEAD tag",
+            AeadError::ExpiredNonce => "AEAD message too long for one nonce",
+        })
+    }
+}
+
+impl Error for AeadError {
+    fn description(&self) -> &str {
+        match *self {
+            AeadError::BadTag => "Bad AEAD tag",
+            AeadError::ExpiredNonce => "AEAD message too long for one nonce",
+        }
+    }
+}
+
+///Gives the one-time Poly1305 key for `key` and `nonce`: the first 32 bytes of
+///ChaCha20 block 0
+pub fn poly1305_key_gen(key: &[u8; 32], nonce: &[u8; 12]) -> [u8; 32] {
+    let mut chacha = ChaCha20::with_nonce(nonce, key);
+    let mut one_time_key = [0u8; 32];
+    one_time_key.copy_from_slice(&chacha.encrypt(&[0u8; 32]).unwrap());
+    one_time_key
+}
+
+///Gives the data the tag is computed over: `aad` and `ciphertext`, each padded
+///with zeros to a multiple of 16 bytes, then both of their lengths
+pub fn mac_data(aad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for part in [aad, ciphertext].iter() {
+        data.extend_from_slice(part);
+        data.resize(data.len().div_ceil(16) * 16, 0);
+    }
+    for &len in [aad.len(), ciphertext.len()].iter() {
+        data.extend((0..8).map(|idx| ((len as u64) >> (8 * idx)) as u8));
+    }
+    data
+}
+
+///Encrypts `plaintext` and authenticates it along with `aad`. Gives the
+///ciphertext and tag.
+pub fn seal(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], plaintext: &[u8])
+        -> Result<(Vec<u8>, [u8; 16]), AeadError> {
+    let mut chacha = ChaCha20::with_nonce(nonce, key);
+    chacha.seek(1);
+    let ciphertext = chacha.encrypt(plaintext)
+                           .map_err(|_| AeadError::ExpiredNonce)?;
+    let tag = poly1305::poly1305(&mac_data(aad, &ciphertext),
+                                 &poly1305_key_gen(key, nonce));
+    Ok((ciphertext, tag))
+}
+
+///Checks `tag` over `aad` and `ciphertext`, then decrypts `ciphertext`
+pub fn open(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8],
+            tag: &[u8; 16]) -> Result<Vec<u8>, AeadError> {
+    if !poly1305::verify(&mac_data(aad, ciphertext),
+                         &poly1305_key_gen(key, nonce), tag) {
+        return Err(AeadError::BadTag);
+    }
+    let mut chacha = ChaCha20::with_nonce(nonce, key);
+    chacha.seek(1);
+    chacha.decrypt(ciphertext).map_err(|_| AeadError::ExpiredNonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AeadError;
+
+    fn rfc_key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        for (idx, el) in key.iter_mut().enumerate() {
+            *el = 0x80 + idx as u8;
+        }
+        key
+    }
+
+    const NONCE: [u8; 12] = [0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43,
+                             0x44, 0x45, 0x46, 0x47];
+    const AAD: [u8; 12] = [0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3,
+                           0xc4, 0xc5, 0xc6, 0xc7];
+    const PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: If I \
+                               could offer you only one tip for the future, \
+                               sunscreen would be it.";
+
+    #[test]
+    fn poly1305_key_gen() {
+        //RFC 8439, section 2.6.2
+        let nonce = [0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04,
+                     0x05, 0x06, 0x07];
+        let expected = [0x8a, 0xd5, 0xa0, 0x8b, 0x90, 0x5f, 0x81, 0xcc, 0x81,
+                        0x50, 0x40, 0x27, 0x4a, 0xb2, 0x94, 0x71, 0xa8, 0x33,
+                        0xb6, 0x37, 0xe3, 0xfd, 0x0d, 0xa5, 0x08, 0xdb, 0xb8,
+                        0xe2, 0xfd, 0xd1, 0xa6, 0x46];
+        assert_eq!(super::poly1305_key_gen(&rfc_key(), &nonce), expected);
+    }
+
+    #[test]
+    fn mac_data() {
+        let data = super::mac_data(b"abc", &[7u8; 17]);
+        assert_eq!(data.len(), 16 + 32 + 16);
+        assert_eq!(&data[..4], b"abc\x00");
+        assert_eq!(&data[48..], &[3, 0, 0, 0, 0, 0, 0, 0,
+                                  17, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn seal() {
+        //RFC 8439, section 2.8.2
+        let (ciphertext, tag) = super::seal(&rfc_key(), &NONCE, &AAD,
+                                            PLAINTEXT).unwrap();
+        let expected = [0xd3, 0x1a, 0x8d, 0x34, 0x64, 0x8e, 0x60, 0xdb, 0x7b,
+                        0x86, 0xaf, 0xbc, 0x53, 0xef, 0x7e, 0xc2, 0xa4, 0xad,
+                        0xed, 0x51, 0x29, 0x6e, 0x08, 0xfe, 0xa9, 0xe2, 0xb5,
+                        0xa7, 0x36, 0xee, 0x62, 0xd6, 0x3d, 0xbe, 0xa4, 0x5e,
+                        0x8c, 0xa9, 0x67, 0x12, 0x82, 0xfa, 0xfb, 0x69, 0xda,
+                        0x92, 0x72, 0x8b, 0x1a, 0x71, 0xde, 0x0a, 0x9e, 0x06,
+                        0x0b, 0x29, 0x05, 0xd6, 0xa5, 0xb6, 0x7e, 0xcd, 0x3b,
+                        0x36, 0x92, 0xdd, 0xbd, 0x7f, 0x2d, 0x77, 0x8b, 0x8c,
+                        0x98, 0x03, 0xae, 0xe3, 0x28, 0x09, 0x1b, 0x58, 0xfa,
+                        0xb3, 0x24, 0xe4, 0xfa, 0xd6, 0x75, 0x94, 0x55, 0x85,
+                        0x80, 0x8b, 0x48, 0x31, 0xd7, 0xbc, 0x3f, 0xf4, 0xde,
+                        0xf0, 0x8e, 0x4b, 0x7a, 0x9d, 0xe5, 0x76, 0xd2, 0x65,
+                        0x86, 0xce, 0xc6, 0x4b, 0x61, 0x16];
+        let expected_tag = [0x1a, 0xe1, 0x0b, 0x59, 0x4f, 0x09, 0xe2, 0x6a,
+                            0x7e, 0x90, 0x2e, 0xcb, 0xd0, 0x60, 0x06, 0x91];
+        assert_eq!(&ciphertext[..], &expected[..]);
+        assert_eq!(tag, expected_tag);
+    }
+
+    #[test]
+    fn open() {
+        let (ciphertext, tag) = super::seal(&rfc_key(), &NONCE, &AAD,
+                                            PLAINTEXT).unwrap();
+        assert_eq!(super::open(&rfc_key(), &NONCE, &AAD, &ciphertext, &tag)
+                       .unwrap(),
+                   PLAINTEXT);
+        let mut tampered = ciphertext.clone();
+        tampered[0] ^= 1;
+        assert_eq!(super::open(&rfc_key(), &NONCE, &AAD, &tampered, &tag),
+                   Err(AeadError::BadTag));
+        assert_eq!(super::open(&rfc_key(), &NONCE, b"", &ciphertext, &tag),
+                   Err(AeadError::BadTag));
+    }
+}
@@ -0,0 +1,126 @@
+//!ChaCha20-Poly1305 (RFC 8439), and recovery of the one-time Poly1305 key
+//!when a key and nonce are used for two messages.
+
+extern crate num;
+extern crate rand;
+
+pub mod chacha20;
+pub mod keystream;
+pub mod poly1305;
+pub mod aead;
+mod roots;
+
+use num::bigint::BigUint;
+use num::traits::Zero;
+
+///Recovers the one-time Poly1305 keys which could have given `first_tag` over
+///`first_data` and `second_tag` over `second_data`. For the AEAD, the data is
+///`aead::mac_data(aad, ciphertext)`. There is almost always exactly one.
+pub fn recover_keys(first_data: &[u8], first_tag: &[u8; 16],
+                    second_data: &[u8], second_tag: &[u8; 16])
+        -> Vec<[u8; 32]> {
+    let p = poly1305::prime();
+    let first = coefficients(first_data);
+    let second = coefficients(second_data);
+    //each tag is (h(r) + s) mod 2^128 with h(r) < p, so h1(r) - h2(r) is
+    //t1 - t2 plus a small multiple of 2^128
+    let zero = BigUint::zero();
+    let diff = (0..first.len().max(second.len())).map(|idx| {
+        let a = first.get(idx).unwrap_or(&zero);
+        let b = second.get(idx).unwrap_or(&zero);
+        (a + &p - b) % &p
+    }).collect::<Vec<BigUint>>();
+    let t1 = BigUint::from_bytes_le(first_tag);
+    let t2 = BigUint::from_bytes_le(second_tag);
+    let two_128 = BigUint::from(1u32) << 128usize;
+    let mut keys = Vec::new();
+    for wraps in 0..9u32 {
+        //(t1 - t2 + (wraps - 4) * 2^128) mod p, kept non-negative
+        let target = (&t1 + &p * 8u32 + &two_128 * wraps
+                      - &t2 - &two_128 * 4u32) % &p;
+        let mut poly = diff.clone();
+        poly[0] = (&poly[0] + &p - target) % &p;
+        for r in roots::roots(&poly) {
+            if !poly1305::is_clamped(&r) {
+                continue;
+            }
+            let h1 = poly1305::evaluate(first_data, &r);
+            let h2 = poly1305::evaluate(second_data, &r);
+            let s = (&t1 + &two_128 * 2u32 - h1 % &two_128) % &two_128;
+            if poly1305::tag_bytes(&(h2 + &s)) != *second_tag {
+                continue;
+            }
+            let mut key = [0u8; 32];
+            key[..16].copy_from_slice(&poly1305::tag_bytes(&r));
+            key[16..].copy_from_slice(&poly1305::tag_bytes(&s));
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    keys
+}
+
+///Gives the coefficients of h(r) for `data`, constant term first. The first
+///block is multiplied by r the most times.
+fn coefficients(data: &[u8]) -> Vec<BigUint> {
+    let mut coefficients = vec![BigUint::zero()];
+    coefficients.extend(poly1305::blocks(data).into_iter().rev());
+    coefficients
+}
+
+#[cfg(test)]
+mod tests {
+    use aead;
+    use poly1305;
+
+    #[test]
+    fn recover_keys() {
+        let key = [0x42u8; 32];
+        let nonce = [7u8; 12];
+        let (first_ct, first_tag) = aead::seal(&key, &nonce, b"header one",
+                                               b"attack at dawn").unwrap();
+        let (second_ct, second_tag) = aead::seal(&key, &nonce, b"header two",
+                                                 b"retreat at dusk, then \
+                                                   regroup by the river")
+                                          .unwrap();
+        let keys = super::recover_keys(&aead::mac_data(b"header one",
+                                                       &first_ct),
+                                       &first_tag,
+                                       &aead::mac_data(b"header two",
+                                                       &second_ct),
+                                       &second_tag);
+        //only the clamped part of r matters, so that's all that comes back
+        let mut expected = aead::poly1305_key_gen(&key, &nonce);
+        let r = poly1305::tag_bytes(&poly1305::clamp(&expected[..16]));
+        expected[..16].copy_from_slice(&r);
+        assert_eq!(keys, vec![expected]);
+    }
+
+    #[test]
+    fn forge_after_nonce_reuse() {
+        let key = [0x13u8; 32];
+        let nonce = [0x37u8; 12];
+        let known = b"pay mallory 0010 dollars";
+        let (first_ct, first_tag) = aead::seal(&key, &nonce, b"", known)
+                                        .unwrap();
+        let (second_ct, second_tag) = aead::seal(&key, &nonce, b"",
+                                                 b"something else entirely")
+                                          .unwrap();
+        let keys = super::recover_keys(&aead::mac_data(b"", &first_ct),
+                                       &first_tag,
+                                       &aead::mac_data(b"", &second_ct),
+                                       &second_tag);
+        assert_eq!(keys.len(), 1);
+        //the reused keystream is known from the known plaintext
+        let wanted = b"pay mallory 9999 dollars";
+        let forged_ct = first_ct.iter().zip(known.iter()).zip(wanted.iter())
+                                .map(|((c, k), w)| c ^ k ^ w)
+                                .collect::<Vec<u8>>();
+        let forged_tag = poly1305::poly1305(&aead::mac_data(b"", &forged_ct),
+                                            &keys[0]);
+        assert_eq!(aead::open(&key, &nonce, b"", &forged_ct, &forged_tag)
+                       .unwrap(),
+                   &wanted[..]);
+    }
+}
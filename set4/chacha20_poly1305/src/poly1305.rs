@@ -0,0 +1,116 @@
+//!Poly1305 one-time authenticator, as in RFC 8439. Arithmetic is done with
+//!bignums for clarity, so only the final tag check is constant-time.
+
+use num::bigint::BigUint;
+use num::traits::{One, Zero};
+
+///Gives 2^130 - 5, the Poly1305 prime
+pub fn prime() -> BigUint {
+    (BigUint::one() << 130usize) - BigUint::from(5u32)
+}
+
+///Gives the Poly1305 tag of `message` under the one-time `key`
+pub fn poly1305(message: &[u8], key: &[u8; 32]) -> [u8; 16] {
+    let r = clamp(&key[..16]);
+    let s = BigUint::from_bytes_le(&key[16..]);
+    tag_bytes(&(evaluate(message, &r) + s))
+}
+
+///True if `tag` is the Poly1305 tag of `message` under `key`, otherwise false.
+///Takes the same time wherever the tags differ.
+pub fn verify(message: &[u8], key: &[u8; 32], tag: &[u8; 16]) -> bool {
+    constant_time_eq(&poly1305(message, key), tag)
+}
+
+///Gives the `r` half of a key, with the bits Poly1305 requires cleared
+pub fn clamp(r: &[u8]) -> BigUint {
+    let mut clamped = [0u8; 16];
+    clamped.copy_from_slice(&r[..16]);
+    for &idx in [3, 7, 11, 15].iter() {
+        clamped[idx] &= 0x0f;
+    }
+    for &idx in [4, 8, 12].iter() {
+        clamped[idx] &= 0xfc;
+    }
+    BigUint::from_bytes_le(&clamped)
+}
+
+///True if `r` could have come from `clamp`, otherwise false
+pub fn is_clamped(r: &BigUint) -> bool {
+    r.bits() <= 128 && clamp(&tag_bytes(r)) == *r
+}
+
+///Gives the numbers Poly1305 makes of `message`: each 16-byte chunk, read
+///little-endian, with a 1 bit set just above its top byte
+pub fn blocks(message: &[u8]) -> Vec<BigUint> {
+    message.chunks(16).map(|chunk| {
+        let mut bytes = chunk.to_vec();
+        bytes.push(1);
+        BigUint::from_bytes_le(&bytes)
+    }).collect()
+}
+
+///Gives the polynomial part of the tag, before `s` is added: the blocks of
+///`message` evaluated at `r`, modulo 2^130 - 5
+pub fn evaluate(message: &[u8], r: &BigUint) -> BigUint {
+    let p = prime();
+    blocks(message).into_iter().fold(BigUint::zero(), |acc, block| {
+        ((acc + block) * r) % &p
+    })
+}
+
+///Gives the low 128 bits of `n` as 16 little-endian bytes
+pub fn tag_bytes(n: &BigUint) -> [u8; 16] {
+    let mut tag = [0u8; 16];
+    for (el, byte) in tag.iter_mut().zip(n.to_bytes_le()) {
+        *el = byte;
+    }
+    tag
+}
+
+fn constant_time_eq(a: &[u8; 16], b: &[u8; 16]) -> bool {
+    a.iter().zip(b.iter()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use num::bigint::BigUint;
+
+    fn rfc_key() -> [u8; 32] {
+        [0x85, 0xd6, 0xbe, 0x78, 0x57, 0x55, 0x6d, 0x33, 0x7f, 0x44, 0x52, 0xfe,
+         0x42, 0xd5, 0x06, 0xa8, 0x01, 0x03, 0x80, 0x8a, 0xfb, 0x0d, 0xb2, 0xfd,
+         0x4a, 0xbf, 0xf6, 0xaf, 0x41, 0x49, 0xf5, 0x1b]
+    }
+
+    #[test]
+    fn poly1305() {
+        //RFC 8439, section 2.5.2
+        let expected = [0xa8, 0x06, 0x1d, 0xc1, 0x30, 0x51, 0x36, 0xc6, 0xc2,
+                        0x2b, 0x8b, 0xaf, 0x0c, 0x01, 0x27, 0xa9];
+        assert_eq!(super::poly1305(b"Cryptographic Forum Research Group",
+                                   &rfc_key()),
+                   expected);
+    }
+
+    #[test]
+    fn verify() {
+        let tag = super::poly1305(b"Cryptographic Forum Research Group",
+                                  &rfc_key());
+        assert!(super::verify(b"Cryptographic Forum Research Group",
+                              &rfc_key(), &tag));
+        let mut bad_tag = tag;
+        bad_tag[15] ^= 1;
+        assert!(!super::verify(b"Cryptographic Forum Research Group",
+                               &rfc_key(), &bad_tag));
+    }
+
+    #[test]
+    fn clamp() {
+        let clamped = super::clamp(&[0xff; 16]);
+        assert_eq!(clamped,
+                   BigUint::parse_bytes(b"0ffffffc0ffffffc0ffffffc0fffffff",
+                                        16).unwrap());
+        assert!(super::is_clamped(&clamped));
+        assert!(!super::is_clamped(&(clamped + BigUint::from(1u32))));
+    }
+}
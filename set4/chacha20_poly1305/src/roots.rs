@@ -0,0 +1,172 @@
+//!Root finding for polynomials over GF(2^130 - 5), by Cantor-Zassenhaus.
+//!Polynomials are coefficient vectors, constant term first.
+
+use num::bigint::BigUint;
+use num::traits::{One, Zero};
+use rand;
+use rand::Rng;
+
+use poly1305::prime;
+
+///Gives the distinct roots of `poly` in GF(2^130 - 5)
+pub fn roots(poly: &[BigUint]) -> Vec<BigUint> {
+    let p = prime();
+    let poly = trim(poly.iter().map(|c| c % &p).collect());
+    if poly.len() < 2 {
+        return Vec::new();
+    }
+    //x^p - x is the product of (x - a) over the whole field, so this gcd keeps
+    //just the distinct linear factors of `poly`
+    let x = vec![BigUint::zero(), BigUint::one()];
+    let x_to_p = pow_mod(&x, &p, &poly);
+    let linear_part = gcd(&poly, &sub(&x_to_p, &x));
+    split(&linear_part)
+}
+
+///Gives the roots of `poly`, which must be monic and a product of distinct
+///linear factors
+fn split(poly: &[BigUint]) -> Vec<BigUint> {
+    let p = prime();
+    match poly.len() {
+        0 | 1 => return Vec::new(),
+        2 => return vec![(&p - &poly[0]) % &p],
+        _ => {}
+    }
+    //for random a, (x + a)^((p-1)/2) - 1 vanishes at about half of the roots
+    let half = (&p - BigUint::one()) >> 1usize;
+    let mut rng = rand::thread_rng();
+    loop {
+        let mut a_bytes = [0u8; 17];
+        rng.fill_bytes(&mut a_bytes);
+        let x_plus_a = vec![BigUint::from_bytes_le(&a_bytes) % &p,
+                            BigUint::one()];
+        let power = pow_mod(&x_plus_a, &half, poly);
+        let factor = gcd(poly, &sub(&power, &[BigUint::one()]));
+        if factor.len() > 1 && factor.len() < poly.len() {
+            let (cofactor, _) = div_rem(poly, &factor);
+            let mut found = split(&factor);
+            found.extend(split(&cofactor));
+            return found;
+        }
+    }
+}
+
+fn trim(mut poly: Vec<BigUint>) -> Vec<BigUint> {
+    while poly.last().is_some_and(|c| c.is_zero()) {
+        poly.pop();
+    }
+    poly
+}
+
+fn sub(a: &[BigUint], b: &[BigUint]) -> Vec<BigUint> {
+    let p = prime();
+    let zero = BigUint::zero();
+    trim((0..a.len().max(b.len())).map(|idx| {
+        let x = a.get(idx).unwrap_or(&zero);
+        let y = b.get(idx).unwrap_or(&zero);
+        (x + &p - y) % &p
+    }).collect())
+}
+
+fn mul(a: &[BigUint], b: &[BigUint]) -> Vec<BigUint> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let p = prime();
+    let mut product = vec![BigUint::zero(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] = (&product[i + j] + x * y) % &p;
+        }
+    }
+    trim(product)
+}
+
+///Gives the quotient and remainder of `a` divided by nonzero `b`
+fn div_rem(a: &[BigUint], b: &[BigUint]) -> (Vec<BigUint>, Vec<BigUint>) {
+    let p = prime();
+    let lead_inverse = inverse(b.last().unwrap());
+    let mut remainder = trim(a.to_vec());
+    if remainder.len() < b.len() {
+        return (Vec::new(), remainder);
+    }
+    let mut quotient = vec![BigUint::zero(); remainder.len() - b.len() + 1];
+    while remainder.len() >= b.len() {
+        let shift = remainder.len() - b.len();
+        let coeff = (remainder.last().unwrap() * &lead_inverse) % &p;
+        for (idx, c) in b.iter().enumerate() {
+            remainder[shift + idx] = (&remainder[shift + idx] + &p
+                                      - (&coeff * c) % &p) % &p;
+        }
+        quotient[shift] = coeff;
+        remainder = trim(remainder);
+    }
+    (trim(quotient), remainder)
+}
+
+///Gives the monic greatest common divisor of `a` and `b`
+fn gcd(a: &[BigUint], b: &[BigUint]) -> Vec<BigUint> {
+    let mut a = trim(a.to_vec());
+    let mut b = trim(b.to_vec());
+    while !b.is_empty() {
+        let (_, remainder) = div_rem(&a, &b);
+        a = b;
+        b = remainder;
+    }
+    if let Some(lead) = a.last().cloned() {
+        let p = prime();
+        let lead_inverse = inverse(&lead);
+        for c in a.iter_mut() {
+            *c = (&*c * &lead_inverse) % &p;
+        }
+    }
+    a
+}
+
+///Gives `base`^`exp` modulo `modulus`
+fn pow_mod(base: &[BigUint], exp: &BigUint, modulus: &[BigUint])
+        -> Vec<BigUint> {
+    let base = div_rem(base, modulus).1;
+    let mut result = vec![BigUint::one()];
+    for bit in (0..exp.bits()).rev() {
+        result = div_rem(&mul(&result, &result), modulus).1;
+        if exp.bit(bit) {
+            result = div_rem(&mul(&result, &base), modulus).1;
+        }
+    }
+    result
+}
+
+fn inverse(n: &BigUint) -> BigUint {
+    let p = prime();
+    n.modpow(&(&p - BigUint::from(2u32)), &p)
+}
+
+#[cfg(test)]
+mod tests {
+    use num::bigint::BigUint;
+    use num::traits::One;
+
+    use poly1305::prime;
+
+    #[test]
+    fn roots() {
+        //(x - 3)(x - 5)(x - (p - 1)) = (x^2 - 8x + 15)(x + 1)
+        //                            = x^3 - 7x^2 + 7x + 15
+        let p = prime();
+        let poly = vec![BigUint::from(15u32), BigUint::from(7u32),
+                        &p - BigUint::from(7u32), BigUint::one()];
+        let mut roots = super::roots(&poly);
+        roots.sort();
+        assert_eq!(roots, vec![BigUint::from(3u32), BigUint::from(5u32),
+                               &p - BigUint::one()]);
+    }
+
+    #[test]
+    fn no_roots() {
+        //x^2 + 1 has no roots, since p = 3 mod 4
+        assert!(super::roots(&[BigUint::one(), BigUint::from(0u32),
+                               BigUint::one()]).is_empty());
+        assert!(super::roots(&[BigUint::from(4u32)]).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+
+//!Rewrites known plaintext inside a CBC or CTR ciphertext by flipping bits,
+//!without knowing the key.
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Mode {
+    ///Flips go into the previous ciphertext block, which then decrypts to
+    ///garbage
+    #[allow(dead_code)]
+    Cbc { block_size: usize },
+    ///Flips go straight into the ciphertext, and nothing else changes
+    #[allow(dead_code)]
+    Ctr,
+}
+
+///A ciphertext with bytes injected
+#[derive(Debug)]
+pub struct Injection {
+    pub ciphertext: Vec<u8>,
+    ///The plaintext block which now decrypts to garbage, if any
+    #[allow(dead_code)]
+    pub scrambled: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InjectError {
+    ///The known and desired bytes differ in length
+    LengthMismatch,
+    ///The bytes run past the end of the ciphertext
+    OutOfRange,
+    ///In CBC mode, the bytes have to be inside a single block
+    SpansBlocks,
+    ///In CBC mode, the first block can only be changed through the IV
+    FirstBlock,
+}
+
+impl fmt::Display for InjectError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(match *self {
+            InjectError::LengthMismatch =>
+                "Known and desired bytes differ in length",
+            InjectError::OutOfRange => "Injection runs past the ciphertext",
+            InjectError::SpansBlocks => "Injection spans CBC blocks",
+            InjectError::FirstBlock => "Injection is in the first CBC block",
+        })
+    }
+}
+
+impl Error for InjectError {
+    fn description(&self) -> &str {
+        match *self {
+            InjectError::LengthMismatch =>
+                "Known and desired bytes differ in length",
+            InjectError::OutOfRange => "Injection runs past the ciphertext",
+            InjectError::SpansBlocks => "Injection spans CBC blocks",
+            InjectError::FirstBlock => "Injection is in the first CBC block",
+        }
+    }
+}
+
+///Changes the plaintext bytes at `offset` of `ciphertext` from `known` to
+///`desired`. For CBC, `ciphertext` doesn't include the IV.
+pub fn inject(ciphertext: &[u8], known: &[u8], desired: &[u8], offset: usize,
+              mode: Mode) -> Result<Injection, InjectError> {
+    if known.len() != desired.len() {
+        return Err(InjectError::LengthMismatch);
+    }
+    if offset + known.len() > ciphertext.len() {
+        return Err(InjectError::OutOfRange);
+    }
+    let (target, scrambled) = match mode {
+        Mode::Ctr => (offset, None),
+        Mode::Cbc { block_size } => {
+            let block = offset / block_size;
+            if !known.is_empty()
+                    && (offset + known.len() - 1) / block_size != block {
+                return Err(InjectError::SpansBlocks);
+            }
+            if block == 0 {
+                return Err(InjectError::FirstBlock);
+            }
+            (offset - block_size, Some(block - 1))
+        }
+    };
+    let mut ciphertext = ciphertext.to_vec();
+    for (idx, (k, d)) in known.iter().zip(desired.iter()).enumerate() {
+        ciphertext[target + idx] ^= k ^ d;
+    }
+    Ok(Injection { ciphertext, scrambled })
+}
+
+///Gives where to inject `len` bytes in CBC mode so that both they and the
+///block scrambled before them sit inside the attacker-controlled plaintext
+///from `start` to `end`. That plaintext needs to be at least `len` bytes past
+///two whole blocks.
+#[allow(dead_code)]
+pub fn harmless_offset(start: usize, end: usize, len: usize,
+                       block_size: usize) -> Option<usize> {
+    if len > block_size {
+        return None;
+    }
+    //the first whole block inside the controlled bytes gets scrambled
+    let scrambled = start.div_ceil(block_size);
+    let offset = (scrambled + 1) * block_size;
+    if offset + len <= end {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InjectError, Mode};
+
+    #[test]
+    fn ctr() {
+        let injection = super::inject(&[0; 8], b"AAAA", b"ABCD", 3, Mode::Ctr)
+                            .unwrap();
+        assert_eq!(injection.ciphertext, vec![0, 0, 0, 0, 3, 2, 5, 0]);
+        assert_eq!(injection.scrambled, None);
+    }
+
+    #[test]
+    fn cbc() {
+        let mode = Mode::Cbc { block_size: 4 };
+        let injection = super::inject(&[0; 12], b"AA", b"BC", 9, mode)
+                            .unwrap();
+        assert_eq!(injection.ciphertext,
+                   vec![0, 0, 0, 0, 0, 3, 2, 0, 0, 0, 0, 0]);
+        assert_eq!(injection.scrambled, Some(1));
+    }
+
+    #[test]
+    fn errors() {
+        let cbc = Mode::Cbc { block_size: 4 };
+        let inject = |offset, mode| {
+            super::inject(&[0; 12], b"AA", b"BB", offset, mode).err()
+        };
+        assert_eq!(inject(11, Mode::Ctr), Some(InjectError::OutOfRange));
+        assert_eq!(inject(7, cbc), Some(InjectError::SpansBlocks));
+        assert_eq!(inject(1, cbc), Some(InjectError::FirstBlock));
+        assert_eq!(super::inject(&[0; 12], b"AA", b"B", 4, cbc).err(),
+                   Some(InjectError::LengthMismatch));
+    }
+
+    #[test]
+    fn harmless_offset() {
+        assert_eq!(super::harmless_offset(32, 64, 10, 16), Some(48));
+        assert_eq!(super::harmless_offset(33, 74, 10, 16), Some(64));
+        assert_eq!(super::harmless_offset(33, 73, 10, 16), None);
+        assert_eq!(super::harmless_offset(0, 64, 17, 16), None);
+    }
+}
@@ -3,21 +3,19 @@ extern crate rand;
 
 mod aes;
 mod ctr;
+mod inject;
 mod oracle;
 use oracle::Oracle;
 
 fn gain_admin(oracle: &Oracle) -> Vec<u8>{
-    use std::iter::repeat;
     let injection = "user=admin";
-    let aaa = repeat('A').take(injection.len()).collect::<String>();
+    let aaa = "A".repeat(injection.len());
     let canvas = oracle.encrypt(&aaa[..]);
     let prefix = "comment1=cooking%20MCs;userdata=";
     let target_idx = prefix.len(); //start of As
-    let mut paint = canvas.clone();
-    for idx in 0..injection.len() {
-        paint[target_idx + idx] ^= injection.as_bytes()[idx] ^ ('A' as u8);
-    }
-    paint
+    inject::inject(&canvas, aaa.as_bytes(), injection.as_bytes(), target_idx,
+                   inject::Mode::Ctr)
+        .unwrap().ciphertext
 }
 
 fn format_hex(hex: &[u8]) -> String {
@@ -35,3 +33,14 @@ fn main() {
     println!("Submitting ciphertext: {}", format_hex(&admin_ciphertext[..]));
     println!("Admin: {}", oracle.is_admin(&admin_ciphertext[..]));
 }
+
+#[cfg(test)]
+mod tests {
+    use oracle::Oracle;
+
+    #[test]
+    fn gain_admin() {
+        let oracle = Oracle::new();
+        assert!(oracle.is_admin(&super::gain_admin(&oracle)));
+    }
+}
@@ -1,38 +1,61 @@
-use crypto::{buffer, aes, blockmodes };
-use crypto::buffer::{ ReadBuffer, WriteBuffer, BufferResult };
+use crypto::aessafe::{AesSafe128Encryptor, AesSafe128Decryptor};
+use crypto::blowfish::Blowfish;
+use crypto::symmetriccipher::{BlockEncryptor, BlockDecryptor};
 pub use crypto::symmetriccipher::SymmetricCipherError;
 
-/// This function encrypts `plaintext` using CBC mode AES-128, under `key`.
-/// `plaintext` will be PKCS7 padded to a multiple of 128 bits.
-pub fn aes_cbc_encrypt(plaintext: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
+/// This function encrypts `plaintext` using CBC mode with `cipher`, starting
+/// from `iv`. `plaintext` will be PKCS7 padded to a multiple of the block size.
+pub fn cbc_encrypt<E: BlockEncryptor>(cipher: &E, plaintext: &[u8], iv: &[u8])
+        -> Vec<u8> {
+    let block_size = cipher.block_size();
+    let pad = block_size - plaintext.len() % block_size;
+    let mut padded = plaintext.to_vec();
+    padded.extend(vec![pad as u8; pad]);
+    let mut final_result = Vec::<u8>::with_capacity(padded.len());
+    let mut prev = iv.to_vec();
+    for chunk in padded.chunks(block_size) {
+        let input = chunk.iter().zip(prev.iter()).map(|(a, b)| a ^ b)
+                         .collect::<Vec<u8>>();
+        cipher.encrypt_block(&input, &mut prev);
+        final_result.extend_from_slice(&prev);
+    }
+    final_result
+}
+
+/// This function decrypts `ciphertext` using CBC mode with `cipher`, starting
+/// from `iv`. `ciphertext`'s length must be a multiple of the block size, and
+/// the PKCS7 padding must be valid.
+pub fn cbc_decrypt<D: BlockDecryptor>(cipher: &D, ciphertext: &[u8],
+                                      iv: &[u8])
+        -> Result<Vec<u8>, SymmetricCipherError> {
     use crypto::symmetriccipher::SymmetricCipherError::{InvalidLength,
         InvalidPadding};
-    let mut encryptor = aes::cbc_encryptor(
-        aes::KeySize::KeySize128,
-        key,
-        iv,
-        blockmodes::PkcsPadding);
-    let mut final_result = Vec::<u8>::new();
-    let mut read_buffer = buffer::RefReadBuffer::new(plaintext);
-    let mut buffer = [0; 4096];
-    let mut write_buffer = buffer::RefWriteBuffer::new(&mut buffer);
-    loop {
-        let result;
-        match encryptor.encrypt(&mut read_buffer, &mut write_buffer, true) {
-            Ok(r)  => result = r,
-            Err(e) => match e { //These should never happen
-                InvalidLength => panic!("Invalid Length in aes_cbc_encrypt"),
-                InvalidPadding => panic!("Invalid Padding in aes_cbc_encrypt"),
-            },
-        }
-        final_result.extend(write_buffer.take_read_buffer().take_remaining()
-                            .iter().cloned());
-        match result {
-            BufferResult::BufferUnderflow => break,
-            BufferResult::BufferOverflow => { }
-        }
+    let block_size = cipher.block_size();
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(block_size) {
+        return Err(InvalidLength);
     }
-    final_result
+    let mut final_result = Vec::<u8>::with_capacity(ciphertext.len());
+    let mut block = vec![0u8; block_size];
+    let mut prev = iv;
+    for chunk in ciphertext.chunks(block_size) {
+        cipher.decrypt_block(chunk, &mut block);
+        final_result.extend(block.iter().zip(prev.iter()).map(|(a, b)| a ^ b));
+        prev = chunk;
+    }
+    let len = final_result.len();
+    let pad = final_result[len - 1] as usize;
+    if pad == 0 || pad > block_size
+            || final_result[len - pad..].iter().any(|&b| b as usize != pad) {
+        return Err(InvalidPadding);
+    }
+    final_result.truncate(len - pad);
+    Ok(final_result)
+}
+
+/// This function encrypts `plaintext` using CBC mode AES-128, under `key`.
+/// `plaintext` will be PKCS7 padded to a multiple of 128 bits.
+pub fn aes_cbc_encrypt(plaintext: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
+    cbc_encrypt(&AesSafe128Encryptor::new(key), plaintext, iv)
 }
 
 /// This function decrypts `ciphertext` using CBC mode AES, using `key`.
@@ -40,31 +63,27 @@ pub fn aes_cbc_encrypt(plaintext: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
 /// be a PKCS7-padded string
 pub fn aes_cbc_decrypt(ciphertext: &[u8], key: &[u8], iv: &[u8]) ->
         Result<Vec<u8>, SymmetricCipherError> {
-    let mut decryptor = aes::cbc_decryptor(
-        aes::KeySize::KeySize128,
-        key,
-        iv,
-        blockmodes::PkcsPadding);
-    let mut final_result = Vec::<u8>::new();
-    let mut read_buffer = buffer::RefReadBuffer::new(ciphertext);
-    let mut buffer = [0; 4096];
-    let mut write_buffer = buffer::RefWriteBuffer::new(&mut buffer);
-    loop {
-        let result = try!(
-            decryptor.decrypt(&mut read_buffer, &mut write_buffer, true)
-        );
-        final_result.extend(write_buffer.take_read_buffer().take_remaining()
-                            .iter().cloned());
-        match result {
-            BufferResult::BufferUnderflow => break,
-            BufferResult::BufferOverflow => { }
-        }
-    }
-    Ok(final_result)
+    cbc_decrypt(&AesSafe128Decryptor::new(key), ciphertext, iv)
+}
+
+/// This function encrypts `plaintext` using CBC mode Blowfish, under `key`.
+/// `plaintext` will be PKCS7 padded to a multiple of 64 bits.
+pub fn blowfish_cbc_encrypt(plaintext: &[u8], key: &[u8], iv: &[u8])
+        -> Vec<u8> {
+    cbc_encrypt(&Blowfish::new(key), plaintext, iv)
+}
+
+/// This function decrypts `ciphertext` using CBC mode Blowfish, using `key`.
+/// `ciphertext`'s length must be a multiple of 64 bits.
+pub fn blowfish_cbc_decrypt(ciphertext: &[u8], key: &[u8], iv: &[u8]) ->
+        Result<Vec<u8>, SymmetricCipherError> {
+    cbc_decrypt(&Blowfish::new(key), ciphertext, iv)
 }
 
 #[cfg(test)]
 mod tests {
+    use crypto::aessafe::AesSafe128Encryptor;
+    use crypto::symmetriccipher::BlockEncryptor;
     use super::aes_cbc_encrypt;
 
     #[test]
@@ -101,4 +120,35 @@ mod tests {
         let output = aes_cbc_decrypt(&ciphertext, &key, &iv);
         assert!(output.is_err());
     }
+
+    #[test]
+    fn decrypt_invalid_padding() {
+        let key = "yellow submarine".as_bytes();
+        let iv = "the 1st 16 bytes".as_bytes();
+        //padding of 0 and of more than a block are both invalid
+        for &pad in [0u8, 17].iter() {
+            let mut plaintext = [pad; 16];
+            for (p, i) in plaintext.iter_mut().zip(iv.iter()) {
+                *p ^= *i;
+            }
+            let mut ciphertext = [0u8; 16];
+            AesSafe128Encryptor::new(key).encrypt_block(&plaintext,
+                                                        &mut ciphertext);
+            assert!(aes_cbc_decrypt(&ciphertext, key, iv).is_err());
+        }
+    }
+
+    use super::{blowfish_cbc_encrypt, blowfish_cbc_decrypt};
+
+    #[test]
+    fn blowfish_round_trip() {
+        let key = "8 bytes!".as_bytes();
+        for len in 0..20 {
+            let message = vec![b'A'; len];
+            let ciphertext = blowfish_cbc_encrypt(&message, key, key);
+            assert_eq!(ciphertext.len(), (len / 8 + 1) * 8);
+            assert_eq!(blowfish_cbc_decrypt(&ciphertext, key, key).unwrap(),
+                       message);
+        }
+    }
 }
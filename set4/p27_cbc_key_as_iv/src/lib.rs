@@ -1,38 +1,151 @@
 extern crate crypto;
 extern crate rand;
 
-mod cbc;
-mod oracle;
+pub mod cbc;
+pub mod oracle;
 
-//retrieves key for AES-128 CBC when key = IV
-fn crack_key(oracle : &oracle::Oracle) -> Vec<u8> {
-    //make the plain text five blocks long to preserve padding
-    let plaintext = std::iter::repeat('A').take(5*16).collect::<String>();
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+///Ways `crack_key` can fail when the oracle doesn't behave as expected
+#[derive(Debug, PartialEq)]
+pub enum CrackError {
+    ///The oracle rejected the padding of the substituted ciphertext
+    BadDecryption,
+    ///No guess for a byte gave valid padding
+    NoSolution,
+}
+
+impl Display for CrackError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            CrackError::BadDecryption => formatter.write_str(
+                "Oracle rejected the substituted ciphertext"),
+            CrackError::NoSolution =>
+                formatter.write_str("No guess gave valid padding"),
+        }
+    }
+}
+
+impl Error for CrackError {
+    fn description(&self) -> &str {
+        match *self {
+            CrackError::BadDecryption =>
+                "Oracle rejected the substituted ciphertext",
+            CrackError::NoSolution => "No guess gave valid padding",
+        }
+    }
+}
+
+//true if `oracle` accepts the padding of `ciphertext`
+fn padding_ok(oracle : &oracle::Oracle, ciphertext: &[u8]) -> bool {
+    !matches!(oracle.decrypt(ciphertext),
+              Err(oracle::DecryptError::BadDecryption))
+}
+
+//fills in the unknown bytes of `decrypted`, the raw block decryption of
+//`block`, by padding oracle, working back from the end
+fn padding_attack(oracle : &oracle::Oracle, block: &[u8],
+                  decrypted: &mut [Option<u8>]) -> Result<(), CrackError> {
+    let block_size = block.len();
+    for idx in (0..block_size).rev() {
+        if decrypted[idx].is_some() {
+            continue;
+        }
+        let pad = (block_size - idx) as u8;
+        //the block before `block`, set so that everything after `idx` is pad
+        let mut prev = decrypted.iter()
+                                .map(|d| d.map_or(0, |d| d ^ pad))
+                                .chain(block.iter().cloned())
+                                .collect::<Vec<u8>>();
+        for guess in 0..256 {
+            prev[idx] = guess as u8;
+            if !padding_ok(oracle, &prev) {
+                continue;
+            }
+            //on the last byte, the padding could be longer than meant
+            if idx == block_size - 1 && idx > 0 {
+                prev[idx - 1] ^= 1;
+                let ok = padding_ok(oracle, &prev);
+                prev[idx - 1] ^= 1;
+                if !ok {
+                    continue;
+                }
+            }
+            decrypted[idx] = Some(guess as u8 ^ pad);
+            break;
+        }
+        if decrypted[idx].is_none() {
+            return Err(CrackError::NoSolution);
+        }
+    }
+    Ok(())
+}
+
+///Retrieves the key for CBC when key = IV, for any block size. Whatever part
+///of the plaintext the oracle shows is used, and the rest comes from its
+///padding errors.
+pub fn crack_key(oracle : &oracle::Oracle) -> Result<Vec<u8>, CrackError> {
+    //encrypting nothing gives a single block of padding
+    let block_size = oracle.encrypt("").len();
+    let plaintext = "A".repeat(block_size);
     let ciphertext = oracle.encrypt(&plaintext[..]);
-    let block1 = ciphertext.chunks(16).next().unwrap();
-    let substitute = block1.iter().cloned()
-                           .chain(std::iter::repeat(0u8).take(16))
-                           .chain(block1.iter().cloned())
-                           .chain(ciphertext.iter().cloned().skip(3*16))
-                           .collect::<Vec<u8>>();
+    let block1 = &ciphertext[..block_size];
+    //block1 decrypts to plaintext ^ key, which comes out as is after a zero
+    //block. The original blocks go on the end to keep the padding valid.
+    let substitute = std::iter::repeat_n(0u8, block_size)
+                         .chain(block1.iter().cloned())
+                         .chain(ciphertext.iter().cloned())
+                         .collect::<Vec<u8>>();
     let sub_plain = match oracle.decrypt(&substitute[..]) {
         Err(oracle::DecryptError::BadAscii(v)) => v,
         Ok(s) => s.into_bytes(),
-        _ => panic!("Bad decryption"),
+        Err(_) => return Err(CrackError::BadDecryption),
     };
-    let mut sub_blocks = sub_plain.chunks(16);
-    sub_blocks.next().unwrap().iter().zip(
-                   sub_blocks.skip(1).next().unwrap().iter()
-               ).map(|(a,b)| a ^ b).collect::<Vec<u8>>()
+    let mut decrypted = (block_size..2*block_size)
+                            .map(|idx| sub_plain.get(idx).cloned())
+                            .collect::<Vec<Option<u8>>>();
+    padding_attack(oracle, block1, &mut decrypted)?;
+    Ok(decrypted.iter().zip(plaintext.bytes())
+                .map(|(d, p)| d.unwrap() ^ p).collect::<Vec<u8>>())
 }
 
-#[test]
-fn it_works() {
-    let oracle = oracle::Oracle::new();
-    let message = "Attack at dawn";
-    let ciphertext = oracle.encrypt(&message);
-    let key = crack_key(&oracle);
-    let decoded = cbc::aes_cbc_decrypt(&ciphertext[..], &key[..], &key[..])
-                  .unwrap();
-    assert_eq!(message.as_bytes(), &decoded[..])
+#[cfg(test)]
+mod tests {
+    use cbc;
+    use oracle::{Oracle, Cipher, Leak};
+    use super::crack_key;
+
+    #[test]
+    fn it_works() {
+        let oracle = Oracle::new();
+        let message = "Attack at dawn";
+        let ciphertext = oracle.encrypt(&message);
+        let key = crack_key(&oracle).unwrap();
+        let decoded = cbc::aes_cbc_decrypt(&ciphertext[..], &key[..], &key[..])
+                      .unwrap();
+        assert_eq!(message.as_bytes(), &decoded[..])
+    }
+
+    #[test]
+    fn restricted() {
+        let message = "Attack at dawn";
+        for &cipher in [Cipher::Aes128, Cipher::Blowfish].iter() {
+            let block_size = cipher.block_size();
+            for &leak in [Leak::Full, Leak::Prefix(block_size + 3),
+                          Leak::Prefix(1), Leak::Flag].iter() {
+                let oracle = Oracle::with(cipher, leak);
+                let ciphertext = oracle.encrypt(message);
+                let key = crack_key(&oracle).unwrap();
+                let decoded = match cipher {
+                    Cipher::Aes128 =>
+                        cbc::aes_cbc_decrypt(&ciphertext, &key, &key),
+                    Cipher::Blowfish =>
+                        cbc::blowfish_cbc_decrypt(&ciphertext, &key, &key),
+                };
+                assert_eq!(decoded.unwrap(), message.as_bytes(),
+                           "{:?} {:?}", cipher, leak);
+            }
+        }
+    }
 }
@@ -2,27 +2,82 @@ use cbc;
 
 use rand;
 
+///The block cipher an `Oracle` uses. The key is one block long, so that it
+///can also be the IV.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cipher {
+    Aes128,
+    Blowfish,
+}
+
+impl Cipher {
+    pub fn block_size(&self) -> usize {
+        match *self {
+            Cipher::Aes128 => 16,
+            Cipher::Blowfish => 8,
+        }
+    }
+}
+
+///How much of a decryption an `Oracle` gives back
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Leak {
+    ///All of it
+    Full,
+    ///Only the first `n` bytes
+    Prefix(usize),
+    ///None of it, only whether it succeeded and if not, why
+    Flag,
+}
+
 pub struct Oracle {
-    key: [u8; 16],
+    key: Vec<u8>,
+    cipher: Cipher,
+    leak: Leak,
 }
 
 impl Oracle {
     pub fn new() -> Oracle {
+        Oracle::with(Cipher::Aes128, Leak::Full)
+    }
+    pub fn with(cipher: Cipher, leak: Leak) -> Oracle {
         Oracle {
-            key: random_block(),
+            key: random_block(cipher.block_size()),
+            cipher,
+            leak,
         }
     }
     #[cfg(test)]
     pub fn controlled(key : &[u8; 16]) -> Oracle {
-        Oracle { key: key.clone() }
+        Oracle { key: key.to_vec(), cipher: Cipher::Aes128, leak: Leak::Full }
     }
     pub fn encrypt(&self, message: &str) -> Vec<u8> {
-        cbc::aes_cbc_encrypt(message.as_bytes(), &self.key, &self.key)
+        match self.cipher {
+            Cipher::Aes128 =>
+                cbc::aes_cbc_encrypt(message.as_bytes(), &self.key, &self.key),
+            Cipher::Blowfish =>
+                cbc::blowfish_cbc_encrypt(message.as_bytes(), &self.key,
+                                          &self.key),
+        }
     }
+    ///Decrypts `ciphertext`, giving back only as much of the plaintext as the
+    ///oracle's `Leak` allows, whether or not it's ASCII
     pub fn decrypt(&self, ciphertext: &[u8]) -> Result<String, DecryptError> {
-        if let Ok(decoded) = cbc::aes_cbc_decrypt(&ciphertext, &self.key,
-                                                  &self.key){
-            if decoded.iter().any(|c| c > &127u8) {
+        let decoded = match self.cipher {
+            Cipher::Aes128 =>
+                cbc::aes_cbc_decrypt(ciphertext, &self.key, &self.key),
+            Cipher::Blowfish =>
+                cbc::blowfish_cbc_decrypt(ciphertext, &self.key, &self.key),
+        };
+        if let Ok(mut decoded) = decoded {
+            let ascii = decoded.iter().all(|c| c <= &127u8);
+            let shown = match self.leak {
+                Leak::Full => decoded.len(),
+                Leak::Prefix(n) => n,
+                Leak::Flag => 0,
+            };
+            decoded.truncate(shown);
+            if !ascii {
                 Err(DecryptError::BadAscii(decoded))
             }
             else {
@@ -35,20 +90,23 @@ impl Oracle {
     }
 }
 
+impl Default for Oracle {
+    fn default() -> Oracle {
+        Oracle::new()
+    }
+}
+
 #[derive(Debug)]
 pub enum DecryptError {
     BadDecryption,
+    ///Holds as much of the plaintext as the oracle gives back
     BadAscii(Vec<u8>),
 }
 
-fn random_block() -> [u8; 16] {
+fn random_block(block_size: usize) -> Vec<u8> {
     use rand::Rng;
     let mut rng = rand::thread_rng();
-    let mut block = [0u8; 16];
-    for el in block.iter_mut() {
-        *el = rng.gen::<u8>();
-    }
-    block
+    (0..block_size).map(|_| rng.gen::<u8>()).collect()
 }
 
 #[cfg(test)]
@@ -65,4 +123,20 @@ mod tests {
                             0x89, 0xe5, 0x19, 0xc0, 0x2e, 0x5e, 0x3f];
         assert_eq!(output, expected);
     }
+
+    use super::{Cipher, DecryptError, Leak};
+
+    #[test]
+    fn leak() {
+        let oracle = Oracle::with(Cipher::Blowfish, Leak::Prefix(3));
+        assert_eq!(oracle.decrypt(&oracle.encrypt("hello world")).unwrap(),
+                   "hel");
+        let oracle = Oracle::with(Cipher::Aes128, Leak::Flag);
+        assert_eq!(oracle.decrypt(&oracle.encrypt("hello world")).unwrap(),
+                   "");
+        match oracle.decrypt(&oracle.encrypt("h\u{e9}llo world")) {
+            Err(DecryptError::BadAscii(ref leaked)) if leaked.is_empty() => {}
+            other => panic!("Expected nothing leaked, got {:?}", other),
+        }
+    }
 }
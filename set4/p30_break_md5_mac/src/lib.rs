@@ -1,9 +1,10 @@
-#[cfg(test)]
 extern crate rand;
 
 #[cfg(test)]
 mod md4_mac;
 
+pub mod md4_collision;
+
 mod md4;
 mod bits;
 
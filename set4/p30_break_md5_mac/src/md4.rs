@@ -2,8 +2,11 @@
 
 use bits;
 
+///Initial MD4 values
+pub const INITIAL_VALUES: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
 pub fn md4(message: &[u8]) -> [u8; 16] {
-    let mut md4_values = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    let mut md4_values = INITIAL_VALUES;
     for block in pad_and_partition(0, message) {
         md4_values = md4_continue(&block, &md4_values);
     }
@@ -55,14 +58,18 @@ pub fn create_pad(message_len: usize, length_addition: usize) -> Vec<u8> {
     .collect()
 }
 
+///MD4 round 1 auxiliary function
+pub fn f(x: u32, y: u32, z: u32) -> u32 { (x & y) | (!x & z) }
+
+///MD4 round 2 auxiliary function
+pub fn g(x: u32, y: u32, z: u32) -> u32 { (x & y) | (x & z) | (y & z) }
+
+///MD4 round 3 auxiliary function
+pub fn h(x: u32, y: u32, z: u32) -> u32 { x ^ y ^ z }
+
 ///Gives new MD4 values, given old values `md4_values` and a block `block` to
 ///digest
 pub fn md4_continue(block: &[u32; 16], md4_values: &[u32; 4]) -> [u32; 4] {
-    //auxilary functions
-    fn f(x: u32, y: u32, z: u32) -> u32 { (x & y) | (!x & z) }
-    fn g(x: u32, y: u32, z: u32) -> u32 { (x & y) | (x & z) | (y & z) }
-    fn h(x: u32, y: u32, z: u32) -> u32 { x ^ y ^ z }
-
     //process block
     let mut a = md4_values[0];
     let mut b = md4_values[1];
@@ -0,0 +1,228 @@
+//!MD4 collisions, following Wang et al., "Cryptanalysis of the Hash Functions
+//!MD4 and RIPEMD". Bit positions in this module are numbered from 1 (least
+//!significant) to 32 (most significant), as in the paper.
+
+use rand::Rng;
+use md4;
+use bits;
+
+///A sufficient condition on one bit of a chaining variable
+#[derive(Clone, Copy)]
+enum Cond {
+    ///Bit must be 0
+    Zero(u32),
+    ///Bit must be 1
+    One(u32),
+    ///Bit must equal the same bit of the previous chaining variable
+    Eq(u32),
+}
+
+use self::Cond::{Zero, One, Eq};
+
+///Conditions on a1, d1, c1, b1, ..., a4, d4, c4, b4
+const ROUND_1_CONDS: [&[Cond]; 16] = [
+    &[Eq(7)],
+    &[Zero(7), Eq(8), Eq(11)],
+    &[One(7), One(8), Zero(11), Eq(26)],
+    &[One(7), Zero(8), Zero(11), Zero(26)],
+    &[One(8), One(11), Zero(26), Eq(14)],
+    &[Zero(14), Eq(19), Eq(20), Eq(21), Eq(22), One(26)],
+    &[Eq(13), Zero(14), Eq(15), Zero(19), Zero(20), One(21), Zero(22)],
+    &[One(13), One(14), Zero(15), Eq(17), Zero(19), Zero(20), Zero(21),
+      Zero(22)],
+    &[One(13), One(14), One(15), Zero(17), Zero(19), Zero(20), Zero(21),
+      One(22), Eq(23), Eq(26)],
+    &[One(13), One(14), One(15), Zero(17), Zero(20), One(21), One(22),
+      Zero(23), One(26), Eq(30)],
+    &[One(17), Zero(20), Zero(21), Zero(22), Zero(23), Zero(26), One(30),
+      Eq(32)],
+    &[Zero(20), One(21), One(22), Eq(23), One(26), Zero(30), Zero(32)],
+    &[Zero(23), Zero(26), Eq(27), Eq(29), One(30), Zero(32)],
+    &[Zero(23), Zero(26), One(27), One(29), Zero(30), One(32)],
+    &[Eq(19), One(23), One(26), Zero(27), Zero(29), Zero(30)],
+    &[Zero(19), One(26), One(27), One(29), Zero(30)],
+];
+
+///Left rotations used in round 1, indexed by step
+const ROUND_1_SHIFTS: [u32; 4] = [3, 7, 11, 19];
+
+///Constant added in round 2
+const ROUND_2_K: u32 = 0x5a827999;
+
+///Tries to find a pair of distinct 64-byte blocks which give the same MD4
+///compression output when starting from the standard MD4 initial values.
+///Gives up and returns `None` after `max_tries` candidate blocks.
+pub fn find_collision<R: Rng>(rng: &mut R, max_tries: usize)
+        -> Option<([u8; 64], [u8; 64])> {
+    for _ in 0..max_tries {
+        let mut block = [0u32; 16];
+        for word in block.iter_mut() {
+            *word = rng.gen();
+        }
+        massage(&mut block);
+        let partner = differential_partner(&block);
+        if block != partner &&
+           md4::md4_continue(&block, &md4::INITIAL_VALUES) ==
+           md4::md4_continue(&partner, &md4::INITIAL_VALUES) {
+            return Some((to_bytes(&block), to_bytes(&partner)));
+        }
+    }
+    None
+}
+
+///Gives the block which differs from `block` by Wang's message differential:
+///ΔM = (m1 + 2^31, m2 + 2^31 - 2^28, m12 - 2^16)
+pub fn differential_partner(block: &[u32; 16]) -> [u32; 16] {
+    let mut partner = *block;
+    partner[1] = partner[1].wrapping_add(1 << 31);
+    partner[2] = partner[2].wrapping_add(1 << 31).wrapping_sub(1 << 28);
+    partner[12] = partner[12].wrapping_sub(1 << 16);
+    partner
+}
+
+///Modifies `block` so that all round 1 conditions hold (single-step
+///modification) and then corrects a5 and d5 (multi-step modification)
+pub fn massage(block: &mut [u32; 16]) {
+    //chaining variables, in the order a0, d0, c0, b0, a1, d1, c1, b1, ...
+    let mut state = [0u32; 20];
+    state[0] = md4::INITIAL_VALUES[0];
+    state[1] = md4::INITIAL_VALUES[3];
+    state[2] = md4::INITIAL_VALUES[2];
+    state[3] = md4::INITIAL_VALUES[1];
+
+    //single-step modification
+    for step in 0..16 {
+        let value = round_1_step(&state, step, block[step]);
+        state[step + 4] = apply_conds(value, state[step + 3],
+                                      ROUND_1_CONDS[step]);
+        block[step] = round_1_word(&state, step);
+    }
+
+    //multi-step modification of a5, by way of a1
+    let a5 = round_2_a5(&state, block);
+    let a5_conds = [(19, bit(state[18], 19)), (26, 1), (27, 0), (29, 1),
+                    (32, 1)];
+    for &(pos, wanted) in a5_conds.iter() {
+        if bit(a5, pos) != wanted {
+            state[4] ^= 1 << (pos - 1);
+            for (step, word) in block.iter_mut().enumerate().take(5) {
+                *word = round_1_word(&state, step);
+            }
+        }
+    }
+
+    //multi-step modification of d5, by way of a2
+    let a5 = round_2_a5(&state, block);
+    let d5 = round_2_d5(&state, block, a5);
+    let d5_conds = [(19, bit(a5, 19)), (26, bit(state[19], 26)),
+                    (27, bit(state[19], 27)), (29, bit(state[19], 29)),
+                    (32, bit(state[19], 32))];
+    for &(pos, wanted) in d5_conds.iter() {
+        if bit(d5, pos) != wanted {
+            state[8] ^= 1 << (pos - 3);
+            for (step, word) in block.iter_mut().enumerate().take(9).skip(4) {
+                *word = round_1_word(&state, step);
+            }
+        }
+    }
+}
+
+///Gives bit `pos` of `word`
+fn bit(word: u32, pos: u32) -> u32 {
+    (word >> (pos - 1)) & 1
+}
+
+///Forces `value` to satisfy `conds`, where `prev` is the previous chaining
+///variable
+fn apply_conds(value: u32, prev: u32, conds: &[Cond]) -> u32 {
+    let mut value = value;
+    for cond in conds.iter() {
+        let (pos, wanted) = match *cond {
+            Zero(pos) => (pos, 0),
+            One(pos) => (pos, 1),
+            Eq(pos) => (pos, bit(prev, pos)),
+        };
+        value = value & !(1 << (pos - 1)) | wanted << (pos - 1);
+    }
+    value
+}
+
+///Gives the chaining variable produced by round 1 step `step` with message
+///word `word`
+fn round_1_step(state: &[u32; 20], step: usize, word: u32) -> u32 {
+    state[step]
+        .wrapping_add(md4::f(state[step + 3], state[step + 2], state[step + 1]))
+        .wrapping_add(word)
+        .rotate_left(ROUND_1_SHIFTS[step % 4])
+}
+
+///Gives the message word which makes round 1 step `step` produce
+///`state[step + 4]`
+fn round_1_word(state: &[u32; 20], step: usize) -> u32 {
+    state[step + 4]
+        .rotate_right(ROUND_1_SHIFTS[step % 4])
+        .wrapping_sub(state[step])
+        .wrapping_sub(md4::f(state[step + 3], state[step + 2], state[step + 1]))
+}
+
+///Gives a5, the output of the first step of round 2
+fn round_2_a5(state: &[u32; 20], block: &[u32; 16]) -> u32 {
+    state[16]
+        .wrapping_add(md4::g(state[19], state[18], state[17]))
+        .wrapping_add(block[0])
+        .wrapping_add(ROUND_2_K)
+        .rotate_left(3)
+}
+
+///Gives d5, the output of the second step of round 2
+fn round_2_d5(state: &[u32; 20], block: &[u32; 16], a5: u32) -> u32 {
+    state[17]
+        .wrapping_add(md4::g(a5, state[19], state[18]))
+        .wrapping_add(block[4])
+        .wrapping_add(ROUND_2_K)
+        .rotate_left(5)
+}
+
+///Converts a block of words to bytes, in the order MD4 reads them
+fn to_bytes(block: &[u32; 16]) -> [u8; 64] {
+    let mut bytes = [0; 64];
+    for (dst, src) in bytes.iter_mut().zip(bits::u32_to_u8_le(&block[..])) {
+        *dst = src;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use md4;
+
+    #[test]
+    fn find_collision() {
+        let mut rng = ::rand::thread_rng();
+        let (block_a, block_b) = super::find_collision(&mut rng, 1 << 24)
+            .expect("No collision found");
+        assert!(block_a[..] != block_b[..], "Blocks are identical");
+        assert_eq!(md4::md4(&block_a), md4::md4(&block_b));
+    }
+
+    #[test]
+    fn massage_round_1() {
+        let mut rng = ::rand::thread_rng();
+        let mut block = [0u32; 16];
+        for word in block.iter_mut() {
+            *word = ::rand::Rng::gen(&mut rng);
+        }
+        super::massage(&mut block);
+        let mut state = [0u32; 20];
+        state[0] = md4::INITIAL_VALUES[0];
+        state[1] = md4::INITIAL_VALUES[3];
+        state[2] = md4::INITIAL_VALUES[2];
+        state[3] = md4::INITIAL_VALUES[1];
+        for step in 0..16 {
+            state[step + 4] = super::round_1_step(&state, step, block[step]);
+            assert_eq!(super::apply_conds(state[step + 4], state[step + 3],
+                                          super::ROUND_1_CONDS[step]),
+                       state[step + 4], "Round 1 step {} violated", step);
+        }
+    }
+}
@@ -18,7 +18,23 @@ fn main() {
     }
 }
 
-fn crack(mut client: &mut Client) -> Option<[u8; 20]> {
+fn crack(client: &mut Client) -> Option<[u8; 20]> {
+    let query = guess_signature(|query| get_delay(client, query));
+    println!("");
+    let target = format!("{}/test?file={}&signature={}",
+                         TARGET_HOST, TARGET_FILE, format_hex(&query[..]));
+    let response = client.get(&target[..]).send().unwrap();
+    match response.status {
+        StatusCode::Ok => Some(query),
+        StatusCode::InternalServerError => None,
+        code => panic!("Unexpected status code: {}", code),
+    }
+}
+
+///Builds up the signature a byte at a time, keeping whichever byte takes the
+///longest to be rejected according to `get_delay`
+fn guess_signature<F>(mut get_delay: F) -> [u8; 20]
+        where F: FnMut(&[u8]) -> u64 {
     use std::io::Write;
     let mut query = [0u8; 20];
     for idx in 0..query.len() {
@@ -26,7 +42,7 @@ fn crack(mut client: &mut Client) -> Option<[u8; 20]> {
         let mut max_delay = 0;
         for byte in (0usize..256).map(|n| n as u8) {
             query[idx] = byte;
-            let delay = get_delay(client, &query[..]);
+            let delay = get_delay(&query[..]);
             //println!("{}: {}", format_hex(&query[..idx+1]), delay);
             if delay > max_delay {
                 best_query = byte;
@@ -37,15 +53,7 @@ fn crack(mut client: &mut Client) -> Option<[u8; 20]> {
         print!("\rCracking: {}", format_hex(&query[..idx+1]));
         std::io::stdout().flush().unwrap();
     }
-    println!("");
-    let target = format!("{}/test?file={}&signature={}",
-                         TARGET_HOST, TARGET_FILE, format_hex(&query[..]));
-    let response = client.get(&target[..]).send().unwrap();
-    match response.status {
-        StatusCode::Ok => Some(query),
-        StatusCode::InternalServerError => None,
-        code => panic!("Unexpected status code: {}", code),
-    }
+    query
 }
 
 fn get_delay(client: &mut Client, query: &[u8]) -> u64 {
@@ -65,3 +73,41 @@ fn format_hex(hex: &[u8]) -> String {
     }
     s
 }
+
+#[cfg(test)]
+mod tests {
+    use super::guess_signature;
+
+    const SIGNATURE: [u8; 20] = [0x5a; 20];
+    const SLEEP: u64 = 50_000_000;
+
+    //a stand-in for the server's response time, with up to 10ms of jitter
+    fn simulated_delay(state: &mut u64, matching: usize) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        matching as u64 * SLEEP + *state % 10_000_000
+    }
+
+    #[test]
+    fn cracks_insecure_compare() {
+        let mut state = 1u64;
+        let guess = guess_signature(|query| {
+            let matching = query.iter().zip(SIGNATURE.iter())
+                                .take_while(|&(x, y)| x == y).count();
+            //insecure_compare sleeps for every matching byte
+            simulated_delay(&mut state, matching)
+        });
+        assert_eq!(guess, SIGNATURE);
+    }
+
+    #[test]
+    fn fails_constant_compare() {
+        let mut state = 1u64;
+        let guess = guess_signature(|_| {
+            //constant_compare sleeps for every byte whatever the query is
+            simulated_delay(&mut state, SIGNATURE.len())
+        });
+        assert!(guess != SIGNATURE);
+    }
+}
@@ -3,6 +3,7 @@ extern crate hyper;
 extern crate rustc_serialize;
 extern crate rand;
 
+mod ct;
 mod hmac_sha1;
 
 use hyper::server::{Server, Request, Response};
@@ -12,20 +13,49 @@ use hyper::uri::RequestUri::AbsolutePath;
 
 const HOST: &'static str = "localhost:9000";
 
+///How the server checks signatures
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Compare {
+    ///Byte-by-byte with an early exit, which leaks the signature
+    Insecure,
+    ///Same delay whatever the signature, then `ct::eq`
+    Constant,
+}
+
 fn main() {
+    let compare = match parse_compare(std::env::args().skip(1)) {
+        Some(compare) => compare,
+        None => {
+            println!("Usage: server [--compare=insecure|constant]");
+            std::process::exit(1);
+        }
+    };
     let key = gen_key();
     println!("Key: {} (len {})", format_hex(&key[..]), key.len());
     let server = Server::http(HOST).unwrap();
     println!("test.txt hmac: {} (Shhhh!)",
              format_hex(&file_hmac(&key[..], "test.txt").unwrap()[..]));
+    println!("Comparing signatures with {:?}", compare);
     println!("Listening on port 9000");
     server.handle(
         move |req: Request, res: Response| {
-            handle_request(&key[..], req, res)
+            handle_request(&key[..], compare, req, res)
         }
     ).unwrap();
 }
 
+fn parse_compare<I: Iterator<Item = String>>(args: I) -> Option<Compare> {
+    let mut compare = Compare::Insecure;
+    for arg in args {
+        compare = match &arg[..] {
+            "--compare=insecure" => Compare::Insecure,
+            "--compare=constant" => Compare::Constant,
+            _ => return None,
+        };
+    }
+    Some(compare)
+}
+
 fn format_hex(hex: &[u8]) -> String {
     use std::fmt::Write;
     let mut s = String::new();
@@ -42,11 +72,13 @@ fn gen_key() -> Vec<u8> {
     rng.gen_iter().take(key_len).collect()
 }
 
-fn handle_request(key: &[u8], req: Request, mut res: Response<Fresh>) {
+fn handle_request(key: &[u8], compare: Compare, req: Request,
+                  mut res: Response<Fresh>) {
     match req.method {
         hyper::Get => {
             match req.uri {
-                AbsolutePath(path) => *res.status_mut() = handle_path(key, &path[..]),
+                AbsolutePath(path) =>
+                    *res.status_mut() = handle_path(key, compare, &path[..]),
                 _ => *res.status_mut() = StatusCode::NotFound,
             }
         },
@@ -55,15 +87,17 @@ fn handle_request(key: &[u8], req: Request, mut res: Response<Fresh>) {
     send_response(res);
 }
 
-fn handle_path(key: &[u8], path: &str) -> StatusCode {
+fn handle_path(key: &[u8], compare: Compare, path: &str) -> StatusCode {
     let full_path = format!("http://{}/{}", HOST, path);
-    match hyper::Url::parse(&full_path[..]).ok().and_then(|url| url.query_pairs()) {
-        Some(pairs) => {
+    match hyper::Url::parse(&full_path[..]) {
+        Ok(url) => {
+            let pairs: Vec<_> = url.query_pairs().into_owned().collect();
             if pairs.len() == 2 {
                 let (ref arg1, ref filename) = pairs[0];
                 let (ref arg2, ref signature) = pairs[1];
                 if &arg1[..]=="file" && &arg2[..]=="signature" {
-                        check_signature(key, &filename[..], &signature[..])
+                        check_signature(key, compare, &filename[..],
+                                        &signature[..])
                     }
                 else { StatusCode::BadRequest }
             }
@@ -89,7 +123,8 @@ fn send_response(res: Response) {
     }
 }
 
-fn check_signature(key: &[u8], filename: &str, signature: &str) -> StatusCode {
+fn check_signature(key: &[u8], compare: Compare, filename: &str,
+                   signature: &str) -> StatusCode {
     use rustc_serialize::hex::FromHex;
     let parsed_signature = match signature.from_hex() {
         Ok(sig) => sig,
@@ -99,7 +134,13 @@ fn check_signature(key: &[u8], filename: &str, signature: &str) -> StatusCode {
         Ok(sha1) => sha1,
         _ => return StatusCode::NotFound,
     };
-    if insecure_compare(&file_hash[..], &parsed_signature[..]) {
+    let equal = match compare {
+        Compare::Insecure =>
+            insecure_compare(&file_hash[..], &parsed_signature[..]),
+        Compare::Constant =>
+            constant_compare(&file_hash[..], &parsed_signature[..]),
+    };
+    if equal {
         StatusCode::Ok
     }
     else {
@@ -127,6 +168,15 @@ fn insecure_compare(first: &[u8], second: &[u8]) -> bool {
     true
 }
 
+///Like `insecure_compare`, but sleeps for every byte of `expected` whatever
+///`given` is, so the response time says nothing about the signature
+fn constant_compare(expected: &[u8], given: &[u8]) -> bool {
+    for _ in expected.iter() {
+        std::thread::sleep_ms(50);
+    }
+    ct::eq(expected, given)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -138,4 +188,33 @@ mod tests {
             "should have been unequal");
     }
 
+    #[test] #[ignore]
+    fn constant_compare() {
+        use std::time::Instant;
+        assert!(super::constant_compare(b"yellow submarine", b"yellow submarine"),
+            "should have been equal");
+        let start = Instant::now();
+        assert!(!super::constant_compare(b"yellow submarine", b"Yellow submarine"),
+            "should have been unequal");
+        let early = start.elapsed();
+        let start = Instant::now();
+        assert!(!super::constant_compare(b"yellow submarine", b"yellow submarinE"),
+            "should have been unequal");
+        let late = start.elapsed();
+        //the same sleeps happen either way, so only scheduling noise differs
+        assert!(late < early + early / 20 && early < late + late / 20);
+    }
+
+    #[test]
+    fn parse_compare() {
+        use super::Compare;
+        let parse = |args: &[&str]| {
+            super::parse_compare(args.iter().map(|arg| arg.to_string()))
+        };
+        assert_eq!(parse(&[]), Some(Compare::Insecure));
+        assert_eq!(parse(&["--compare=insecure"]), Some(Compare::Insecure));
+        assert_eq!(parse(&["--compare=constant"]), Some(Compare::Constant));
+        assert_eq!(parse(&["--compare=fast"]), None);
+    }
+
 }
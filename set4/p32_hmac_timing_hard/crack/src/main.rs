@@ -18,13 +18,8 @@ fn main() {
     }
 }
 
-fn crack(mut client: &mut Client) -> Option<[u8; 20]> {
-    use std::io::Write;
-    let mut query = [0u8; 20];
-    for idx in 0..query.len() {
-        query[idx] = crack_idx(client, &mut query, idx);
-        std::io::stdout().flush().unwrap();
-    }
+fn crack(client: &mut Client) -> Option<[u8; 20]> {
+    let query = guess_signature(|query| get_delay(client, query));
     println!("");
     let target = format!("{}/test?file={}&signature={}",
                          TARGET_HOST, TARGET_FILE, format_hex(&query[..]));
@@ -36,14 +31,28 @@ fn crack(mut client: &mut Client) -> Option<[u8; 20]> {
     }
 }
 
-fn crack_idx(mut client: &mut Client, query: &mut [u8; 20], idx: usize) -> u8 {
+///Builds up the signature a byte at a time, keeping whichever byte takes the
+///longest to be rejected according to `get_delay`
+fn guess_signature<F>(mut get_delay: F) -> [u8; 20]
+        where F: FnMut(&[u8]) -> u64 {
+    use std::io::Write;
+    let mut query = [0u8; 20];
+    for idx in 0..query.len() {
+        query[idx] = crack_idx(&mut get_delay, &mut query, idx);
+        std::io::stdout().flush().unwrap();
+    }
+    query
+}
+
+fn crack_idx<F>(get_delay: &mut F, query: &mut [u8; 20], idx: usize) -> u8
+        where F: FnMut(&[u8]) -> u64 {
     const NPASSES: usize = 10;
     //get delays
     let mut delays = vec![Vec::new(); 256];
     for pass in 0..(NPASSES as u64) {
         for byte in (0usize..256) {
             query[idx] = byte as u8;
-            let new_delay = get_delay(client, &query[..]);
+            let new_delay = get_delay(&query[..]);
             delays[byte].push(new_delay);
             let complete = idx*NPASSES*256 + (pass as usize)*256 + byte;
             let todo = query.len()*NPASSES*256;
@@ -82,3 +91,41 @@ fn format_hex(hex: &[u8]) -> String {
     }
     s
 }
+
+#[cfg(test)]
+mod tests {
+    use super::guess_signature;
+
+    const SIGNATURE: [u8; 20] = [0x5a; 20];
+    const SLEEP: u64 = 1_000_000;
+
+    //a stand-in for the server's response time, with up to 1ms of jitter
+    fn simulated_delay(state: &mut u64, matching: usize) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        matching as u64 * SLEEP + *state % 1_000_000
+    }
+
+    #[test]
+    fn cracks_insecure_compare() {
+        let mut state = 1u64;
+        let guess = guess_signature(|query| {
+            let matching = query.iter().zip(SIGNATURE.iter())
+                                .take_while(|&(x, y)| x == y).count();
+            //insecure_compare sleeps for every matching byte
+            simulated_delay(&mut state, matching)
+        });
+        assert_eq!(guess, SIGNATURE);
+    }
+
+    #[test]
+    fn fails_constant_compare() {
+        let mut state = 1u64;
+        let guess = guess_signature(|_| {
+            //constant_compare sleeps for every byte whatever the query is
+            simulated_delay(&mut state, SIGNATURE.len())
+        });
+        assert!(guess != SIGNATURE);
+    }
+}
@@ -0,0 +1,268 @@
+//!Birthday attacks on truncated MACs and hashes. Truncating a tag to `n` bits
+//!means a collision can be found in about 2^(n/2) evaluations, whatever the
+//!strength of the underlying function.
+
+#[cfg(test)]
+extern crate crypto;
+extern crate rand;
+
+#[cfg(test)]
+mod sha1_mac;
+#[cfg(test)]
+mod md4_mac;
+#[cfg(test)]
+mod md4;
+#[cfg(test)]
+mod bits;
+
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use rand::Rng;
+
+///How to search for a collision
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    ///Store every tag in a hash table. Fastest, but memory use grows with the
+    ///work done.
+    Table,
+    ///Pollard rho with distinguished points: walk x -> tag(x) from random
+    ///starts, only storing points whose low `distinguished_bits` bits are zero.
+    ///Memory use is about 2^-`distinguished_bits` that of `Table`.
+    Rho { distinguished_bits: u32 },
+}
+
+///Two distinct inputs with the same truncated tag
+#[derive(Debug, Clone)]
+pub struct Collision {
+    pub a: Vec<u8>,
+    pub b: Vec<u8>,
+    ///Shared truncated tag
+    pub tag: Vec<u8>,
+    ///Number of times the tag function was called
+    pub evaluations: u64,
+    ///Expected number of calls for a generic birthday attack
+    pub expected_evaluations: f64,
+    ///Number of tags (or distinguished points) held in memory at the end
+    pub stored: usize,
+}
+
+impl Collision {
+    ///Ratio of actual to expected work
+    pub fn work_ratio(&self) -> f64 {
+        self.evaluations as f64 / self.expected_evaluations
+    }
+}
+
+///Expected number of evaluations to find a collision in a `bits`-bit tag:
+///sqrt(pi / 2 * 2^bits)
+pub fn expected_evaluations(bits: u32) -> f64 {
+    (std::f64::consts::PI / 2.0 * 2f64.powi(bits as i32)).sqrt()
+}
+
+///Keeps the first `bits` bits of `tag`, zeroing the rest of the last byte.
+///Panics if `tag` is shorter than `bits` bits.
+pub fn truncate(tag: &[u8], bits: u32) -> Vec<u8> {
+    let nbytes = (bits as usize).div_ceil(8);
+    assert!(bits > 0 && nbytes <= tag.len(),
+            "Cannot truncate to {} bits", bits);
+    let mut truncated = tag[..nbytes].to_vec();
+    let spare = nbytes as u32 * 8 - bits;
+    if let Some(last) = truncated.last_mut() {
+        *last &= 0xff << spare;
+    }
+    truncated
+}
+
+///Finds two distinct inputs whose outputs under `tag_fn` agree in their first
+///`bits` bits. Inputs are `bits`-bit strings, packed into bytes like the
+///truncated tags.
+pub fn find_collision<F>(tag_fn: F, bits: u32, mode: Mode) -> Collision
+        where F: Fn(&[u8]) -> Vec<u8> {
+    let evaluations = Cell::new(0u64);
+    let tag = |input: &[u8]| {
+        evaluations.set(evaluations.get() + 1);
+        truncate(&tag_fn(input), bits)
+    };
+    let (a, b, stored) = match mode {
+        Mode::Table => table_search(&tag, bits),
+        Mode::Rho { distinguished_bits } =>
+            rho_search(&tag, bits, distinguished_bits),
+    };
+    Collision {
+        tag: truncate(&tag_fn(&a), bits),
+        a,
+        b,
+        evaluations: evaluations.get(),
+        expected_evaluations: expected_evaluations(bits),
+        stored,
+    }
+}
+
+///Hash table search over random inputs
+fn table_search<F>(tag: &F, bits: u32) -> (Vec<u8>, Vec<u8>, usize)
+        where F: Fn(&[u8]) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let mut seen = HashMap::new();
+    loop {
+        let input = random_input(&mut rng, bits);
+        match seen.insert(tag(&input), input.clone()) {
+            Some(other) if other != input => {
+                let stored = seen.len();
+                return (other, input, stored);
+            }
+            _ => {}
+        }
+    }
+}
+
+///Pollard rho search with distinguished points
+fn rho_search<F>(tag: &F, bits: u32, distinguished_bits: u32)
+        -> (Vec<u8>, Vec<u8>, usize) where F: Fn(&[u8]) -> Vec<u8> {
+    assert!(distinguished_bits < bits, "Too many distinguished bits");
+    let mut rng = rand::thread_rng();
+    //walks which have not hit a distinguished point by then are probably
+    //stuck in a cycle
+    let max_len = 20u64 << distinguished_bits;
+    //distinguished point -> (start of walk, length of walk)
+    let mut trails = HashMap::new();
+    loop {
+        let start = random_input(&mut rng, bits);
+        let mut point = start.clone();
+        let mut len = 0;
+        while !is_distinguished(&point, bits, distinguished_bits) {
+            if len == max_len {
+                break;
+            }
+            point = tag(&point);
+            len += 1;
+        }
+        if len == max_len {
+            continue;
+        }
+        match trails.get(&point).cloned() {
+            Some((other_start, other_len)) => {
+                if let Some((a, b)) = merge_point(tag, start, len, other_start,
+                                                  other_len) {
+                    return (a, b, trails.len());
+                }
+            }
+            None => {
+                trails.insert(point, (start, len));
+            }
+        }
+    }
+}
+
+///Given two walks which end at the same point, finds the distinct inputs at
+///which they merge. Returns `None` if one walk is part of the other.
+fn merge_point<F>(tag: &F, a: Vec<u8>, a_len: u64, b: Vec<u8>, b_len: u64)
+        -> Option<(Vec<u8>, Vec<u8>)> where F: Fn(&[u8]) -> Vec<u8> {
+    let (mut a, mut b) = (a, b);
+    for _ in b_len..a_len {
+        a = tag(&a);
+    }
+    for _ in a_len..b_len {
+        b = tag(&b);
+    }
+    if a == b {
+        return None;
+    }
+    loop {
+        let next_a = tag(&a);
+        let next_b = tag(&b);
+        if next_a == next_b {
+            return Some((a, b));
+        }
+        a = next_a;
+        b = next_b;
+    }
+}
+
+///True if the low `distinguished_bits` of `bits`-bit `point` are zero
+fn is_distinguished(point: &[u8], bits: u32, distinguished_bits: u32) -> bool {
+    let spare = point.len() as u32 * 8 - bits;
+    let mut remaining = distinguished_bits + spare;
+    for &byte in point.iter().rev() {
+        if remaining == 0 {
+            break;
+        }
+        let checked = remaining.min(8);
+        if byte & (0xffu16 >> (8 - checked)) as u8 != 0 {
+            return false;
+        }
+        remaining -= checked;
+    }
+    true
+}
+
+///Random `bits`-bit input, packed like a truncated tag
+fn random_input<R: Rng>(rng: &mut R, bits: u32) -> Vec<u8> {
+    let mut input = vec![0u8; (bits as usize).div_ceil(8)];
+    rng.fill_bytes(&mut input);
+    truncate(&input, bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_collision, truncate, Mode};
+    use sha1_mac::Sha1Mac;
+    use md4_mac::Md4Mac;
+
+    #[test]
+    fn truncate_bits() {
+        assert_eq!(truncate(&[0xff, 0xff, 0xff], 12), vec![0xff, 0xf0]);
+        assert_eq!(truncate(&[0x12, 0x34, 0x56], 16), vec![0x12, 0x34]);
+        assert_eq!(truncate(&[0x12, 0x34, 0x56], 1), vec![0x00]);
+    }
+
+    #[test]
+    #[should_panic(expected="Cannot truncate")]
+    fn truncate_too_far() {
+        truncate(&[0x12, 0x34], 17);
+    }
+
+    #[test]
+    fn is_distinguished() {
+        assert!(super::is_distinguished(&[0xab, 0xc0], 12, 2));
+        assert!(!super::is_distinguished(&[0xab, 0xd0], 12, 2));
+        assert!(super::is_distinguished(&[0xab, 0x00], 16, 8));
+        assert!(!super::is_distinguished(&[0xab, 0x00], 16, 9));
+    }
+
+    fn check(collision: &super::Collision, bits: u32,
+             tag_fn: &dyn Fn(&[u8]) -> Vec<u8>) {
+        assert!(collision.a != collision.b);
+        assert_eq!(truncate(&tag_fn(&collision.a), bits), collision.tag);
+        assert_eq!(truncate(&tag_fn(&collision.b), bits), collision.tag);
+        assert!(collision.work_ratio() < 20.0, "Far too much work: {:.2}x",
+                collision.work_ratio());
+    }
+
+    #[test]
+    fn sha1_mac_table() {
+        let mac = Sha1Mac::new(b"yellow submarine");
+        let tag_fn = |msg: &[u8]| mac.hash(msg).to_vec();
+        let collision = find_collision(tag_fn, 24, Mode::Table);
+        check(&collision, 24, &tag_fn);
+    }
+
+    #[test]
+    fn sha1_mac_rho() {
+        let mac = Sha1Mac::new(b"yellow submarine");
+        let tag_fn = |msg: &[u8]| mac.hash(msg).to_vec();
+        let collision = find_collision(tag_fn, 28,
+                                       Mode::Rho { distinguished_bits: 6 });
+        check(&collision, 28, &tag_fn);
+    }
+
+    #[test]
+    fn md4_mac_rho() {
+        let mac = Md4Mac::new(b"octopus's garden");
+        let tag_fn = |msg: &[u8]| mac.hash(msg).to_vec();
+        let collision = find_collision(tag_fn, 32,
+                                       Mode::Rho { distinguished_bits: 8 });
+        check(&collision, 32, &tag_fn);
+        assert!(collision.stored < 4096, "Too many distinguished points kept");
+    }
+}
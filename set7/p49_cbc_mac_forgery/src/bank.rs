@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use rand;
+
+use cbc_mac;
+use transfer::{Transfer, TransferList, TransferError};
+
+///Transfer request for the first protocol, where the client chooses the IV
+#[derive(Debug, Clone)]
+pub struct IvRequest {
+    pub message: Vec<u8>,
+    pub iv: [u8; 16],
+    pub mac: [u8; 16],
+}
+
+///Transfer request for the second protocol, where the IV is fixed at zero
+#[derive(Debug, Clone)]
+pub struct FixedIvRequest {
+    pub message: Vec<u8>,
+    pub mac: [u8; 16],
+}
+
+///Bank API server. Checks the CBC-MAC on each request with a key it shares
+///with its web clients, then carries out the transfers.
+pub struct Bank {
+    key: [u8; 16],
+    balances: RefCell<HashMap<u64, i64>>,
+}
+
+impl Bank {
+    pub fn new() -> Bank {
+        Bank::controlled(&random_block())
+    }
+
+    pub fn controlled(key: &[u8; 16]) -> Bank {
+        Bank { key: *key, balances: RefCell::new(HashMap::new()) }
+    }
+
+    ///Web client logged in as `account`. Clients only sign transfers from the
+    ///account they are logged in as.
+    pub fn client(&self, account: u64) -> Client<'_> {
+        Client { bank: self, account }
+    }
+
+    ///Net amount transferred into `account` so far
+    pub fn balance(&self, account: u64) -> i64 {
+        *self.balances.borrow().get(&account).unwrap_or(&0)
+    }
+
+    ///Carries out a first protocol request, if its MAC is valid
+    pub fn process(&self, request: &IvRequest)
+            -> Result<Transfer, TransferError> {
+        if !cbc_mac::validate(&request.message, &self.key, &request.iv,
+                              &request.mac) {
+            return Err(TransferError::BadMac);
+        }
+        let transfer = Transfer::from_bytes(&request.message)?;
+        self.apply(transfer.from, &[(transfer.to, transfer.amount)])?;
+        Ok(transfer)
+    }
+
+    ///Carries out a second protocol request, if its MAC is valid
+    pub fn process_list(&self, request: &FixedIvRequest)
+            -> Result<TransferList, TransferError> {
+        if !cbc_mac::validate(&request.message, &self.key, &[0u8; 16],
+                              &request.mac) {
+            return Err(TransferError::BadMac);
+        }
+        let list = TransferList::from_bytes(&request.message)?;
+        self.apply(list.from, &list.transfers)?;
+        Ok(list)
+    }
+
+    ///Carries out every `(to, amount)` transfer from `from`, or none of them
+    ///if any balance would overflow
+    fn apply(&self, from: u64, transfers: &[(u64, u64)])
+            -> Result<(), TransferError> {
+        let mut balances = self.balances.borrow().clone();
+        for &(to, amount) in transfers.iter() {
+            if amount > i64::MAX as u64 {
+                return Err(TransferError::Overflow);
+            }
+            let amount = amount as i64;
+            for &(account, delta) in [(from, -amount), (to, amount)].iter() {
+                let balance = balances.entry(account).or_insert(0);
+                *balance = balance.checked_add(delta)
+                                  .ok_or(TransferError::Overflow)?;
+            }
+        }
+        *self.balances.borrow_mut() = balances;
+        Ok(())
+    }
+}
+
+impl Default for Bank {
+    fn default() -> Bank {
+        Bank::new()
+    }
+}
+
+///Web client for a single account
+pub struct Client<'a> {
+    bank: &'a Bank,
+    account: u64,
+}
+
+impl<'a> Client<'a> {
+    ///Signs a first protocol request to send `amount` to `to`
+    pub fn transfer(&self, to: u64, amount: u64) -> IvRequest {
+        let transfer = Transfer { from: self.account, to, amount };
+        let message = format!("{}", transfer).into_bytes();
+        let iv = random_block();
+        let mac = cbc_mac::cbc_mac(&message, &self.bank.key, &iv);
+        IvRequest { message, iv, mac }
+    }
+
+    ///Signs a second protocol request for `transfers`, each a `(to, amount)`
+    ///pair
+    pub fn transfer_list(&self, transfers: &[(u64, u64)]) -> FixedIvRequest {
+        let list = TransferList { from: self.account,
+                                  transfers: transfers.to_vec() };
+        let message = format!("{}", list).into_bytes();
+        let mac = cbc_mac::cbc_mac(&message, &self.bank.key, &[0u8; 16]);
+        FixedIvRequest { message, mac }
+    }
+}
+
+fn random_block() -> [u8; 16] {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut block = [0u8; 16];
+    for el in block.iter_mut() {
+        *el = rng.gen::<u8>();
+    }
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bank;
+    use transfer::Transfer;
+
+    #[test]
+    fn process() {
+        let bank = Bank::new();
+        let request = bank.client(1).transfer(2, 100);
+        assert_eq!(bank.process(&request).unwrap(),
+                   Transfer { from: 1, to: 2, amount: 100 });
+        assert_eq!(bank.balance(1), -100);
+        assert_eq!(bank.balance(2), 100);
+    }
+
+    #[test]
+    fn process_bad_mac() {
+        let bank = Bank::new();
+        let mut request = bank.client(1).transfer(2, 100);
+        let last = request.message.len() - 1;
+        request.message[last] = b'9';
+        assert!(bank.process(&request).is_err());
+        assert_eq!(bank.balance(2), 0);
+    }
+
+    #[test]
+    fn process_list() {
+        let bank = Bank::new();
+        let request = bank.client(1).transfer_list(&[(2, 100), (3, 50)]);
+        let list = bank.process_list(&request).unwrap();
+        assert_eq!(list.transfers, vec![(2, 100), (3, 50)]);
+        assert_eq!(bank.balance(1), -150);
+    }
+
+    #[test]
+    fn process_huge_amount() {
+        let bank = Bank::new();
+        let request = bank.client(1).transfer(2, 1 << 63);
+        assert!(bank.process(&request).is_err());
+        let request = bank.client(1).transfer_list(&[(2, u64::MAX)]);
+        assert_eq!(bank.process_list(&request).unwrap().transfers, vec![]);
+        assert_eq!(bank.balance(1), 0);
+        assert_eq!(bank.balance(2), 0);
+    }
+
+    #[test]
+    fn process_overflow() {
+        let bank = Bank::new();
+        let max = i64::MAX as u64;
+        let request = bank.client(1).transfer(2, max);
+        assert!(bank.process(&request).is_ok());
+        let request = bank.client(3).transfer_list(&[(4, 1), (2, max)]);
+        assert!(bank.process_list(&request).is_err());
+        assert_eq!(bank.balance(2), i64::MAX);
+        assert_eq!(bank.balance(3), 0);
+        assert_eq!(bank.balance(4), 0);
+    }
+}
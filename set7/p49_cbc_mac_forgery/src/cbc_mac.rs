@@ -0,0 +1,37 @@
+use cbc;
+
+///Gives the CBC-MAC of `message` under `key` and `iv`: the last block of its
+///AES-128 CBC encryption, PKCS7 padding included
+pub fn cbc_mac(message: &[u8], key: &[u8], iv: &[u8]) -> [u8; 16] {
+    let ciphertext = cbc::aes_cbc_encrypt(message, key, iv);
+    let mut mac = [0u8; 16];
+    mac.copy_from_slice(&ciphertext[ciphertext.len() - 16..]);
+    mac
+}
+
+///True if `mac` is the CBC-MAC of `message` under `key` and `iv`, otherwise
+///false
+pub fn validate(message: &[u8], key: &[u8], iv: &[u8], mac: &[u8; 16]) -> bool {
+    cbc_mac(message, key, iv) == *mac
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn cbc_mac() {
+        let key = b"yellow submarine";
+        let iv = b"the 1st 16 bytes";
+        let expected = [224, 191, 66, 17, 60, 77, 69, 210, 210, 160, 37, 129,
+                        90, 239, 119, 37];
+        assert_eq!(super::cbc_mac(b"hello world", key, iv), expected);
+    }
+
+    #[test]
+    fn validate() {
+        let key = b"yellow submarine";
+        let iv = b"the 1st 16 bytes";
+        let mac = super::cbc_mac(b"hello world", key, iv);
+        assert!(super::validate(b"hello world", key, iv, &mac));
+        assert!(!super::validate(b"hello world!", key, iv, &mac));
+    }
+}
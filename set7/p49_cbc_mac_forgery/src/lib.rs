@@ -0,0 +1,146 @@
+extern crate crypto;
+extern crate rand;
+extern crate regex;
+
+mod cbc;
+pub mod cbc_mac;
+pub mod transfer;
+pub mod bank;
+
+use bank::{IvRequest, FixedIvRequest};
+
+///Given `message` with CBC-MAC `mac` under an attacker-controlled `iv`, gives
+///a message whose first block is `first_block` and an IV under which it has
+///the same MAC. Panics unless `first_block` is 16 bytes long and `message` is
+///at least that long.
+pub fn forge_first_block(message: &[u8], iv: &[u8; 16], first_block: &[u8])
+        -> (Vec<u8>, [u8; 16]) {
+    assert!(first_block.len() == 16 && message.len() >= 16,
+            "Can only replace a whole first block");
+    let mut forged_iv = [0u8; 16];
+    for idx in 0..16 {
+        forged_iv[idx] = iv[idx] ^ message[idx] ^ first_block[idx];
+    }
+    let forged = first_block.iter().chain(message[16..].iter()).cloned()
+                            .collect::<Vec<u8>>();
+    (forged, forged_iv)
+}
+
+///Given messages `first` and `second`, both MAC'd under the same key with a
+///zero IV, gives a message whose MAC is the MAC of `second`: `first` with its
+///padding, then `second` with its first block XORed with `first_mac`. That
+///block decrypts as garbage.
+pub fn splice(first: &[u8], first_mac: &[u8; 16], second: &[u8]) -> Vec<u8> {
+    let mut spliced = pkcs_pad(first, 16);
+    for (idx, &byte) in second.iter().enumerate() {
+        spliced.push(if idx < 16 { byte ^ first_mac[idx] } else { byte });
+    }
+    spliced
+}
+
+///Rewrites a first protocol `request` from the attacker's own account so that
+///it draws on `victim` instead. The attacker's and victim's account numbers
+///must be the same length, so that only the first block changes.
+pub fn steal_with_iv(request: &IvRequest, victim: u64)
+        -> Option<IvRequest> {
+    let message = String::from_utf8_lossy(&request.message).into_owned();
+    let from_end = message.find('&').unwrap_or(0);
+    let new_from = format!("from={}", victim);
+    if new_from.len() != from_end || from_end > 16 || message.len() < 16 {
+        return None;
+    }
+    let first_block = new_from.bytes().chain(message.bytes().skip(from_end))
+                              .take(16).collect::<Vec<u8>>();
+    let (forged, iv) = forge_first_block(&request.message, &request.iv,
+                                         &first_block);
+    Some(IvRequest { message: forged, iv, mac: request.mac })
+}
+
+///Appends the attacker's own signed second protocol request `own` to a
+///captured request `captured` from the victim, keeping a valid MAC. Only the
+///part of `own` after its first block survives intact, so it should hold the
+///transfers to smuggle in, starting with a `;`.
+pub fn steal_with_splice(captured: &FixedIvRequest, own: &FixedIvRequest)
+        -> FixedIvRequest {
+    FixedIvRequest {
+        message: splice(&captured.message, &captured.mac, &own.message),
+        mac: own.mac,
+    }
+}
+
+///Pads `message` with PKCS7 padding
+fn pkcs_pad(message: &[u8], blocksize: u8) -> Vec<u8> {
+    let padsize = blocksize-(message.len()%(blocksize as usize)) as u8;
+    let pad = vec![padsize; padsize as usize];
+    message.iter().chain(pad.iter()).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bank::Bank;
+    use transfer::Transfer;
+
+    #[test]
+    fn forge_first_block() {
+        use cbc_mac::{cbc_mac, validate};
+        let key = b"yellow submarine";
+        let iv = [7u8; 16];
+        let message = b"0123456789abcdef and then some more";
+        let mac = cbc_mac(message, key, &iv);
+        let (forged, forged_iv) = super::forge_first_block(message, &iv,
+                                                           b"fedcba9876543210");
+        assert!(forged.starts_with(b"fedcba9876543210 and then"));
+        assert!(validate(&forged, key, &forged_iv, &mac));
+    }
+
+    #[test]
+    fn splice() {
+        use cbc_mac::{cbc_mac, validate};
+        let key = b"yellow submarine";
+        let zero_iv = [0u8; 16];
+        let first = b"in the town where i was born";
+        let second = b"lived a man who sailed to sea";
+        let first_mac = cbc_mac(first, key, &zero_iv);
+        let second_mac = cbc_mac(second, key, &zero_iv);
+        let spliced = super::splice(first, &first_mac, second);
+        assert_eq!(spliced.len(), 32 + second.len());
+        assert!(spliced.ends_with(&second[16..]));
+        assert!(validate(&spliced, key, &zero_iv, &second_mac));
+    }
+
+    #[test]
+    fn steal_with_iv() {
+        let bank = Bank::new();
+        let attacker = 31;
+        let victim = 52;
+        let own = bank.client(attacker).transfer(attacker, 1000000);
+        let forged = super::steal_with_iv(&own, victim).unwrap();
+        assert_eq!(bank.process(&forged).unwrap(),
+                   Transfer { from: victim, to: attacker, amount: 1000000 });
+        assert_eq!(bank.balance(victim), -1000000);
+        assert_eq!(bank.balance(attacker), 1000000);
+    }
+
+    #[test]
+    fn steal_with_iv_mismatched_ids() {
+        let bank = Bank::new();
+        let own = bank.client(3).transfer(3, 1000000);
+        assert!(super::steal_with_iv(&own, 100).is_none());
+    }
+
+    #[test]
+    fn steal_with_splice() {
+        let bank = Bank::new();
+        let attacker = 2;
+        let victim = 7;
+        let captured = bank.client(victim).transfer_list(&[(3, 10), (5, 20)]);
+        //"from=2&tx_list=2" fills the first block, which gets scrambled
+        let own = bank.client(attacker).transfer_list(&[(attacker, 1),
+                                                        (attacker, 1000000)]);
+        let forged = super::steal_with_splice(&captured, &own);
+        let list = bank.process_list(&forged).unwrap();
+        assert_eq!(list.from, victim);
+        assert!(list.transfers.contains(&(attacker, 1000000)));
+        assert_eq!(bank.balance(attacker), 1000000);
+    }
+}
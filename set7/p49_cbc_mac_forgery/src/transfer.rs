@@ -0,0 +1,190 @@
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::str::FromStr;
+use std::error::Error;
+use regex::Regex;
+
+///A single transfer, serialized as `from=#&to=#&amount=#`
+#[derive(Debug, PartialEq)]
+pub struct Transfer {
+    pub from: u64,
+    pub to: u64,
+    pub amount: u64,
+}
+
+impl Transfer {
+    ///Parses a transfer from raw message bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Transfer, TransferError> {
+        match String::from_utf8(bytes.to_vec()) {
+            Ok(s) => Transfer::from_str(&s[..]),
+            Err(e) => Err(TransferError::BadParse(format!("{}", e))),
+        }
+    }
+}
+
+impl Display for Transfer {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let repr = format!("from={}&to={}&amount={}",
+                           self.from, self.to, self.amount);
+        formatter.write_str(&repr[..])
+    }
+}
+
+impl FromStr for Transfer {
+    type Err = TransferError;
+    fn from_str(s: &str) -> Result<Self, TransferError> {
+        let re = Regex::new(r"^from=(\d+)&to=(\d+)&amount=(\d+)$").unwrap();
+        if let Some(caps) = re.captures(s) {
+            Ok(Transfer {
+                from: parse_number(caps.at(1).unwrap())?,
+                to: parse_number(caps.at(2).unwrap())?,
+                amount: parse_amount(caps.at(3).unwrap())?,
+            })
+        }
+        else {
+            Err(TransferError::BadParse(format!("Could not parse {}", s)))
+        }
+    }
+}
+
+///A batch of transfers from one account, serialized as
+///`from=#&tx_list=#:#;#:#;...`, where each list entry is `to:amount`
+#[derive(Debug, PartialEq)]
+pub struct TransferList {
+    pub from: u64,
+    pub transfers: Vec<(u64, u64)>,
+}
+
+impl TransferList {
+    ///Parses a transfer list from raw message bytes. Invalid UTF-8 is
+    ///replaced rather than rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Result<TransferList, TransferError> {
+        TransferList::from_str(&String::from_utf8_lossy(bytes))
+    }
+}
+
+impl Display for TransferList {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let list = self.transfers.iter()
+                       .map(|&(to, amount)| format!("{}:{}", to, amount))
+                       .collect::<Vec<String>>();
+        let repr = format!("from={}&tx_list={}", self.from, list.join(";"));
+        formatter.write_str(&repr[..])
+    }
+}
+
+impl FromStr for TransferList {
+    type Err = TransferError;
+    ///Entries of the list which can't be parsed are skipped, as a forgiving
+    ///server would
+    fn from_str(s: &str) -> Result<Self, TransferError> {
+        let re = Regex::new(r"(?s)^from=(\d+)&tx_list=(.*)$").unwrap();
+        let entry_re = Regex::new(r"^(\d+):(\d+)$").unwrap();
+        if let Some(caps) = re.captures(s) {
+            let from = parse_number(caps.at(1).unwrap())?;
+            let transfers = caps.at(2).unwrap().split(';').filter_map(|entry| {
+                entry_re.captures(entry).and_then(|entry_caps| {
+                    match (parse_number(entry_caps.at(1).unwrap()),
+                           parse_amount(entry_caps.at(2).unwrap())) {
+                        (Ok(to), Ok(amount)) => Some((to, amount)),
+                        _ => None,
+                    }
+                })
+            }).collect();
+            Ok(TransferList { from, transfers })
+        }
+        else {
+            Err(TransferError::BadParse(format!("Could not parse {}", s)))
+        }
+    }
+}
+
+fn parse_number(s: &str) -> Result<u64, TransferError> {
+    s.parse::<u64>().map_err(|e| TransferError::BadParse(format!("{}", e)))
+}
+
+///Amounts are kept as signed balances, so they must fit in an `i64`
+fn parse_amount(s: &str) -> Result<u64, TransferError> {
+    let amount = parse_number(s)?;
+    if amount > i64::MAX as u64 {
+        return Err(TransferError::BadParse(
+            format!("Amount {} is too large", amount)));
+    }
+    Ok(amount)
+}
+
+#[derive(Debug)]
+pub enum TransferError {
+    BadParse(String),
+    BadMac,
+    Overflow,
+}
+
+impl Display for TransferError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let err = match *self {
+            TransferError::BadParse(ref s) =>
+                format!("Bad Transfer Parse: {}", s),
+            TransferError::BadMac => "Bad Transfer MAC".to_string(),
+            TransferError::Overflow =>
+                "Transfer would overflow a balance".to_string(),
+        };
+        formatter.write_str(&err[..])
+    }
+}
+
+impl Error for TransferError {
+    fn description(&self) -> &str {
+        match *self {
+            TransferError::BadParse(_) => "Bad Transfer Parse",
+            TransferError::BadMac => "Bad Transfer MAC",
+            TransferError::Overflow => "Transfer Balance Overflow",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::{Transfer, TransferList};
+
+    #[test]
+    fn transfer_round_trip() {
+        let transfer = Transfer { from: 1, to: 22, amount: 333 };
+        let repr = format!("{}", transfer);
+        assert_eq!(repr, "from=1&to=22&amount=333");
+        assert_eq!(Transfer::from_str(&repr).unwrap(), transfer);
+    }
+
+    #[test]
+    fn transfer_bad_parse() {
+        assert!(Transfer::from_str("from=1&to=22").is_err());
+        assert!(Transfer::from_str("from=1&to=22&amount=333&x=4").is_err());
+        assert!(Transfer::from_str("from=1&to=2&amount=99999999999999999999")
+                .is_err());
+        assert!(Transfer::from_bytes(b"from=1&to=2&amount=3\xff").is_err());
+        assert!(Transfer::from_str("from=1&to=2&amount=9223372036854775808")
+                .is_err());
+        assert!(Transfer::from_str("from=1&to=2&amount=9223372036854775807")
+                .is_ok());
+    }
+
+    #[test]
+    fn transfer_list_round_trip() {
+        let list = TransferList { from: 4, transfers: vec![(5, 6), (7, 8)] };
+        let repr = format!("{}", list);
+        assert_eq!(repr, "from=4&tx_list=5:6;7:8");
+        assert_eq!(TransferList::from_str(&repr).unwrap(), list);
+    }
+
+    #[test]
+    fn transfer_list_skips_bad_entries() {
+        let list = TransferList::from_bytes(b"from=4&tx_list=5:6;\xff\n7;8:9")
+                   .unwrap();
+        assert_eq!(list, TransferList { from: 4,
+                                        transfers: vec![(5, 6), (8, 9)] });
+        let list = TransferList::from_str(
+            "from=4&tx_list=5:9223372036854775808;7:8").unwrap();
+        assert_eq!(list, TransferList { from: 4, transfers: vec![(7, 8)] });
+    }
+}
@@ -0,0 +1,110 @@
+//!CBC-MAC with a public key is not a hash function: anyone who knows the key
+//!can make any content match any MAC value.
+
+extern crate crypto;
+
+mod aes;
+mod cbc;
+pub mod cbc_mac;
+
+///Encrypts a single 16-byte block under `key`, without padding
+pub fn encrypt_block(block: &[u8], key: &[u8]) -> [u8; 16] {
+    let mut encrypted = [0u8; 16];
+    encrypted.copy_from_slice(&aes::aes_ecb_encrypt(block, key)[..16]);
+    encrypted
+}
+
+///Decrypts a single 16-byte block under `key`, without padding
+pub fn decrypt_block(block: &[u8], key: &[u8]) -> [u8; 16] {
+    //aes_ecb_decrypt insists on valid padding, so follow `block` with an
+    //encrypted block of pure padding
+    let pad_block = aes::aes_ecb_encrypt(&[0u8; 16], key);
+    let ciphertext = block.iter().chain(pad_block[16..].iter()).cloned()
+                          .collect::<Vec<u8>>();
+    let decrypted = aes::aes_ecb_decrypt(&ciphertext, key)
+                        .expect("Padding block did not decrypt");
+    let mut result = [0u8; 16];
+    result.copy_from_slice(&decrypted[..16]);
+    result
+}
+
+///Gives a message starting with `content` whose CBC-MAC under `key` and `iv`
+///is `target`. `content` is extended to a block boundary with `filler`, then
+///followed by a single block of chosen bytes. The message is a whole number
+///of blocks, so its PKCS7 padding is a full block.
+pub fn forge(content: &[u8], filler: u8, target: &[u8; 16], key: &[u8],
+             iv: &[u8; 16]) -> Vec<u8> {
+    let mut message = content.to_vec();
+    while !message.len().is_multiple_of(16) {
+        message.push(filler);
+    }
+    //CBC state after `message`, ignoring padding
+    let ciphertext = cbc::aes_cbc_encrypt(&message, key, iv);
+    let state = if message.is_empty() {
+        &iv[..]
+    }
+    else {
+        &ciphertext[message.len() - 16..message.len()]
+    };
+    //target = E(E(state ^ chosen) ^ pad)
+    let mut before_pad = decrypt_block(target, key);
+    for byte in before_pad.iter_mut() {
+        *byte ^= 16;
+    }
+    let chosen = decrypt_block(&before_pad, key);
+    message.extend(chosen.iter().zip(state.iter()).map(|(a, b)| a ^ b));
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use cbc_mac::cbc_mac;
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+
+    #[test]
+    fn block_round_trip() {
+        let block = b"in the town wher";
+        let encrypted = super::encrypt_block(block, KEY);
+        assert_eq!(&super::decrypt_block(&encrypted, KEY), block);
+    }
+
+    #[test]
+    fn cbc_mac_hash() {
+        let expected = [0x29, 0x6b, 0x8d, 0x7c, 0xb7, 0x8a, 0x24, 0x3d, 0xda,
+                        0x4d, 0x0a, 0x61, 0xd3, 0x3b, 0xbd, 0xd1];
+        assert_eq!(cbc_mac(b"alert('MZA who was that?');\n", KEY, &[0u8; 16]),
+                   expected);
+    }
+
+    #[test]
+    fn forge() {
+        let iv = [0u8; 16];
+        let target = cbc_mac(b"alert('MZA who was that?');\n", KEY, &iv);
+        let content = b"alert('Ayo, the Wu is back!');//";
+        let forged = super::forge(content, b' ', &target, KEY, &iv);
+        assert!(forged.starts_with(content));
+        assert_eq!(forged.len(), 48);
+        assert_eq!(cbc_mac(&forged, KEY, &iv), target);
+    }
+
+    #[test]
+    fn forge_unaligned() {
+        let iv = *b"the 1st 16 bytes";
+        let target = cbc_mac(b"console.log('hello');", KEY, &iv);
+        let content = b"document.location = 'http://example.com'; /*";
+        let forged = super::forge(content, b'*', &target, KEY, &iv);
+        assert!(forged.starts_with(content));
+        assert_eq!(forged.len() % 16, 0);
+        assert_eq!(cbc_mac(&forged, KEY, &iv), target);
+    }
+
+    #[test]
+    fn forge_empty() {
+        let iv = [0u8; 16];
+        let target = cbc_mac(b"alert(1)", KEY, &iv);
+        let forged = super::forge(b"", b' ', &target, KEY, &iv);
+        assert_eq!(forged.len(), 16);
+        assert_eq!(cbc_mac(&forged, KEY, &iv), target);
+    }
+}
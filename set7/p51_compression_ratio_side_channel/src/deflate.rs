@@ -0,0 +1,173 @@
+//!Raw DEFLATE (RFC 1951) compression: LZ77 over a 32 KiB window, coded as a
+//!single block with the fixed Huffman codes. Nothing fancy, but the output is
+//!valid for any inflater, and it leaks through its length like the real thing.
+
+use std::collections::HashMap;
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 128;
+
+const LENGTH_BASES: [usize; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19,
+                                   23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+                                   131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+                                 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASES: [usize; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65,
+                                     97, 129, 193, 257, 385, 513, 769, 1025,
+                                     1537, 2049, 3073, 4097, 6145, 8193, 12289,
+                                     16385, 24577];
+const DISTANCE_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6,
+                                   6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
+                                   13, 13];
+
+///Compresses `data` into a raw DEFLATE stream, with no zlib or gzip wrapper
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.write_bits(1, 1); //BFINAL
+    writer.write_bits(1, 2); //BTYPE = fixed Huffman codes
+    let mut chains: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let (length, distance) = longest_match(data, pos, &chains);
+        let step = if length >= MIN_MATCH {
+            write_match(&mut writer, length, distance);
+            length
+        }
+        else {
+            write_literal(&mut writer, data[pos] as usize);
+            1
+        };
+        for idx in pos..pos + step {
+            if idx + MIN_MATCH <= data.len() {
+                chains.entry(&data[idx..idx + MIN_MATCH]).or_default()
+                      .push(idx);
+            }
+        }
+        pos += step;
+    }
+    write_literal(&mut writer, 256); //end of block
+    writer.finish()
+}
+
+///Gives the `(length, distance)` of the longest earlier match for the data at
+///`pos`, preferring the closest on ties. The length is 0 if there is none.
+fn longest_match(data: &[u8], pos: usize, chains: &HashMap<&[u8], Vec<usize>>)
+        -> (usize, usize) {
+    let mut best = (0, 0);
+    if pos + MIN_MATCH > data.len() {
+        return best;
+    }
+    let candidates = match chains.get(&data[pos..pos + MIN_MATCH]) {
+        Some(candidates) => candidates,
+        None => return best,
+    };
+    let max_length = (data.len() - pos).min(MAX_MATCH);
+    for &start in candidates.iter().rev().take(MAX_CHAIN) {
+        if pos - start > WINDOW_SIZE {
+            break;
+        }
+        let length = (0..max_length).take_while(|&idx| {
+            data[start + idx] == data[pos + idx]
+        }).count();
+        if length > best.0 {
+            best = (length, pos - start);
+            if length == max_length {
+                break;
+            }
+        }
+    }
+    best
+}
+
+///Writes the fixed Huffman code for a literal/length symbol
+fn write_literal(writer: &mut BitWriter, symbol: usize) {
+    let (code, bits) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    };
+    writer.write_code(code as u32, bits);
+}
+
+///Writes a back-reference of `length` bytes, `distance` bytes back
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let idx = LENGTH_BASES.iter().rposition(|&base| base <= length).unwrap();
+    write_literal(writer, 257 + idx);
+    writer.write_bits((length - LENGTH_BASES[idx]) as u32, LENGTH_EXTRA[idx]);
+    let idx = DISTANCE_BASES.iter().rposition(|&base| base <= distance)
+                            .unwrap();
+    writer.write_code(idx as u32, 5);
+    writer.write_bits((distance - DISTANCE_BASES[idx]) as u32,
+                      DISTANCE_EXTRA[idx]);
+}
+
+///Packs bits into bytes, least significant bit first
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u32,
+    filled: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter { bytes: Vec::new(), current: 0, filled: 0 }
+    }
+
+    ///Writes the low `count` bits of `value`, least significant first
+    fn write_bits(&mut self, value: u32, count: u32) {
+        for bit in 0..count {
+            self.current |= ((value >> bit) & 1) << self.filled;
+            self.filled += 1;
+            if self.filled == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.filled = 0;
+            }
+        }
+    }
+
+    ///Writes a `count`-bit Huffman code, most significant bit first
+    fn write_code(&mut self, code: u32, count: u32) {
+        for bit in (0..count).rev() {
+            self.write_bits(code >> bit, 1);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.current as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compress;
+
+    #[test]
+    fn empty() {
+        assert_eq!(compress(b""), vec![0x03, 0x00]);
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(compress(b"abc"), vec![0x4b, 0x4c, 0x4a, 0x06, 0x00]);
+    }
+
+    #[test]
+    fn matches() {
+        let expected = vec![0xcb, 0x4e, 0xcd, 0x2c, 0xc9, 0x50, 0x48, 0xcc,
+                            0x4b, 0x51, 0x40, 0x63, 0x01, 0x00];
+        assert_eq!(compress(b"keith and keith and keith"), expected);
+    }
+
+    #[test]
+    fn shrinks_repetition() {
+        let text = b"to be or not to be, that is the question. ".repeat(20);
+        assert!(compress(&text).len() < text.len() / 5);
+    }
+}
@@ -0,0 +1,147 @@
+//!CRIME-style attack: recovers a secret which is compressed alongside
+//!attacker-controlled data, using only the length of the encrypted result.
+
+extern crate crypto;
+extern crate rand;
+
+mod aes;
+mod cbc;
+mod ctr;
+pub mod deflate;
+pub mod oracle;
+
+///Bytes which cost 8 bits as DEFLATE literals, and which shouldn't show up in
+///a request
+const SHORT_FILLER: &[u8] = b"\x01\x02\x03\x04\x05\x06\x07\x08\x0b\x0c\x0e\
+                              \x0f\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\
+                              \x1a\x1b\x1c\x1d\x1e\x1f";
+///Number of bits of filler to send when measuring with no shift. Any number of
+///bits from this on can be made from 8- and 9-bit literals.
+const FILLER_OFFSET: usize = 63;
+///Most guesses kept at once when several compress equally well
+const BEAM_WIDTH: usize = 8;
+
+///Recovers the secret following `known` in a request, a byte at a time, where
+///`oracle` gives the encrypted length of a request with the given body. Each
+///byte is guessed from `alphabet`, which must be printable ASCII. Stops when no
+///guess compresses better than the others, or after `max_len` bytes.
+pub fn recover_secret<F>(oracle: &F, known: &[u8], alphabet: &[u8],
+                         max_len: usize) -> Vec<u8>
+        where F: Fn(&[u8]) -> usize {
+    let granularity = granularity(oracle);
+    let mut beam = vec![known.to_vec()];
+    for _ in 0..max_len {
+        let mut scored = Vec::new();
+        for prefix in beam.iter() {
+            for &byte in alphabet.iter() {
+                let mut guess = prefix.clone();
+                guess.push(byte);
+                let score = measure(oracle, &guess, granularity);
+                scored.push((score, guess));
+            }
+        }
+        let best = scored.iter().map(|&(score, _)| score).min().unwrap();
+        let total = scored.len();
+        let kept = scored.into_iter().filter(|&(score, _)| score == best)
+                         .map(|(_, guess)| guess).collect::<Vec<Vec<u8>>>();
+        if kept.len() == total {
+            break;
+        }
+        beam = kept;
+        beam.truncate(BEAM_WIDTH);
+    }
+    beam.swap_remove(0).split_off(known.len())
+}
+
+///Gives the number of bytes by which the oracle's output length changes, by
+///growing the body one 8-bit literal at a time
+fn granularity<F>(oracle: &F) -> usize where F: Fn(&[u8]) -> usize {
+    let base = oracle(b"");
+    (1..SHORT_FILLER.len()).map(|count| oracle(&SHORT_FILLER[..count]))
+                           .fold(0, |acc, length| {
+                               gcd(acc, length.abs_diff(base))
+                           })
+}
+
+///Gives a score for `body` which orders bodies by their compressed length in
+///bits, up to a constant. Finds how much filler it takes to tip the output
+///length over to the next step, which is less the longer the compressed body.
+fn measure<F>(oracle: &F, body: &[u8], granularity: usize) -> usize
+        where F: Fn(&[u8]) -> usize {
+    let with_shift = |shift: usize| {
+        let mut padded = filler(FILLER_OFFSET + shift);
+        padded.extend_from_slice(body);
+        oracle(&padded)
+    };
+    let base = with_shift(0);
+    //smallest shift in (low, high] which grows the output
+    let mut low = 0;
+    let mut high = 8 * granularity;
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        if with_shift(mid) > base {
+            high = mid;
+        }
+        else {
+            low = mid;
+        }
+    }
+    8 * base + 8 * granularity - high
+}
+
+///Gives distinct bytes which compress to exactly `bits` bits as literals.
+///`bits` must be at least 56.
+fn filler(bits: usize) -> Vec<u8> {
+    let long = bits % 8;
+    let short = (bits - 9 * long) / 8;
+    SHORT_FILLER[..short].iter().cloned()
+                         .chain((0x90..0x90 + long as u8).rev()).collect()
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use oracle::{Mode, Oracle};
+
+    const BASE_64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz\
+                             0123456789+/=";
+
+    #[test]
+    fn filler() {
+        for bits in 56..200 {
+            let filler = super::filler(bits);
+            let literal_bits = filler.iter().map(|&byte| {
+                if byte < 144 { 8 } else { 9 }
+            }).sum::<usize>();
+            assert_eq!(literal_bits, bits);
+        }
+    }
+
+    #[test]
+    fn granularity() {
+        let ctr = Oracle::new(Mode::Ctr);
+        assert_eq!(super::granularity(&|body: &[u8]| ctr.length(body)), 1);
+        let cbc = Oracle::new(Mode::Cbc);
+        assert_eq!(super::granularity(&|body: &[u8]| cbc.length(body)), 16);
+    }
+
+    #[test]
+    fn recover_ctr() {
+        let oracle = Oracle::new(Mode::Ctr);
+        let secret = super::recover_secret(&|body: &[u8]| oracle.length(body),
+                                           b"sessionid=", BASE_64, 64);
+        assert_eq!(&secret[..],
+                   &b"TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE="[..]);
+    }
+
+    #[test]
+    fn recover_cbc() {
+        let oracle = Oracle::controlled(b"c2VjcmV0IGNvb2tpZQ==", Mode::Cbc);
+        let secret = super::recover_secret(&|body: &[u8]| oracle.length(body),
+                                           b"sessionid=", BASE_64, 64);
+        assert_eq!(&secret[..], &b"c2VjcmV0IGNvb2tpZQ=="[..]);
+    }
+}
@@ -0,0 +1,101 @@
+use rand;
+use rand::Rng;
+
+use cbc;
+use ctr;
+use deflate;
+
+///Session id of the victim, as given in the challenge
+const SESSION_ID: &[u8] = b"TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=";
+
+///How the oracle encrypts its compressed requests
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Ctr,
+    Cbc,
+}
+
+///Formats attacker-supplied bodies into requests carrying the victim's session
+///cookie, then compresses and encrypts them under a fresh key each time. Only
+///the length of the result is of any use to the attacker.
+pub struct Oracle {
+    session_id: Vec<u8>,
+    mode: Mode,
+}
+
+impl Oracle {
+    pub fn new(mode: Mode) -> Oracle {
+        Oracle::controlled(SESSION_ID, mode)
+    }
+
+    pub fn controlled(session_id: &[u8], mode: Mode) -> Oracle {
+        Oracle { session_id: session_id.to_vec(), mode }
+    }
+
+    ///Gives the plaintext request for `body`
+    pub fn format_request(&self, body: &[u8]) -> Vec<u8> {
+        let mut request = b"POST / HTTP/1.1\nHost: hapless.com\n\
+                                Cookie: sessionid=".to_vec();
+        request.extend_from_slice(&self.session_id);
+        let length = format!("\nContent-Length: {}\n", body.len());
+        request.extend_from_slice(length.as_bytes());
+        request.extend_from_slice(body);
+        request
+    }
+
+    ///Compresses and encrypts the request for `body`. In CBC mode the random IV
+    ///is prepended.
+    pub fn encrypt(&self, body: &[u8]) -> Vec<u8> {
+        let compressed = deflate::compress(&self.format_request(body));
+        let mut rng = rand::thread_rng();
+        let key = random_block();
+        match self.mode {
+            Mode::Ctr => {
+                let mut encryptor = ctr::AesCtr::new(rng.gen::<u64>(), &key);
+                encryptor.encrypt(&compressed).unwrap()
+            }
+            Mode::Cbc => {
+                let iv = random_block();
+                let mut encrypted = iv.to_vec();
+                encrypted.extend(cbc::aes_cbc_encrypt(&compressed, &key, &iv));
+                encrypted
+            }
+        }
+    }
+
+    ///Gives the length of `encrypt(body)`, which is all that leaks
+    pub fn length(&self, body: &[u8]) -> usize {
+        self.encrypt(body).len()
+    }
+}
+
+fn random_block() -> [u8; 16] {
+    let mut rng = rand::thread_rng();
+    let mut block = [0u8; 16];
+    for el in block.iter_mut() {
+        *el = rng.gen::<u8>();
+    }
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mode, Oracle};
+
+    #[test]
+    fn format_request() {
+        let oracle = Oracle::controlled(b"abc", Mode::Ctr);
+        let expected = b"POST / HTTP/1.1\nHost: hapless.com\n\
+                         Cookie: sessionid=abc\nContent-Length: 5\nhello";
+        assert_eq!(&oracle.format_request(b"hello")[..], &expected[..]);
+    }
+
+    #[test]
+    fn length() {
+        let ctr = Oracle::new(Mode::Ctr);
+        let cbc = Oracle::new(Mode::Cbc);
+        assert_eq!(ctr.length(b"sessionid=Tm"), ctr.length(b"sessionid=Tm"));
+        assert!(ctr.length(b"sessionid=Tm") < ctr.length(b"sessionid=!?"));
+        assert_eq!(cbc.length(b"") % 16, 0);
+    }
+}
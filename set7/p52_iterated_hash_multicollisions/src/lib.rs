@@ -0,0 +1,161 @@
+extern crate crypto;
+extern crate rand;
+
+mod aes;
+pub mod md_hash;
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use md_hash::{MdHash, BLOCK_SIZE};
+
+///A single message block
+pub type Block = [u8; BLOCK_SIZE];
+
+///Finds two distinct blocks which give the same state when digested by `hash`
+///in state `state`. Returns the two blocks and the state they lead to.
+pub fn find_collision<R: Rng>(hash: &MdHash, state: u32, rng: &mut R)
+        -> (Block, Block, u32) {
+    let mut seen = HashMap::new();
+    loop {
+        let mut block = [0u8; BLOCK_SIZE];
+        rng.fill_bytes(&mut block);
+        let next = hash.compress(state, &block);
+        match seen.insert(next, block) {
+            Some(other) if other != block => return (other, block, next),
+            _ => {}
+        }
+    }
+}
+
+///Joux multicollision: a chain of colliding block pairs. Picking either block
+///from each pair gives a message, and all such messages collide.
+pub struct Multicollision {
+    ///Colliding block pairs, in message order
+    pub pairs: Vec<(Block, Block)>,
+    ///State reached by every message (before padding)
+    pub state: u32,
+}
+
+impl Multicollision {
+    ///Creates a 2^`n`-way multicollision for `hash`
+    pub fn new<R: Rng>(hash: &MdHash, n: usize, rng: &mut R) -> Multicollision {
+        let mut multi = Multicollision { pairs: Vec::new(), state: hash.initial() };
+        for _ in 0..n {
+            multi.extend(hash, rng);
+        }
+        multi
+    }
+
+    ///Doubles the number of colliding messages by adding another block pair
+    pub fn extend<R: Rng>(&mut self, hash: &MdHash, rng: &mut R) {
+        let (a, b, next) = find_collision(hash, self.state, rng);
+        self.pairs.push((a, b));
+        self.state = next;
+    }
+
+    ///Number of colliding messages
+    pub fn count(&self) -> u64 {
+        1 << self.pairs.len()
+    }
+
+    ///Gives colliding message number `idx`. Bit `k` of `idx` chooses which
+    ///block of pair `k` is used.
+    pub fn message(&self, idx: u64) -> Vec<u8> {
+        self.pairs.iter().enumerate().flat_map(|(k, (a, b))| {
+            if idx >> k & 1 == 0 { a.iter() } else { b.iter() }
+        }).cloned().collect()
+    }
+
+    ///Gives all colliding messages
+    pub fn messages(&self) -> Vec<Vec<u8>> {
+        (0..self.count()).map(|idx| self.message(idx)).collect()
+    }
+}
+
+///Finds two distinct messages which collide under both `f` and `g`, and so
+///under the cascade hash f(x) || g(x). Works by generating a multicollision in
+///`f` large enough that a birthday collision in `g` is likely among its
+///messages, and growing it until one is found. `f` should be the cheaper hash.
+pub fn cascade_collision<R: Rng>(f: &MdHash, g: &MdHash, rng: &mut R)
+        -> (Vec<u8>, Vec<u8>) {
+    let mut multi = Multicollision::new(f, (g.bits() as usize).div_ceil(2), rng);
+    loop {
+        if let Some((a, b)) = find_message_collision(&multi, g) {
+            return (multi.message(a), multi.message(b));
+        }
+        multi.extend(f, rng);
+    }
+}
+
+///Searches the messages of `multi` for a pair which collides under `hash`,
+///returning their indices
+fn find_message_collision(multi: &Multicollision, hash: &MdHash)
+        -> Option<(u64, u64)> {
+    //walk the tree of messages, one block pair at a time
+    let mut states = vec![hash.initial()];
+    for (k, (a, b)) in multi.pairs.iter().enumerate() {
+        let mut next = vec![0; states.len() * 2];
+        for (idx, &state) in states.iter().enumerate() {
+            next[idx] = hash.compress(state, a);
+            next[idx | 1 << k] = hash.compress(state, b);
+        }
+        states = next;
+    }
+    let pad = md_hash::pad(multi.pairs.len() * BLOCK_SIZE);
+    let mut seen = HashMap::new();
+    for (idx, &state) in states.iter().enumerate() {
+        let hashed = hash.digest_from(state, &pad);
+        if let Some(other) = seen.insert(hashed, idx as u64) {
+            return Some((other, idx as u64));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use rand;
+    use md_hash::MdHash;
+
+    #[test]
+    fn find_collision() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0xbeef);
+        let (a, b, state) = super::find_collision(&hash, 0xbeef, &mut rng);
+        assert!(a != b);
+        assert_eq!(hash.compress(0xbeef, &a), state);
+        assert_eq!(hash.compress(0xbeef, &b), state);
+    }
+
+    #[test]
+    fn multicollision() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0xbeef);
+        let multi = super::Multicollision::new(&hash, 4, &mut rng);
+        let messages = multi.messages();
+        assert_eq!(messages.len(), 16);
+        let expected = hash.hash(&messages[0]);
+        for (idx, message) in messages.iter().enumerate() {
+            assert_eq!(hash.hash(message), expected);
+            assert!(messages[..idx].iter().all(|other| other != message),
+                    "Duplicate message {}", idx);
+        }
+    }
+
+    #[test]
+    fn cascade_collision() {
+        let mut rng = rand::thread_rng();
+        let f = MdHash::new(16, 0xbeef);
+        let g = MdHash::new(24, 0xc0ffee);
+        let (a, b) = super::cascade_collision(&f, &g, &mut rng);
+        //each search of 2^t messages costs about 3 * 2^t calls to g, and a
+        //collision is all but certain once t is n_g/2 + 2
+        assert!(g.compressions() < 1 << (g.bits() / 2 + 5), "{} calls to g",
+                g.compressions());
+        assert!(a != b);
+        assert_eq!(f.hash(&a), f.hash(&b));
+        assert_eq!(g.hash(&a), g.hash(&b));
+    }
+}
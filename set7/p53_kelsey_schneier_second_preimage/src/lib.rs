@@ -0,0 +1,199 @@
+extern crate crypto;
+extern crate rand;
+
+mod aes;
+pub mod md_hash;
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use md_hash::{MdHash, BLOCK_SIZE};
+
+///A single message block
+pub type Block = [u8; BLOCK_SIZE];
+
+///Block used to fill the long side of each expandable message piece
+const FILLER: Block = [0u8; BLOCK_SIZE];
+
+///Kelsey-Schneier expandable message: a set of messages of every length from
+///`min_blocks()` to `max_blocks()` blocks, all of which reach the same state.
+pub struct ExpandableMessage {
+    ///For each piece, the single block and the final block of the long side.
+    ///The long side of piece `i` is 2^(k-1-i) filler blocks followed by its
+    ///final block.
+    pieces: Vec<(Block, Block)>,
+    ///State reached by every message
+    pub state: u32,
+}
+
+impl ExpandableMessage {
+    ///Creates an expandable message of `k` pieces for `hash`, covering
+    ///lengths `k` to `k + 2^k - 1` blocks
+    pub fn new<R: Rng>(hash: &MdHash, k: usize, rng: &mut R)
+            -> ExpandableMessage {
+        let mut pieces = Vec::with_capacity(k);
+        let mut state = hash.initial();
+        for idx in 0..k {
+            let filler_len = 1 << (k - 1 - idx);
+            let filler = FILLER.iter().cloned().cycle()
+                               .take(filler_len * BLOCK_SIZE)
+                               .collect::<Vec<u8>>();
+            let long_state = hash.digest_from(state, &filler);
+            let (short, long, next) =
+                find_collision_between(hash, state, long_state, rng);
+            pieces.push((short, long));
+            state = next;
+        }
+        ExpandableMessage { pieces, state }
+    }
+
+    ///Shortest message length, in blocks
+    pub fn min_blocks(&self) -> usize {
+        self.pieces.len()
+    }
+
+    ///Longest message length, in blocks
+    pub fn max_blocks(&self) -> usize {
+        self.pieces.len() + (1 << self.pieces.len()) - 1
+    }
+
+    ///Gives the message which is `blocks` blocks long. Panics if `blocks` is
+    ///out of range.
+    pub fn message(&self, blocks: usize) -> Vec<u8> {
+        assert!(blocks >= self.min_blocks() && blocks <= self.max_blocks(),
+                "No expandable message of {} blocks", blocks);
+        let k = self.pieces.len();
+        let extra = blocks - k;
+        let mut message = Vec::with_capacity(blocks * BLOCK_SIZE);
+        for (idx, &(short, long)) in self.pieces.iter().enumerate() {
+            let filler_len = 1 << (k - 1 - idx);
+            if extra & filler_len == 0 {
+                message.extend(short.iter().cloned());
+            }
+            else {
+                for _ in 0..filler_len {
+                    message.extend(FILLER.iter().cloned());
+                }
+                message.extend(long.iter().cloned());
+            }
+        }
+        message
+    }
+}
+
+///Finds blocks `a` and `b` such that digesting `a` in state `state_a` gives
+///the same state as digesting `b` in state `state_b`. Returns the two blocks and
+///that state.
+pub fn find_collision_between<R: Rng>(hash: &MdHash, state_a: u32,
+                                      state_b: u32, rng: &mut R)
+        -> (Block, Block, u32) {
+    let mut seen_a = HashMap::new();
+    let mut seen_b = HashMap::new();
+    loop {
+        let mut block = [0u8; BLOCK_SIZE];
+        rng.fill_bytes(&mut block);
+        let next_a = hash.compress(state_a, &block);
+        if let Some(&other) = seen_b.get(&next_a) {
+            return (block, other, next_a);
+        }
+        seen_a.insert(next_a, block);
+        rng.fill_bytes(&mut block);
+        let next_b = hash.compress(state_b, &block);
+        if let Some(&other) = seen_a.get(&next_b) {
+            return (other, block, next_b);
+        }
+        seen_b.insert(next_b, block);
+    }
+}
+
+///Finds a second preimage for `message` under `hash`: a different message of
+///the same length with the same hash. `message` must be at least two blocks
+///long. The work needed falls as `message` gets longer.
+pub fn second_preimage<R: Rng>(hash: &MdHash, message: &[u8], rng: &mut R)
+        -> Vec<u8> {
+    let nblocks = message.len() / BLOCK_SIZE;
+    assert!(nblocks >= 2, "Message must be at least two blocks long");
+    //smallest k with k + 2^k >= nblocks, so that every bridge point fits
+    let mut k = 1;
+    while k + (1 << k) < nblocks {
+        k += 1;
+    }
+    let expandable = ExpandableMessage::new(hash, k, rng);
+
+    //states reachable by replacing the first `idx` blocks of `message`
+    let mut targets = HashMap::new();
+    let mut state = hash.initial();
+    for (idx, chunk) in message.chunks(BLOCK_SIZE).take(nblocks).enumerate() {
+        let mut block = [0u8; BLOCK_SIZE];
+        block.copy_from_slice(chunk);
+        state = hash.compress(state, &block);
+        let prefix_blocks = idx + 1;
+        if prefix_blocks > expandable.min_blocks() &&
+           prefix_blocks <= expandable.max_blocks() + 1 {
+            targets.entry(state).or_insert(prefix_blocks);
+        }
+    }
+
+    //find a bridge block from the expandable message into `message`
+    loop {
+        let mut bridge = [0u8; BLOCK_SIZE];
+        rng.fill_bytes(&mut bridge);
+        let next = hash.compress(expandable.state, &bridge);
+        if let Some(&prefix_blocks) = targets.get(&next) {
+            let mut forged = expandable.message(prefix_blocks - 1);
+            forged.extend(bridge.iter().cloned());
+            forged.extend(message[prefix_blocks * BLOCK_SIZE..].iter().cloned());
+            if forged[..] != message[..] {
+                return forged;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand;
+    use rand::Rng;
+    use md_hash::{MdHash, BLOCK_SIZE};
+
+    #[test]
+    fn find_collision_between() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0);
+        let (a, b, state) = super::find_collision_between(&hash, 0x1337, 0xbeef,
+                                                          &mut rng);
+        assert_eq!(hash.compress(0x1337, &a), state);
+        assert_eq!(hash.compress(0xbeef, &b), state);
+    }
+
+    #[test]
+    fn expandable_message() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0xbeef);
+        let expandable = super::ExpandableMessage::new(&hash, 4, &mut rng);
+        assert_eq!(expandable.min_blocks(), 4);
+        assert_eq!(expandable.max_blocks(), 19);
+        for blocks in 4..20 {
+            let message = expandable.message(blocks);
+            assert_eq!(message.len(), blocks * BLOCK_SIZE);
+            assert_eq!(hash.digest_from(hash.initial(), &message),
+                       expandable.state);
+        }
+    }
+
+    #[test]
+    fn second_preimage() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(20, 0xc0ffee);
+        let message = rng.gen_iter::<u8>().take(1024 * BLOCK_SIZE + 5)
+                         .collect::<Vec<u8>>();
+        let forged = super::second_preimage(&hash, &message, &mut rng);
+        assert!(forged != message);
+        assert_eq!(forged.len(), message.len());
+        //a brute-force second preimage would take around 2^20 compressions
+        assert!(hash.compressions() < 1 << 20, "{} compressions",
+                hash.compressions());
+        assert_eq!(hash.hash(&forged), hash.hash(&message));
+    }
+}
@@ -0,0 +1,136 @@
+//!A deliberately weak Merkle-Damgard hash, built on AES-128
+
+use std::cell::Cell;
+
+use aes;
+
+///Size of a message block, in bytes
+pub const BLOCK_SIZE: usize = 16;
+
+///Merkle-Damgard hash with a state of between 16 and 32 bits. The compression
+///function encrypts each message block under AES-128, using the current state
+///as the key, and truncates the result to the state size.
+pub struct MdHash {
+    bits: u32,
+    initial: u32,
+    compressions: Cell<u64>,
+}
+
+impl MdHash {
+    ///Creates a new MdHash with a `bits`-bit state and initial state `initial`.
+    ///Panics unless `bits` is between 16 and 32.
+    pub fn new(bits: u32, initial: u32) -> MdHash {
+        assert!((16..=32).contains(&bits), "MdHash state must be 16 to 32 bits");
+        MdHash {
+            bits,
+            initial: initial & mask(bits),
+            compressions: Cell::new(0),
+        }
+    }
+
+    ///Size of the state, in bits
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    ///Initial state
+    pub fn initial(&self) -> u32 {
+        self.initial
+    }
+
+    ///Number of times the compression function has been called
+    pub fn compressions(&self) -> u64 {
+        self.compressions.get()
+    }
+
+    ///Gives the state after digesting `block` in state `state`
+    pub fn compress(&self, state: u32, block: &[u8; BLOCK_SIZE]) -> u32 {
+        self.compressions.set(self.compressions.get() + 1);
+        let mut key = [0u8; 16];
+        key[0] = (state >> 24) as u8;
+        key[1] = (state >> 16) as u8;
+        key[2] = (state >> 8) as u8;
+        key[3] = state as u8;
+        let encrypted = aes::aes_ecb_encrypt(&block[..], &key);
+        let truncated = encrypted[..4].iter()
+                                      .fold(0u32, |acc, &b| acc << 8 | b as u32);
+        truncated & mask(self.bits)
+    }
+
+    ///Gives the state after digesting `message` in state `state`, without
+    ///padding. Panics unless `message` is a whole number of blocks.
+    pub fn digest_from(&self, state: u32, message: &[u8]) -> u32 {
+        assert!(message.len().is_multiple_of(BLOCK_SIZE), "Message is not block-aligned");
+        message.chunks(BLOCK_SIZE).fold(state, |state, chunk| {
+            let mut block = [0u8; BLOCK_SIZE];
+            block.copy_from_slice(chunk);
+            self.compress(state, &block)
+        })
+    }
+
+    ///Hashes `message`, including padding
+    pub fn hash(&self, message: &[u8]) -> u32 {
+        let padded = message.iter().cloned().chain(pad(message.len()))
+                            .collect::<Vec<u8>>();
+        self.digest_from(self.initial, &padded)
+    }
+}
+
+///Creates the Merkle-Damgard strengthened pad for a message of `message_len`
+///bytes: a 0x80 byte, zeros up to the block boundary, then a final block
+///holding the message length in bits
+pub fn pad(message_len: usize) -> Vec<u8> {
+    use std::iter::{once, repeat_n};
+    let nzeros = (BLOCK_SIZE - (message_len + 1) % BLOCK_SIZE) % BLOCK_SIZE;
+    let bit_len = message_len as u64 * 8;
+    once(0x80)
+    .chain(repeat_n(0, nzeros + BLOCK_SIZE - 8))
+    .chain((0..8).rev().map(|idx| (bit_len >> (8 * idx)) as u8))
+    .collect()
+}
+
+///Mask for the low `bits` bits of a u32
+fn mask(bits: u32) -> u32 {
+    if bits == 32 { !0 } else { (1 << bits) - 1 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MdHash;
+
+    #[test]
+    fn state_size() {
+        for bits in 16..33 {
+            let hash = MdHash::new(bits, 0xffffffff);
+            assert!((hash.initial() as u64) < 1u64 << bits);
+            assert!((hash.hash(b"yellow submarine") as u64) < 1u64 << bits);
+        }
+    }
+
+    #[test]
+    fn pad() {
+        assert_eq!(super::pad(0).len(), 32);
+        assert_eq!(super::pad(15).len(), 17);
+        assert_eq!(super::pad(16).len(), 32);
+        let padded = super::pad(17);
+        assert_eq!(padded.len(), 31);
+        assert_eq!(padded[0], 0x80);
+        assert_eq!(&padded[29..], &[0x00, 0x88]);
+    }
+
+    #[test]
+    fn hash_matches_digest() {
+        let hash = MdHash::new(24, 0x1337);
+        let message = b"yellow submarine";
+        let padded = message.iter().cloned().chain(super::pad(message.len()))
+                            .collect::<Vec<u8>>();
+        assert_eq!(hash.hash(message), hash.digest_from(0x1337, &padded));
+    }
+
+    #[test]
+    fn counts_compressions() {
+        let hash = MdHash::new(16, 0);
+        hash.hash(b"in the town where i was born");
+        assert_eq!(hash.compressions(), 3);
+    }
+}
@@ -0,0 +1,273 @@
+extern crate crypto;
+extern crate rand;
+
+mod aes;
+pub mod md_hash;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+
+use rand::Rng;
+
+use md_hash::{MdHash, BLOCK_SIZE};
+
+///A single message block
+pub type Block = [u8; BLOCK_SIZE];
+
+///Herding diamond structure. Each level pairs up the states of the level below
+///it, and holds the blocks which lead each pair to a common state, until a
+///single root state is reached.
+#[derive(Debug, PartialEq)]
+pub struct Diamond {
+    ///`levels[0]` holds the leaves. Entry `idx` of each level is a state and
+    ///the block which leads it to entry `idx / 2` of the next level.
+    levels: Vec<Vec<(u32, Block)>>,
+    ///State reached from every leaf
+    root: u32,
+}
+
+impl Diamond {
+    ///Builds a diamond structure with 2^`k` leaves for `hash`. Panics if `k`
+    ///is more than the number of bits in its state, since there wouldn't be
+    ///enough distinct leaves.
+    pub fn build<R: Rng>(hash: &MdHash, k: usize, rng: &mut R) -> Diamond {
+        assert!(k <= hash.bits() as usize,
+                "Diamond has more leaves than states");
+        //distinct random leaf states
+        let mut leaves = Vec::with_capacity(1 << k);
+        let mut seen = HashSet::with_capacity(1 << k);
+        while leaves.len() < 1 << k {
+            let state = rng.gen::<u32>() >> (32 - hash.bits());
+            if seen.insert(state) {
+                leaves.push(state);
+            }
+        }
+        let mut levels = Vec::with_capacity(k);
+        let mut states = leaves;
+        while states.len() > 1 {
+            let mut level = Vec::with_capacity(states.len());
+            let mut next = Vec::with_capacity(states.len() / 2);
+            for pair in states.chunks(2) {
+                let (a, b, state) =
+                    find_collision_between(hash, pair[0], pair[1], rng);
+                level.push((pair[0], a));
+                level.push((pair[1], b));
+                next.push(state);
+            }
+            levels.push(level);
+            states = next;
+        }
+        Diamond { levels, root: states[0] }
+    }
+
+    ///Number of levels, ie the number of blocks from a leaf to the root
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    ///Gives the hash to commit to, for messages with a `prefix_len`-byte
+    ///prefix. This is the hash of every message produced by `herd()`.
+    pub fn prediction(&self, hash: &MdHash, prefix_len: usize) -> u32 {
+        let len = message_len(prefix_len, self.depth());
+        hash.digest_from(self.root, &md_hash::pad(len))
+    }
+
+    ///Gives a message starting with `prefix` which hashes to
+    ///`prediction(hash, prefix.len())`. `prefix` is padded with spaces to a
+    ///whole number of blocks, and followed by a linking block and the path
+    ///through the diamond.
+    pub fn herd<R: Rng>(&self, hash: &MdHash, prefix: &[u8], rng: &mut R)
+            -> Vec<u8> {
+        let mut message = prefix.to_vec();
+        while !message.len().is_multiple_of(BLOCK_SIZE) {
+            message.push(b' ');
+        }
+        let state = hash.digest_from(hash.initial(), &message);
+
+        //find a block linking the prefix to one of the leaves
+        let leaves = self.levels.first().map(|level| {
+            level.iter().enumerate().map(|(idx, &(state, _))| (state, idx))
+                 .collect::<HashMap<u32, usize>>()
+        }).unwrap_or_default();
+        let mut idx = loop {
+            let mut link = [0u8; BLOCK_SIZE];
+            rng.fill_bytes(&mut link);
+            let next = hash.compress(state, &link);
+            if self.levels.is_empty() && next == self.root {
+                message.extend(link.iter().cloned());
+                return message;
+            }
+            if let Some(&idx) = leaves.get(&next) {
+                message.extend(link.iter().cloned());
+                break idx;
+            }
+        };
+
+        //follow the diamond to the root
+        for level in self.levels.iter() {
+            message.extend(level[idx].1.iter().cloned());
+            idx /= 2;
+        }
+        message
+    }
+
+    ///Writes the diamond structure to `writer`, so that it can be
+    ///precomputed once and reused
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&u32_bytes_be(self.levels.len() as u32))?;
+        for level in self.levels.iter() {
+            writer.write_all(&u32_bytes_be(level.len() as u32))?;
+            for &(state, block) in level.iter() {
+                writer.write_all(&u32_bytes_be(state))?;
+                writer.write_all(&block)?;
+            }
+        }
+        writer.write_all(&u32_bytes_be(self.root))
+    }
+
+    ///Reads a diamond structure written by `write_to()` from `reader`
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Diamond> {
+        let depth = read_u32(reader)? as usize;
+        if depth > 31 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "Diamond too deep"));
+        }
+        //nothing is reserved up front, since the lengths aren't to be trusted
+        //until the entries are actually there
+        let mut levels = Vec::new();
+        for level_idx in 0..depth {
+            let len = read_u32(reader)? as usize;
+            if len != 1 << (depth - level_idx) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "Malformed diamond level"));
+            }
+            let mut level = Vec::new();
+            for _ in 0..len {
+                let state = read_u32(reader)?;
+                let mut block = [0u8; BLOCK_SIZE];
+                reader.read_exact(&mut block)?;
+                level.push((state, block));
+            }
+            levels.push(level);
+        }
+        let root = read_u32(reader)?;
+        Ok(Diamond { levels, root })
+    }
+}
+
+///Length in bytes of a herded message with a `prefix_len`-byte prefix and a
+///diamond of depth `depth`
+fn message_len(prefix_len: usize, depth: usize) -> usize {
+    let prefix_blocks = prefix_len.div_ceil(BLOCK_SIZE);
+    (prefix_blocks + 1 + depth) * BLOCK_SIZE
+}
+
+///Finds blocks `a` and `b` such that digesting `a` in state `state_a` gives
+///the same state as digesting `b` in state `state_b`. Returns the two blocks and
+///that state.
+pub fn find_collision_between<R: Rng>(hash: &MdHash, state_a: u32,
+                                      state_b: u32, rng: &mut R)
+        -> (Block, Block, u32) {
+    let mut seen_a = HashMap::new();
+    let mut seen_b = HashMap::new();
+    loop {
+        let mut block = [0u8; BLOCK_SIZE];
+        rng.fill_bytes(&mut block);
+        let next_a = hash.compress(state_a, &block);
+        if let Some(&other) = seen_b.get(&next_a) {
+            return (block, other, next_a);
+        }
+        seen_a.insert(next_a, block);
+        rng.fill_bytes(&mut block);
+        let next_b = hash.compress(state_b, &block);
+        if let Some(&other) = seen_a.get(&next_b) {
+            return (other, block, next_b);
+        }
+        seen_b.insert(next_b, block);
+    }
+}
+
+///Gives bytes in `word` in big-endian order
+fn u32_bytes_be(word: u32) -> [u8; 4] {
+    [(word >> 24) as u8, (word >> 16) as u8, (word >> 8) as u8, word as u8]
+}
+
+///Reads a big-endian u32 from `reader`
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes.iter().fold(0, |acc, &b| acc << 8 | b as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand;
+    use md_hash::MdHash;
+    use super::Diamond;
+
+    #[test]
+    fn herd() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0xbeef);
+        let diamond = Diamond::build(&hash, 6, &mut rng);
+        let prefix = b"Final scores: Red Sox 4, Yankees 2";
+        let prediction = diamond.prediction(&hash, prefix.len());
+        let message = diamond.herd(&hash, prefix, &mut rng);
+        assert!(message.starts_with(prefix));
+        assert_eq!(hash.hash(&message), prediction);
+    }
+
+    #[test]
+    fn herd_empty_diamond() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0xbeef);
+        let diamond = Diamond::build(&hash, 0, &mut rng);
+        let prediction = diamond.prediction(&hash, 3);
+        let message = diamond.herd(&hash, b"abc", &mut rng);
+        assert_eq!(hash.hash(&message), prediction);
+    }
+
+    #[test]
+    #[should_panic(expected="more leaves than states")]
+    fn build_too_deep() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0xbeef);
+        Diamond::build(&hash, 17, &mut rng);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0xbeef);
+        let diamond = Diamond::build(&hash, 4, &mut rng);
+        let mut serialized = Vec::new();
+        diamond.write_to(&mut serialized).unwrap();
+        let restored = Diamond::read_from(&mut &serialized[..]).unwrap();
+        assert_eq!(restored, diamond);
+        let message = restored.herd(&hash, b"Dewey defeats Truman", &mut rng);
+        assert_eq!(hash.hash(&message),
+                   diamond.prediction(&hash, b"Dewey defeats Truman".len()));
+    }
+
+    #[test]
+    fn read_truncated() {
+        let mut rng = rand::thread_rng();
+        let hash = MdHash::new(16, 0xbeef);
+        let diamond = Diamond::build(&hash, 2, &mut rng);
+        let mut serialized = Vec::new();
+        diamond.write_to(&mut serialized).unwrap();
+        serialized.pop();
+        assert!(Diamond::read_from(&mut &serialized[..]).is_err());
+    }
+
+    #[test]
+    fn read_huge_header() {
+        //claims 2^31 leaves, but stops after one
+        let mut serialized = vec![0, 0, 0, 31, 0x80, 0, 0, 0];
+        serialized.extend_from_slice(&[0; 20]);
+        assert!(Diamond::read_from(&mut &serialized[..]).is_err());
+        let too_deep = [0xff, 0xff, 0xff, 0xff];
+        assert_eq!(Diamond::read_from(&mut &too_deep[..]).unwrap_err().kind(),
+                   ::std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,132 @@
+//!Recovers a secret which is encrypted many times under fresh RC4 keys, using
+//!the biases of single bytes of RC4 keystream.
+
+extern crate rand;
+
+pub mod rc4;
+pub mod oracle;
+
+use std::thread;
+
+///A keystream byte which takes a particular value more often than it should
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bias {
+    ///Position in the keystream, counting from 0
+    pub index: usize,
+    pub value: u8,
+}
+
+///The 16th keystream byte leans towards 240
+pub const Z16: Bias = Bias { index: 15, value: 0xf0 };
+///The 32nd keystream byte leans towards 224
+pub const Z32: Bias = Bias { index: 31, value: 0xe0 };
+
+///Counts how often each ciphertext byte shows up at each of `indices`, over
+///`samples` encryptions of `request`, split between `threads` threads. Gives
+///one table of 256 counts per index.
+pub fn tally<F>(oracle: &F, request: &[u8], indices: &[usize], samples: usize,
+                threads: usize) -> Vec<Vec<u64>>
+        where F: Fn(&[u8]) -> Vec<u8> + Sync {
+    let threads = threads.max(1);
+    let partials = thread::scope(|scope| {
+        let handles = (0..threads).map(|thread_idx| {
+            let share = samples / threads
+                        + if thread_idx < samples % threads { 1 } else { 0 };
+            scope.spawn(move || {
+                let mut counts = vec![vec![0u64; 256]; indices.len()];
+                for _ in 0..share {
+                    let ciphertext = oracle(request);
+                    for (table, &idx) in counts.iter_mut().zip(indices.iter()) {
+                        table[ciphertext[idx] as usize] += 1;
+                    }
+                }
+                counts
+            })
+        }).collect::<Vec<_>>();
+        handles.into_iter().map(|handle| handle.join().unwrap())
+               .collect::<Vec<_>>()
+    });
+    let mut counts = vec![vec![0u64; 256]; indices.len()];
+    for partial in partials {
+        for (table, partial_table) in counts.iter_mut().zip(partial.iter()) {
+            for (count, partial_count) in table.iter_mut()
+                                               .zip(partial_table.iter()) {
+                *count += partial_count;
+            }
+        }
+    }
+    counts
+}
+
+///Gives the most likely plaintext byte, given `counts` of the ciphertext byte
+///where the keystream has `bias`
+pub fn most_likely(counts: &[u64], bias: Bias) -> u8 {
+    (0..256usize).max_by_key(|&byte| counts[byte ^ bias.value as usize])
+                 .unwrap() as u8
+}
+
+///Recovers the secret which `oracle` appends to each request, using `samples`
+///encryptions for each of 16 request lengths. Only the first 32 bytes of the
+///secret can be recovered. With `samples` below 2^24 or so, expect mistakes.
+pub fn recover_cookie<F>(oracle: &F, samples: usize, threads: usize) -> Vec<u8>
+        where F: Fn(&[u8]) -> Vec<u8> + Sync {
+    let cookie_len = oracle(b"").len().min(Z32.index + 1);
+    let mut cookie = vec![0u8; cookie_len];
+    for padding in 0..Z16.index + 1 {
+        //byte `idx` of the cookie sits at keystream index `padding + idx`, so
+        //Z16 covers bytes 0-15 and Z32 covers bytes 16-31
+        let biases = [Z16, Z32].iter().cloned().filter(|bias| {
+            bias.index - padding < cookie_len
+        }).collect::<Vec<Bias>>();
+        if biases.is_empty() {
+            continue;
+        }
+        let indices = biases.iter().map(|bias| bias.index)
+                            .collect::<Vec<usize>>();
+        let request = vec![b'A'; padding];
+        let counts = tally(oracle, &request, &indices, samples, threads);
+        for (bias, table) in biases.iter().zip(counts.iter()) {
+            cookie[bias.index - padding] = most_likely(table, *bias);
+        }
+    }
+    cookie
+}
+
+#[cfg(test)]
+mod tests {
+    use oracle::Oracle;
+    use super::Bias;
+
+    #[test]
+    fn tally() {
+        let oracle = |request: &[u8]| request.to_vec();
+        let counts = super::tally(&oracle, b"ab", &[0, 1], 1001, 3);
+        assert_eq!(counts[0][b'a' as usize], 1001);
+        assert_eq!(counts[1][b'b' as usize], 1001);
+        assert_eq!(counts[1].iter().sum::<u64>(), 1001);
+    }
+
+    #[test]
+    fn recover_with_z2() {
+        //the second keystream byte is 0 with twice the expected probability,
+        //which needs far fewer samples than Z16 or Z32
+        let z2 = Bias { index: 1, value: 0 };
+        let oracle = Oracle::controlled(b"SECRET");
+        let encrypt = |request: &[u8]| oracle.encrypt(request);
+        for padding in 0..2 {
+            let counts = super::tally(&encrypt, &vec![b'A'; padding], &[1],
+                                      1 << 15, 2);
+            assert_eq!(super::most_likely(&counts[0], z2),
+                       b"SECRET"[1 - padding]);
+        }
+    }
+
+    #[test] #[ignore]
+    fn recover_cookie() {
+        let oracle = Oracle::new();
+        let cookie = super::recover_cookie(&|request: &[u8]| {
+            oracle.encrypt(request)
+        }, 1 << 24, 8);
+        assert_eq!(&cookie[..], &b"BE SURE TO DRINK YOUR OVALTINE"[..]);
+    }
+}
@@ -0,0 +1,50 @@
+use rand;
+use rand::Rng;
+
+use rc4::Rc4;
+
+///"BE SURE TO DRINK YOUR OVALTINE", as given base64-encoded in the challenge
+const COOKIE: &[u8] = b"BE SURE TO DRINK YOUR OVALTINE";
+
+///Encrypts attacker-chosen requests followed by a secret cookie, under a fresh
+///random RC4 key each time
+pub struct Oracle {
+    cookie: Vec<u8>,
+}
+
+impl Oracle {
+    pub fn new() -> Oracle {
+        Oracle::controlled(COOKIE)
+    }
+
+    pub fn controlled(cookie: &[u8]) -> Oracle {
+        Oracle { cookie: cookie.to_vec() }
+    }
+
+    pub fn encrypt(&self, request: &[u8]) -> Vec<u8> {
+        let mut key = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut key);
+        let plaintext = request.iter().chain(self.cookie.iter()).cloned()
+                               .collect::<Vec<u8>>();
+        Rc4::new(&key).encrypt(&plaintext)
+    }
+}
+
+impl Default for Oracle {
+    fn default() -> Oracle {
+        Oracle::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Oracle;
+
+    #[test]
+    fn encrypt() {
+        let oracle = Oracle::controlled(b"secret");
+        assert_eq!(oracle.encrypt(b"").len(), 6);
+        assert_eq!(oracle.encrypt(b"AAAA").len(), 10);
+        assert!(oracle.encrypt(b"AAAA") != oracle.encrypt(b"AAAA"));
+    }
+}
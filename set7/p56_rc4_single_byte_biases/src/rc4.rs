@@ -0,0 +1,88 @@
+///RC4 keystream. Each byte of output is the next byte of keystream.
+pub struct Rc4 {
+    state: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    ///Runs the key schedule for `key`, which must be 1 to 256 bytes long
+    pub fn new(key: &[u8]) -> Rc4 {
+        assert!(!key.is_empty() && key.len() <= 256,
+                "RC4 keys are 1 to 256 bytes long");
+        let mut state = [0u8; 256];
+        for (idx, el) in state.iter_mut().enumerate() {
+            *el = idx as u8;
+        }
+        let mut j = 0u8;
+        for idx in 0..256 {
+            j = j.wrapping_add(state[idx]).wrapping_add(key[idx % key.len()]);
+            state.swap(idx, j as usize);
+        }
+        Rc4 { state, i: 0, j: 0 }
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Vec<u8> {
+        plaintext.iter().map(|b| b ^ self.next().unwrap()).collect()
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Vec<u8> {
+        self.encrypt(ciphertext)
+    }
+}
+
+impl Iterator for Rc4 {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        self.i = self.i.wrapping_add(1);
+        self.j = self.j.wrapping_add(self.state[self.i as usize]);
+        self.state.swap(self.i as usize, self.j as usize);
+        let idx = self.state[self.i as usize]
+                      .wrapping_add(self.state[self.j as usize]);
+        Some(self.state[idx as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rc4;
+
+    #[test]
+    fn keystream() {
+        //RFC 6229, 40-bit key
+        let rc4 = Rc4::new(&[0x01, 0x02, 0x03, 0x04, 0x05]);
+        let expected = [0xb2, 0x39, 0x63, 0x05, 0xf0, 0x3d, 0xc0, 0x27, 0xcc,
+                        0xc3, 0x52, 0x4a, 0x0a, 0x11, 0x18, 0xa8];
+        assert_eq!(rc4.take(16).collect::<Vec<u8>>(), expected);
+    }
+
+    #[test]
+    fn keystream_offset() {
+        //RFC 6229, 128-bit key, offset 240
+        let key = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+                   0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10];
+        let rc4 = Rc4::new(&key);
+        let expected = [0x06, 0x59, 0x02, 0xe4, 0xb6, 0x20, 0xf6, 0xcc, 0x36,
+                        0xc8, 0x58, 0x9f, 0x66, 0x43, 0x2f, 0x2b];
+        assert_eq!(rc4.skip(240).take(16).collect::<Vec<u8>>(), expected);
+    }
+
+    #[test]
+    fn encrypt() {
+        let mut rc4 = Rc4::new(b"Key");
+        assert_eq!(rc4.encrypt(b"Plaintext"),
+                   vec![0xbb, 0xf3, 0x16, 0xe8, 0xd9, 0x40, 0xaf, 0x0a, 0xd3]);
+        let mut rc4 = Rc4::new(b"Secret");
+        assert_eq!(rc4.encrypt(b"Attack at dawn"),
+                   vec![0x45, 0xa0, 0x1f, 0x64, 0x5f, 0xc3, 0x5b, 0x38, 0x35,
+                        0x52, 0x54, 0x4b, 0x9b, 0xf5]);
+    }
+
+    #[test]
+    fn identity() {
+        let plaintext = b"The first time Yossarian saw the chaplain, \
+                          he fell madly in love with him.".to_vec();
+        let encrypted = Rc4::new(b"Wiki").encrypt(&plaintext);
+        assert_eq!(Rc4::new(b"Wiki").decrypt(&encrypted), plaintext);
+    }
+}